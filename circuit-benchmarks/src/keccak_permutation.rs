@@ -59,12 +59,16 @@ impl<F: Field> Circuit<F> for KeccakRoundTestCircuit<F> {
             },
         )?;
 
+        let next_mixing = self
+            .next_mixing
+            .map(|next_mixing| config.assign_next_inputs(&mut layouter, &next_mixing))
+            .transpose()?;
         config.assign_all(
             &mut layouter,
             in_state,
             self.out_state,
             self.is_mixing,
-            self.next_mixing,
+            next_mixing.as_deref(),
         )?;
         Ok(())
     }
@@ -152,6 +156,18 @@ mod tests {
             .parse()
             .expect("Cannot parse DEGREE env var as u32");
 
+        // Report the layout next to the timings, to weigh columns against rows
+        let mut meta = ConstraintSystem::<Fr>::default();
+        KeccakRoundTestCircuit::<Fr>::configure(&mut meta);
+        println!(
+            "Keccak layout with degree = {}: {} advice, {} fixed, {} instance columns, {} lookups",
+            degree,
+            meta.num_advice_columns(),
+            meta.num_fixed_columns(),
+            meta.num_instance_columns(),
+            meta.lookups().len()
+        );
+
         let rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
@@ -202,4 +218,31 @@ mod tests {
         .unwrap();
         end_timer!(start3);
     }
+
+    #[test]
+    fn bench_rho_witness_precompute() {
+        use keccak256::permutation::rho_helpers::{
+            compute_rho_witnesses, compute_rho_witnesses_serial,
+        };
+        use num_bigint::BigUint;
+
+        const ITERATIONS: usize = 100;
+        let state: [BigUint; 25] = (0..25)
+            .map(|_| convert_b2_to_b13(rand::random()))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+
+        let start1 = start_timer!(|| format!("Serial Rho witnesses x{}", ITERATIONS));
+        for _ in 0..ITERATIONS {
+            compute_rho_witnesses_serial(&state);
+        }
+        end_timer!(start1);
+
+        let start2 = start_timer!(|| format!("Parallel Rho witnesses x{}", ITERATIONS));
+        for _ in 0..ITERATIONS {
+            compute_rho_witnesses(&state);
+        }
+        end_timer!(start2);
+    }
 }
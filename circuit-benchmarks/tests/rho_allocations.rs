@@ -0,0 +1,75 @@
+//! Times the Rho witnesses of a permutation and counts their allocations, with
+//! a fresh `RhoLane` per lane against one lane reset for each of them.
+//!
+//! The counting allocator is global to the binary, so it lives in its own test
+//! binary to leave the other benches alone.
+#![cfg(feature = "benches")]
+
+use ark_std::{end_timer, start_timer};
+use itertools::Itertools;
+use keccak256::{
+    arith_helpers::convert_b2_to_b13,
+    common::{LaneIndex, PERMUTATION},
+    permutation::rho_helpers::RhoLane,
+};
+use num_bigint::BigUint;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn bench_rho_witness_allocations() {
+    // The Rho witnesses of one permutation, a state per round
+    let states: Vec<Vec<BigUint>> = (0..PERMUTATION)
+        .map(|_| {
+            (0..25)
+                .map(|_| convert_b2_to_b13(rand::random()))
+                .collect_vec()
+        })
+        .collect();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start1 = start_timer!(|| "Fresh Rho lanes for a permutation");
+    for state in states.iter() {
+        for (lane, value) in LaneIndex::all().zip(state.iter()) {
+            RhoLane::new(value.clone(), lane.rotation()).get_full_witness();
+        }
+    }
+    end_timer!(start1);
+    println!(
+        "Fresh Rho lanes: {} allocations per permutation",
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations
+    );
+
+    let mut rho_lane = RhoLane::new(BigUint::default(), 0);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start2 = start_timer!(|| "Reset Rho lane for a permutation");
+    for state in states.iter() {
+        for (lane, value) in LaneIndex::all().zip(state.iter()) {
+            rho_lane.reset(value.clone(), lane.rotation());
+            rho_lane.get_full_witness();
+        }
+    }
+    end_timer!(start2);
+    println!(
+        "Reset Rho lane: {} allocations per permutation",
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations
+    );
+}
@@ -0,0 +1,69 @@
+//! Proves the lane conversions of a whole state with all the lanes packed in
+//! three advice columns, and spread over five groups of three columns.
+use criterion::{criterion_group, criterion_main, Criterion};
+use halo2_proofs::{
+    pairing::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem},
+    poly::commitment::Params,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use keccak256::{
+    arith_helpers::convert_b2_to_b13, gate_helpers::biguint_to_f,
+    permutation::lane_layout::LaneLayoutCircuit,
+};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+/// The base 13 to base 9 table has 13^4 rows, more than the conversions of
+/// either layout.
+const K: u32 = 15;
+
+fn bench_layout<const WIDTH: usize>(c: &mut Criterion, params: &Params<G1Affine>) {
+    let mut rng = XorShiftRng::seed_from_u64(WIDTH as u64);
+    let lanes = [(); 25].map(|_| biguint_to_f(&convert_b2_to_b13(rand::Rng::gen(&mut rng))));
+    let circuit = LaneLayoutCircuit::<Fr, WIDTH> { lanes };
+
+    let mut meta = ConstraintSystem::<Fr>::default();
+    LaneLayoutCircuit::<Fr, WIDTH>::configure(&mut meta);
+    println!(
+        "Lane layout of width {} with k = {}: {} advice, {} fixed columns, {} lookups",
+        WIDTH,
+        K,
+        meta.num_advice_columns(),
+        meta.num_fixed_columns(),
+        meta.lookups().len()
+    );
+
+    let mut group = c.benchmark_group(format!("lane layout of width {}", WIDTH));
+    group.sample_size(10);
+    // Key generation synthesizes the circuit without its witness
+    group.bench_function("keygen", |b| {
+        b.iter(|| keygen_pk(params, keygen_vk(params, &circuit).unwrap(), &circuit).unwrap())
+    });
+    let pk = keygen_pk(params, keygen_vk(params, &circuit).unwrap(), &circuit).unwrap();
+    group.bench_function("prove", |b| {
+        b.iter(|| {
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof(
+                params,
+                &pk,
+                &[circuit.clone()],
+                &[&[]],
+                &mut rng,
+                &mut transcript,
+            )
+            .unwrap();
+            transcript.finalize()
+        })
+    });
+    group.finish();
+}
+
+fn bench_lane_layouts(c: &mut Criterion) {
+    let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    bench_layout::<1>(c, &params);
+    bench_layout::<5>(c, &params);
+}
+
+criterion_group!(benches, bench_lane_layouts);
+criterion_main!(benches);
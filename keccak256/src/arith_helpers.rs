@@ -1,4 +1,5 @@
-use crate::common::State;
+use crate::circuit::padding::{HashVariant, LAST_PAD_BYTE};
+use crate::common::{State, NEXT_INPUTS_LANES};
 use eth_types::Field;
 use halo2_proofs::circuit::AssignedCell;
 use itertools::Itertools;
@@ -88,22 +89,47 @@ impl Clone for StateBigInt {
     }
 }
 
-pub fn convert_b2_to_b13(a: u64) -> Lane13 {
-    let mut lane13: BigUint = Zero::zero();
-    for i in 0..64 {
-        let bit = (a >> i) & 1;
-        lane13 += bit * BigUint::from(B13).pow(i);
+/// Encodes a dense lane in a sparse base.
+///
+/// The i-th bit of `value` becomes the i-th chunk (in little endian) of the
+/// sparse lane.
+pub fn dense_to_sparse(value: u64, base: u8) -> BigUint {
+    let bits = (0..64).map(|i| ((value >> i) & 1) as u8).collect_vec();
+    BigUint::from_radix_le(&bits, base.into()).unwrap_or_default()
+}
+
+/// Decodes a sparse lane back to the dense lane, the inverse of
+/// [`dense_to_sparse`].
+///
+/// Every chunk is expected to be a single bit. Chunks that come out of a gate
+/// (like the Theta sums or the Xi combinations) have to be mapped with
+/// [`convert_b13_coef`] or [`convert_b9_coef`] first.
+pub fn sparse_to_dense(sparse: &BigUint, base: u8) -> u64 {
+    let chunks = sparse.to_radix_le(base.into());
+    debug_assert!(chunks.len() <= 64, "sparse lane has more than 64 chunks");
+    debug_assert!(chunks.iter().all(|&x| x <= 1), "sparse chunk is not a bit");
+    chunks
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, &bit)| acc | (u64::from(bit) << i))
+}
+
+/// Decodes a sparse lane like [`sparse_to_dense`], returning `None` when it
+/// has more than 64 chunks or a chunk that is not a bit.
+pub fn try_sparse_to_dense(sparse: &BigUint, base: u8) -> Option<u64> {
+    let chunks = sparse.to_radix_le(base.into());
+    if chunks.len() > 64 || chunks.iter().any(|&x| x > 1) {
+        return None;
     }
-    lane13
+    Some(sparse_to_dense(sparse, base))
+}
+
+pub fn convert_b2_to_b13(a: u64) -> Lane13 {
+    dense_to_sparse(a, B13)
 }
 
 pub fn convert_b2_to_b9(a: u64) -> Lane9 {
-    let mut lane9: BigUint = Zero::zero();
-    for i in 0..64 {
-        let bit = (a >> i) & 1;
-        lane9 += bit * BigUint::from(B9).pow(i);
-    }
-    lane9
+    dense_to_sparse(a, B9)
 }
 
 /// Maps a sum of 12 bits to the XOR result of 12 bits.
@@ -154,6 +180,26 @@ pub fn convert_b13_lane_to_b9(x: Lane13, rot: u32) -> Lane9 {
     BigUint::from_radix_le(&rotated, B9.into()).unwrap_or_default()
 }
 
+/// Rotates the base 13 lane `x` like [`convert_b13_lane_to_b9`], but keeps it
+/// in base 13.
+///
+/// The special chunks are summed into the chunk at `rot`, their sum is still a
+/// count of bits, so the conversion of each chunk to its parity is left for
+/// later.
+pub fn rotate_b13_lane(x: Lane13, rot: u32) -> Lane13 {
+    let mut chunks = x.to_radix_le(B13.into());
+    chunks.resize(65, 0);
+    let special = chunks[0] + chunks[64];
+    let (left, right) = chunks[1..64].split_at(63 - rot as usize);
+    let rotated = right
+        .iter()
+        .chain(std::iter::once(&special))
+        .chain(left.iter())
+        .copied()
+        .collect_vec();
+    BigUint::from_radix_le(&rotated, B13.into()).unwrap_or_default()
+}
+
 pub fn convert_lane<F>(lane: BigUint, from_base: u8, to_base: u8, coef_transform: F) -> BigUint
 where
     F: Fn(u8) -> u8,
@@ -202,6 +248,128 @@ pub fn state_to_biguint<F: Field, const N: usize>(state: [F; N]) -> StateBigInt
     }
 }
 
+/// Returns the index in the circuit state of the `i`-th lane of an input
+/// block.
+///
+/// The circuit state is laid out as `5 * x + y`, while the lanes of a block
+/// fill the state in the spec order `x + 5 * y`. Placing the `i`-th lane at
+/// column `i` would absorb it into the lane `(i / 5, i % 5)`, which is only
+/// the spec lane for the lanes on the diagonal, so every digest would differ
+/// from the reference one. The mapping is a transpose, its own inverse.
+pub fn block_lane_index(i: usize) -> usize {
+    debug_assert!(i < 25);
+    5 * (i % 5) + i / 5
+}
+
+/// How the 8 bytes of a lane are packed in a `u64`.
+///
+/// The Keccak spec packs lanes in little endian, which is the default. The
+/// circuit always works on the spec lanes, the byte order only changes how the
+/// lanes are read from the input and written to the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl Default for ByteOrder {
+    fn default() -> Self {
+        ByteOrder::LittleEndian
+    }
+}
+
+impl ByteOrder {
+    pub fn lane_to_bytes(self, lane: u64) -> [u8; 8] {
+        match self {
+            ByteOrder::LittleEndian => lane.to_le_bytes(),
+            ByteOrder::BigEndian => lane.to_be_bytes(),
+        }
+    }
+
+    pub fn lane_from_bytes(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            ByteOrder::LittleEndian => u64::from_le_bytes(bytes),
+            ByteOrder::BigEndian => u64::from_be_bytes(bytes),
+        }
+    }
+
+    /// Converts a lane packed in this order to the spec lane, or back, since
+    /// swapping the bytes is its own inverse.
+    pub fn to_spec_lane(self, lane: u64) -> u64 {
+        match self {
+            ByteOrder::LittleEndian => lane,
+            ByteOrder::BigEndian => lane.swap_bytes(),
+        }
+    }
+}
+
+/// Places the lanes of an input block, given in binary, in a `State`.
+pub fn next_inputs_to_state<F: Field>(next_inputs: &[F]) -> State {
+    let mut state = [[0u64; 5]; 5];
+    for (i, lane) in next_inputs.iter().enumerate() {
+        let bytes = lane.to_repr();
+        debug_assert!(bytes[8..32] == vec![0u8; 24]);
+        let mut arr = [0u8; 8];
+        arr.copy_from_slice(&bytes[0..8]);
+        state[i % 5][i / 5] = u64::from_le_bytes(arr);
+    }
+    state
+}
+
+/// Splits a stream of bytes in the padded blocks of a [`HashVariant`], with
+/// the lanes of each block packed in little endian as in the spec.
+///
+/// Only one block is buffered at a time. The length of the input is not needed
+/// upfront: the padding goes in the block where the bytes run out, or in an
+/// extra block when the bytes fill the last one exactly.
+pub struct PaddedBlocks<I> {
+    bytes: I,
+    first_pad_byte: u8,
+    padded: bool,
+}
+
+impl<I: Iterator<Item = u8>> PaddedBlocks<I> {
+    /// Pads the `bytes` for Keccak-256.
+    pub fn new(bytes: I) -> Self {
+        Self::with_variant(bytes, HashVariant::Keccak256)
+    }
+
+    /// Pads the `bytes` with the padding rule of `variant`.
+    pub fn with_variant(bytes: I, variant: HashVariant) -> Self {
+        Self {
+            bytes,
+            first_pad_byte: variant.first_pad_byte(),
+            padded: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for PaddedBlocks<I> {
+    type Item = [u64; NEXT_INPUTS_LANES];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.padded {
+            return None;
+        }
+        let mut block = [0u8; 8 * NEXT_INPUTS_LANES];
+        let mut len = 0;
+        for (byte, input) in block.iter_mut().zip(&mut self.bytes) {
+            *byte = input;
+            len += 1;
+        }
+        if len < block.len() {
+            block[len] ^= self.first_pad_byte;
+            block[block.len() - 1] ^= LAST_PAD_BYTE;
+            self.padded = true;
+        }
+        let mut lanes = [0u64; NEXT_INPUTS_LANES];
+        for (lane, bytes) in lanes.iter_mut().zip(block.chunks(8)) {
+            *lane = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Some(lanes)
+    }
+}
+
 pub fn state_to_state_bigint<F: Field, const N: usize>(state: [F; N]) -> State {
     let mut matrix = [[0u64; 5]; 5];
 
@@ -262,6 +430,21 @@ pub fn f_from_radix_be<F: Field>(buf: &[u8], base: u8) -> F {
 mod tests {
     use super::*;
     use num_bigint::BigUint;
+
+    #[test]
+    fn test_block_lane_index() {
+        // The spec lane `x + 5 * y` goes to the circuit column `5 * x + y`
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            assert_eq!(block_lane_index(x + 5 * y), 5 * x + y);
+        }
+        for i in 0..25 {
+            assert_eq!(block_lane_index(block_lane_index(i)), i);
+        }
+        // The lanes `(1, 0)` and `(0, 1)` of a block
+        assert_eq!(block_lane_index(1), 5);
+        assert_eq!(block_lane_index(5), 1);
+    }
+
     #[test]
     fn test_convert_b13_lane_to_b9() {
         // the number 1 is chosen that `convert_b13_coef` has no effect
@@ -281,4 +464,122 @@ mod tests {
             BigUint::from_radix_le(&b, B9.into()).unwrap_or_default()
         );
     }
+
+    #[test]
+    fn test_rotate_b13_lane() {
+        use crate::common::ROTATION_CONSTANTS;
+
+        for &rotation in ROTATION_CONSTANTS.iter().flatten() {
+            let value = rand::random::<u64>();
+            // Without special chunks, it is `rotl` on the sparse lane
+            assert_eq!(
+                rotate_b13_lane(convert_b2_to_b13(value), rotation),
+                convert_b2_to_b13(value.rotate_left(rotation))
+            );
+
+            // With them, converting the chunks afterwards gives the same lane
+            // as converting while rotating
+            let mut chunks = (0..65).map(|_| rand::random::<u8>() % 7).collect_vec();
+            chunks[0] = 5;
+            chunks[64] = 6;
+            let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+            let rotated = rotate_b13_lane(lane.clone(), rotation);
+            let mut rotated_chunks = rotated.to_radix_le(B13.into());
+            rotated_chunks.resize(64, 0);
+            assert_eq!(rotated_chunks[rotation as usize], 11);
+            let converted = rotated_chunks
+                .iter()
+                .map(|&chunk| convert_b13_coef(chunk))
+                .collect_vec();
+            assert_eq!(
+                BigUint::from_radix_le(&converted, B9.into()).unwrap(),
+                convert_b13_lane_to_b9(lane, rotation)
+            );
+        }
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        assert_eq!(ByteOrder::default(), ByteOrder::LittleEndian);
+        assert_eq!(
+            ByteOrder::LittleEndian.lane_from_bytes(bytes),
+            0x0807_0605_0403_0201
+        );
+        assert_eq!(
+            ByteOrder::BigEndian.lane_from_bytes(bytes),
+            0x0102_0304_0506_0708
+        );
+        for order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let lane = order.lane_from_bytes(bytes);
+            assert_eq!(order.lane_to_bytes(lane), bytes);
+            // The spec lane always holds the bytes in little endian
+            assert_eq!(order.to_spec_lane(lane), 0x0807_0605_0403_0201);
+            assert_eq!(order.to_spec_lane(order.to_spec_lane(lane)), lane);
+        }
+    }
+
+    #[test]
+    fn test_dense_sparse_round_trip() {
+        for base in [B13, B9] {
+            for value in [0, 1, u64::MAX, 1 << 63]
+                .into_iter()
+                .chain((0..100).map(|_| rand::random::<u64>()))
+            {
+                let sparse = dense_to_sparse(value, base);
+                assert_eq!(sparse_to_dense(&sparse, base), value);
+            }
+        }
+        assert_eq!(dense_to_sparse(0b101, B13), BigUint::from(170u64));
+        assert_eq!(dense_to_sparse(0b101, B9), BigUint::from(82u64));
+    }
+
+    #[test]
+    fn test_padded_blocks() {
+        use crate::reference::{keccak256, keccak_f1600};
+
+        let rate = 8 * NEXT_INPUTS_LANES;
+        let streamed_digest = |input: &[u8]| {
+            let mut state = [0u64; 25];
+            for block in PaddedBlocks::new(input.iter().copied()) {
+                for (lane, word) in state.iter_mut().zip(block.iter()) {
+                    *lane ^= word;
+                }
+                state = keccak_f1600(state);
+            }
+            state[..4]
+                .iter()
+                .flat_map(|lane| lane.to_le_bytes())
+                .collect::<Vec<_>>()
+        };
+        for len in [0, 1, rate - 1, rate, rate + 1, 2 * rate, 1 << 20] {
+            let input: Vec<u8> = (0..len).map(|_| rand::random::<u8>()).collect();
+            assert_eq!(
+                PaddedBlocks::new(input.iter().copied()).count(),
+                len / rate + 1
+            );
+            assert_eq!(streamed_digest(&input), keccak256(&input).to_vec());
+        }
+    }
+
+    #[test]
+    fn test_padded_blocks_variant() {
+        let rate = 8 * NEXT_INPUTS_LANES;
+        for (variant, first) in [
+            (HashVariant::Keccak256, 0x01u64),
+            (HashVariant::Sha3_256, 0x06),
+        ] {
+            // The empty input pads a single block
+            let blocks = PaddedBlocks::with_variant(std::iter::empty(), variant).collect_vec();
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0][0], first);
+            assert_eq!(blocks[0][NEXT_INPUTS_LANES - 1], 0x80 << 56);
+
+            // One byte short of a block, both pad bytes go in the last byte
+            let input = vec![0u8; rate - 1];
+            let blocks = PaddedBlocks::with_variant(input.into_iter(), variant).collect_vec();
+            assert_eq!(blocks.len(), 1);
+            assert_eq!(blocks[0][NEXT_INPUTS_LANES - 1], (first | 0x80) << 56);
+        }
+    }
 }
@@ -1,3 +1,7 @@
+pub mod byte_to_sparse;
+pub mod field_hash;
+pub mod hash;
+pub mod keys;
 pub mod padding;
 pub mod word_builder;
 
@@ -0,0 +1,249 @@
+use crate::arith_helpers::dense_to_sparse;
+use crate::gate_helpers::biguint_to_f;
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    poly::Rotation,
+};
+use std::marker::PhantomData;
+
+/// Converts the 8 bytes of a lane to a sparse lane, whose bits are digits in
+/// `base`.
+///
+/// Base 13 gives the lanes of the input state of a permutation, base 9 the
+/// lanes of a block for the absorb. Each byte is looked up in a 256 rows table
+/// along with its sparse value, which also range checks the byte. The lane is
+/// the running sum of the sparse bytes from the most significant one, each
+/// shifted by `base**8`. The bytes are in the lane order of the spec, little
+/// endian.
+#[derive(Debug, Clone)]
+pub struct ByteToSparseConfig<F> {
+    q_lookup: Selector,
+    q_running_sum: Selector,
+    byte: Column<Advice>,
+    sparse: Column<Advice>,
+    acc: Column<Advice>,
+    table_byte: TableColumn,
+    table_sparse: TableColumn,
+    base: u8,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> ByteToSparseConfig<F> {
+    /// Side effect: the three columns are equality enabled
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advices: [Column<Advice>; 3],
+        base: u8,
+    ) -> Self {
+        let q_lookup = meta.complex_selector();
+        let q_running_sum = meta.selector();
+        let [byte, sparse, acc] = advices;
+        let table_byte = meta.lookup_table_column();
+        let table_sparse = meta.lookup_table_column();
+
+        meta.enable_equality(byte);
+        meta.enable_equality(sparse);
+        meta.enable_equality(acc);
+
+        meta.lookup("byte -> sparse", |meta| {
+            let q_lookup = meta.query_selector(q_lookup);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let sparse = meta.query_advice(sparse, Rotation::cur());
+            vec![
+                (q_lookup.clone() * byte, table_byte),
+                (q_lookup * sparse, table_sparse),
+            ]
+        });
+        meta.create_gate("sparse byte running sum", |meta| {
+            let q_running_sum = meta.query_selector(q_running_sum);
+            let sparse = meta.query_advice(sparse, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            let shift = F::from(base as u64).pow(&[8, 0, 0, 0]);
+            vec![q_running_sum * (acc - acc_prev * shift - sparse)]
+        });
+
+        Self {
+            q_lookup,
+            q_running_sum,
+            byte,
+            sparse,
+            acc,
+            table_byte,
+            table_sparse,
+            base,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "byte -> sparse",
+            |mut table| {
+                for byte in 0..256u64 {
+                    let offset = byte as usize;
+                    table.assign_cell(|| "byte", self.table_byte, offset, || Ok(F::from(byte)))?;
+                    table.assign_cell(
+                        || "sparse",
+                        self.table_sparse,
+                        offset,
+                        || Ok(biguint_to_f(&dense_to_sparse(byte, self.base))),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Returns the sparse lane of `bytes`, the lane
+    /// `u64::from_le_bytes(bytes)`.
+    pub fn assign_lane(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[AssignedCell<F, F>; 8],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "byte to sparse lane",
+            |mut region| {
+                let mut acc = F::zero();
+                let shift = F::from(self.base as u64).pow(&[8, 0, 0, 0]);
+                let mut acc_cell = None;
+                // From the most significant byte
+                for (offset, byte) in bytes.iter().rev().enumerate() {
+                    self.q_lookup.enable(&mut region, offset)?;
+                    byte.copy_advice(|| "byte", &mut region, self.byte, offset)?;
+                    // A byte that doesn't fit is left for the lookup to reject
+                    let sparse = byte
+                        .value()
+                        .map(|byte| {
+                            biguint_to_f(&dense_to_sparse(byte.get_lower_128() as u64, self.base))
+                        })
+                        .unwrap_or_default();
+                    let sparse_cell = region.assign_advice(
+                        || "sparse byte",
+                        self.sparse,
+                        offset,
+                        || Ok(sparse),
+                    )?;
+                    acc = acc * shift + sparse;
+                    let cell =
+                        region.assign_advice(|| "sparse lane", self.acc, offset, || Ok(acc))?;
+                    if offset == 0 {
+                        region.constrain_equal(cell.cell(), sparse_cell.cell())?;
+                    } else {
+                        self.q_running_sum.enable(&mut region, offset)?;
+                    }
+                    acc_cell = Some(cell);
+                }
+                Ok(acc_cell.unwrap())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith_helpers::{convert_b2_to_b13, convert_b2_to_b9, B13, B9};
+    use crate::gate_helpers::dense_u64;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner, dev::MockProver, pairing::bn256::Fr as Fp, plonk::Circuit,
+    };
+    use std::convert::TryInto;
+
+    #[derive(Default)]
+    struct MyCircuit<F, const BASE: u8> {
+        bytes: [F; 8],
+        expected: F,
+    }
+
+    impl<F: Field, const BASE: u8> Circuit<F> for MyCircuit<F, BASE> {
+        type Config = ByteToSparseConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let advices = [(); 3].map(|_| meta.advice_column());
+            ByteToSparseConfig::configure(meta, advices, BASE)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            let bytes = layouter.assign_region(
+                || "bytes",
+                |mut region| {
+                    let bytes = self
+                        .bytes
+                        .iter()
+                        .enumerate()
+                        .map(|(offset, &byte)| {
+                            region.assign_advice(|| "byte", config.byte, offset, || Ok(byte))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok(bytes.try_into().unwrap())
+                },
+            )?;
+            let lane = config.assign_lane(&mut layouter, &bytes)?;
+            if lane.value().is_some() {
+                assert_eq!(lane.value(), Some(&self.expected));
+                // And back to the dense lane
+                let dense: u64 = self
+                    .bytes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| (byte.get_lower_128() as u64) << (8 * i))
+                    .sum();
+                assert_eq!(dense_u64(&lane, BASE), Some(dense));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_byte_to_sparse() {
+        for bytes in [
+            [0u8; 8],
+            [0xff; 8],
+            [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            rand::random(),
+        ] {
+            let lane = u64::from_le_bytes(bytes);
+            let circuit = MyCircuit::<Fp, B13> {
+                bytes: bytes.map(|byte| Fp::from(byte as u64)),
+                expected: biguint_to_f(&convert_b2_to_b13(lane)),
+            };
+            let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let circuit = MyCircuit::<Fp, B9> {
+                bytes: bytes.map(|byte| Fp::from(byte as u64)),
+                expected: biguint_to_f(&convert_b2_to_b9(lane)),
+            };
+            let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_byte_out_of_range() {
+        // 256 has the 9th bit set, its sparse value would overlap the next
+        // byte
+        let mut bytes = [Fp::zero(); 8];
+        bytes[3] = Fp::from(256);
+        let circuit = MyCircuit::<Fp, B13> {
+            bytes,
+            expected: biguint_to_f(&dense_to_sparse(1 << 32, B13)),
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
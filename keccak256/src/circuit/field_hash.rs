@@ -0,0 +1,294 @@
+//! Keccak-256 of a field element, the 32 byte word most EVM circuits hash.
+use crate::circuit::hash::Keccak256Config;
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+/// The bytes of a word
+const WORD_BYTES: usize = 32;
+
+/// Hashes an assigned field element as a 32 byte big endian word, as the EVM
+/// does for a stack word.
+///
+/// The element is decomposed in bytes that are recomposed into the element.
+/// The bytes are hashed with [`Keccak256Config::hash_bytes`], whose lookups
+/// range check them. The word fits a single padded block.
+///
+/// The bytes are also checked to be below the modulus `p`, by adding them
+/// byte by byte to range checked bytes `d` with the sum `p - 1`, so the word
+/// is the canonical one of the element.
+#[derive(Debug, Clone)]
+pub struct FieldHashConfig<F: Field> {
+    keccak: Keccak256Config<F>,
+    q_word: Selector,
+    q_canonical: Selector,
+    byte: Column<Advice>,
+    word: Column<Advice>,
+    diff: Column<Advice>,
+    carry: Column<Advice>,
+    modulus: Column<Advice>,
+}
+
+impl<F: Field> FieldHashConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let keccak = Keccak256Config::configure(meta);
+        let [byte, word, diff, carry, modulus] = [(); 5].map(|_| {
+            let column = meta.advice_column();
+            meta.enable_equality(column);
+            column
+        });
+        let q_word = meta.selector();
+        let q_canonical = meta.selector();
+        meta.create_gate("word from big endian bytes", |meta| {
+            let q_word = meta.query_selector(q_word);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let word_prev = meta.query_advice(word, Rotation::prev());
+            let word = meta.query_advice(word, Rotation::cur());
+            vec![q_word * (word - word_prev * F::from(256) - byte)]
+        });
+        // Each byte of `p - 1` is the byte plus the diff and the carry of the
+        // less significant byte, minus the carry out of it
+        meta.create_gate("bytes below the modulus", |meta| {
+            let q_canonical = meta.query_selector(q_canonical);
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let diff = meta.query_advice(diff, Rotation::cur());
+            let modulus = meta.query_advice(modulus, Rotation::cur());
+            let carry_in = meta.query_advice(carry, Rotation::next());
+            let carry = meta.query_advice(carry, Rotation::cur());
+            vec![
+                q_canonical.clone()
+                    * (byte + diff + carry_in - modulus - carry.clone() * F::from(256)),
+                q_canonical * carry.clone() * (Expression::Constant(F::one()) - carry),
+            ]
+        });
+        Self {
+            keccak,
+            q_word,
+            q_canonical,
+            byte,
+            word,
+            diff,
+            carry,
+            modulus,
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.keccak.load(layouter)
+    }
+
+    /// Returns the 4 digest lanes of the Keccak-256 of `value` as a 32 byte
+    /// big endian word, each lane the `u64` of its 8 bytes in little endian.
+    pub fn hash_field_element(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        // The repr is little endian
+        let mut word = value
+            .value()
+            .map(|value| value.to_repr())
+            .unwrap_or_default();
+        word.reverse();
+        self.hash_word(layouter, value, word)
+    }
+
+    /// Hashes the big endian `word` bound to `value`.
+    fn hash_word(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        word: [u8; WORD_BYTES],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let (bytes, diffs) = self.assign_bytes(layouter, value, word)?;
+        self.keccak.range_check_bytes(layouter, &diffs)?;
+        self.keccak.hash_bytes(layouter, &bytes)
+    }
+
+    /// Witnesses the bytes of `word` from the most significant one and
+    /// constrains their running sum to `value`. Returns the bytes and the
+    /// bytes of `p - 1 - word`, which the caller range checks.
+    fn assign_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        value: &AssignedCell<F, F>,
+        word: [u8; WORD_BYTES],
+    ) -> Result<(Vec<AssignedCell<F, F>>, Vec<AssignedCell<F, F>>), Error> {
+        let mut p_minus_one = (-F::one()).to_repr();
+        p_minus_one.reverse();
+        // From the least significant byte, a carry set when the word exceeds
+        // the modulus
+        let mut diffs = [0u8; WORD_BYTES];
+        let mut carries = [false; WORD_BYTES + 1];
+        for i in (0..WORD_BYTES).rev() {
+            let sum = word[i] as u16 + carries[i + 1] as u16;
+            carries[i] = sum > p_minus_one[i] as u16;
+            diffs[i] = (p_minus_one[i] as u16 + 256 * carries[i] as u16 - sum) as u8;
+        }
+        layouter.assign_region(
+            || "Field element bytes",
+            |mut region| {
+                let mut acc = F::zero();
+                let mut acc_cell = None;
+                let bytes = word
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, &byte)| {
+                        let byte = F::from(byte as u64);
+                        let byte_cell =
+                            region.assign_advice(|| "byte", self.byte, offset, || Ok(byte))?;
+                        acc = acc * F::from(256) + byte;
+                        let cell =
+                            region.assign_advice(|| "word", self.word, offset, || Ok(acc))?;
+                        if offset == 0 {
+                            region.constrain_equal(cell.cell(), byte_cell.cell())?;
+                        } else {
+                            self.q_word.enable(&mut region, offset)?;
+                        }
+                        acc_cell = Some(cell);
+                        Ok(byte_cell)
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                region.constrain_equal(acc_cell.unwrap().cell(), value.cell())?;
+
+                let diffs = (0..WORD_BYTES)
+                    .map(|offset| {
+                        self.q_canonical.enable(&mut region, offset)?;
+                        region.assign_advice_from_constant(
+                            || "modulus byte",
+                            self.modulus,
+                            offset,
+                            F::from(p_minus_one[offset] as u64),
+                        )?;
+                        let carry = region.assign_advice(
+                            || "carry",
+                            self.carry,
+                            offset,
+                            || Ok(F::from(carries[offset])),
+                        )?;
+                        // The word can't overflow
+                        if offset == 0 {
+                            region.constrain_constant(carry.cell(), F::zero())?;
+                        }
+                        region.assign_advice(
+                            || "diff",
+                            self.diff,
+                            offset,
+                            || Ok(F::from(diffs[offset] as u64)),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                // No carry into the least significant byte
+                region.assign_advice_from_constant(
+                    || "carry",
+                    self.carry,
+                    WORD_BYTES,
+                    F::zero(),
+                )?;
+                Ok((bytes, diffs))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference::keccak256;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        pairing::{bn256::Fr as Fp, group::ff::PrimeField},
+        plonk::Circuit,
+    };
+    use itertools::Itertools;
+    use num_bigint::BigUint;
+
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        value: F,
+        /// Hashed instead of the canonical word of `value` when set
+        word: Option<[u8; WORD_BYTES]>,
+        digest: Vec<F>,
+    }
+
+    impl<F: Field> Circuit<F> for MyCircuit<F> {
+        type Config = FieldHashConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            FieldHashConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            let value = layouter.assign_region(
+                || "value",
+                |mut region| region.assign_advice(|| "value", config.word, 0, || Ok(self.value)),
+            )?;
+            let digest = match self.word {
+                Some(word) => config.hash_word(&mut layouter, &value, word)?,
+                None => config.hash_field_element(&mut layouter, &value)?,
+            };
+            if value.value().is_some() {
+                let digest = digest
+                    .iter()
+                    .map(|lane| *lane.value().unwrap())
+                    .collect_vec();
+                assert_eq!(digest, self.digest);
+            }
+            Ok(())
+        }
+    }
+
+    fn digest(word: &[u8]) -> Vec<Fp> {
+        keccak256(word)
+            .chunks(8)
+            .map(|lane| Fp::from(u64::from_le_bytes(lane.try_into().unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn test_hash_field_element() {
+        let mut word = [0u8; WORD_BYTES];
+        for (i, byte) in word.iter_mut().enumerate().skip(1) {
+            *byte = i as u8;
+        }
+        let mut repr = word;
+        repr.reverse();
+        let circuit = MyCircuit::<Fp> {
+            value: Fp::from_repr(repr).unwrap(),
+            word: None,
+            digest: digest(&word),
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_non_canonical_word() {
+        // The word of `x + p` recomposes into `x` too
+        let value = Fp::from(5);
+        let p = BigUint::from_bytes_le(&(-Fp::one()).to_repr()) + 1u64;
+        let word = (BigUint::from_bytes_le(&value.to_repr()) + p).to_bytes_be();
+        let word: [u8; WORD_BYTES] = word.try_into().unwrap();
+        let circuit = MyCircuit::<Fp> {
+            value,
+            word: Some(word),
+            digest: digest(&word),
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
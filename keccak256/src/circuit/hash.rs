@@ -0,0 +1,808 @@
+use crate::arith_helpers::{ByteOrder, B9};
+use crate::circuit::{
+    byte_to_sparse::ByteToSparseConfig,
+    padding::{HashVariant, PaddingConfig, BYTES_LEN_17_WORDS},
+};
+use crate::common::NEXT_INPUTS_LANES;
+use crate::permutation::{
+    circuit::{estimate_rows, lane_conversion_rows, padded_blocks, KeccakFConfig},
+    squeeze::{SqueezeConfig, DIGEST_LANES},
+};
+use crate::reference::keccak256_with_state;
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use std::convert::TryInto;
+use std::iter;
+use std::marker::PhantomData;
+
+#[derive(Clone, Debug)]
+pub struct Keccak256Config<F: Field> {
+    keccak_f: KeccakFConfig<F>,
+    padding: PaddingConfig<F>,
+    to_sparse: ByteToSparseConfig<F>,
+    squeeze: SqueezeConfig<F>,
+    instance: Column<Instance>,
+}
+
+/// Proves the Keccak-256 digest of an input.
+///
+/// The input bytes are padded in blocks of the rate by [`PaddingConfig`] and
+/// each block is converted to base 9 lanes, which range checks its bytes. The
+/// first block is absorbed into the initial state and every other block at
+/// the end of the permutation before it. The 4 lanes of the digest are
+/// squeezed out of the last permutation. The digest and the input are the
+/// instance values, see [`Keccak256Circuit::instance`].
+///
+/// The input length is part of the layout, so keys are only valid for inputs
+/// of the same length.
+///
+/// The sponge starts from the zero state unless another one is set with
+/// [`Keccak256Circuit::with_initial_state`].
+#[derive(Default)]
+pub struct Keccak256Circuit<F> {
+    input: Vec<u8>,
+    initial_state: [u64; 25],
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> Keccak256Circuit<F> {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input,
+            initial_state: [0; 25],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Starts the sponge from `state`, in the spec order `x + 5 * y`, as keyed
+    /// and duplex constructions do. The state is fixed in the circuit, so it
+    /// is part of the keys.
+    pub fn with_initial_state(mut self, state: [u64; 25]) -> Self {
+        self.initial_state = state;
+        self
+    }
+
+    /// Returns the smallest `k` the circuit fits in, for its input length.
+    ///
+    /// This is [`estimate_rows`] for the input length plus the rows of the
+    /// input, its padding and the squeeze of the digest. The lookup tables
+    /// take `9^5 = 59049` rows, which fit in `2^16`, but a single permutation
+    /// already takes more than `2^16` rows, so even the empty input needs
+    /// `k = 17`.
+    pub fn min_k(&self) -> u32 {
+        let input_len = self.input.len();
+        // The input bytes, then the flag, the absorb and the base conversion
+        // of the first block into the initial state
+        let input_rows = (input_len + 24) / 25 + 1 + 3 + 25 * lane_conversion_rows();
+        // The padding constants and region, and the conversion of the lanes
+        let block_rows = 1 + BYTES_LEN_17_WORDS + NEXT_INPUTS_LANES * 8;
+        // The squeeze flag and the conversion of the digest lanes
+        let squeeze_rows = 1 + DIGEST_LANES * lane_conversion_rows();
+        let rows = estimate_rows(input_len)
+            + input_rows
+            + padded_blocks(input_len) * block_rows
+            + squeeze_rows;
+        (0..u32::BITS).find(|k| 1 << k >= rows).unwrap()
+    }
+
+    /// The instance values of the circuit: the lanes of the digest in the
+    /// spec order, each as the `u64` of its 8 bytes in little endian, then
+    /// the bytes of the input.
+    pub fn instance(&self) -> Vec<F> {
+        keccak256_with_state(self.initial_state, &self.input)
+            .chunks(8)
+            .map(|bytes| F::from(u64::from_le_bytes(bytes.try_into().unwrap())))
+            .chain(self.input.iter().map(|&byte| F::from(byte as u64)))
+            .collect()
+    }
+}
+
+impl<F: Field> Keccak256Config<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let keccak_f = KeccakFConfig::configure(meta);
+        let padding = PaddingConfig::configure(meta, HashVariant::Keccak256);
+        let to_sparse =
+            ByteToSparseConfig::configure(meta, keccak_f.state[0..3].try_into().unwrap(), B9);
+        let squeeze = SqueezeConfig::configure(
+            meta,
+            keccak_f.from_b9_table(),
+            keccak_f.state,
+            keccak_f.constant(),
+            NEXT_INPUTS_LANES,
+            ByteOrder::default(),
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        Self {
+            keccak_f,
+            padding,
+            to_sparse,
+            squeeze,
+            instance,
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.keccak_f.load(layouter)?;
+        self.to_sparse.load(layouter)
+    }
+
+    /// The 25 advice columns of the state, lane `(x, y)` at `5 * x + y`.
+    pub fn state_columns(&self) -> [Column<Advice>; 25] {
+        self.keccak_f.state
+    }
+
+    /// Hashes `input` and exposes its digest lanes at the instance rows
+    /// `first_row..first_row + DIGEST_LANES`, followed by the input bytes.
+    ///
+    /// Each call runs its own permutations on its own state cells, only the
+    /// tables are shared, so one config can hash any number of inputs.
+    pub fn assign_hash(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[u8],
+        first_row: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.assign_hash_with_state(layouter, [0; 25], input, first_row)
+    }
+
+    /// Same as [`Self::assign_hash`], with the sponge starting from
+    /// `initial_state` instead of zeros. The lanes are in the spec order
+    /// `x + 5 * y` and are constants of the circuit, see
+    /// [`KeccakFConfig::absorb_initial`].
+    pub fn assign_hash_with_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initial_state: [u64; 25],
+        input: &[u8],
+        first_row: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let bytes = self.assign_input(layouter, input)?;
+        for (row, byte) in bytes.iter().enumerate() {
+            layouter.constrain_instance(
+                byte.cell(),
+                self.instance,
+                first_row + DIGEST_LANES + row,
+            )?;
+        }
+        let digest = self.hash_bytes_with_state(layouter, initial_state, &bytes)?;
+        for (row, lane) in digest.iter().enumerate() {
+            layouter.constrain_instance(lane.cell(), self.instance, first_row + row)?;
+        }
+        Ok(digest)
+    }
+
+    /// Returns the digest lanes of the assigned `bytes`, without exposing
+    /// them. The bytes are range checked along the way.
+    pub fn hash_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.hash_bytes_with_state(layouter, [0; 25], bytes)
+    }
+
+    /// Same as [`Self::hash_bytes`], with the sponge starting from
+    /// `initial_state`.
+    fn hash_bytes_with_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initial_state: [u64; 25],
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let num_blocks = padded_blocks(bytes.len());
+        let blocks = (0..num_blocks)
+            .map(|idx| {
+                let acc_len = idx * BYTES_LEN_17_WORDS;
+                let end = bytes.len().min(acc_len + BYTES_LEN_17_WORDS);
+                self.assign_block(
+                    layouter,
+                    idx == num_blocks - 1,
+                    bytes.len(),
+                    acc_len,
+                    &bytes[acc_len..end],
+                )
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut blocks = blocks.into_iter();
+        // There's always a block, the padding of the empty input fills one
+        let first = blocks.next().unwrap();
+        let state = self
+            .keccak_f
+            .absorb_initial(layouter, initial_state, &first)?;
+        let out_state = self.keccak_f.absorb_blocks(layouter, state, blocks)?;
+        self.squeeze
+            .assign_state(layouter, &out_state, DIGEST_LANES)
+    }
+
+    /// Hashes an input given as a stream of `chunks` and returns the digest
+    /// lanes, without exposing them.
+    ///
+    /// The chunks may have any length. Each full block is padded and absorbed
+    /// as soon as its last byte is read, so only one block of bytes is held at
+    /// a time. The input length is only known when the stream ends, and the
+    /// last block is padded then, see [`KeccakHasher`].
+    pub fn absorb_stream<B: AsRef<[u8]>>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        chunks: impl Iterator<Item = B>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let mut hasher = KeccakHasher::new();
+        for chunk in chunks {
+            hasher.update(self, layouter, chunk.as_ref())?;
+        }
+        hasher.finalize(self, layouter)
+    }
+
+    /// Range checks `bytes` with the lookup converting the hashed bytes, a
+    /// lane of 8 bytes at a time.
+    pub(crate) fn range_check_bytes(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<(), Error> {
+        assert_eq!(bytes.len() % 8, 0);
+        for lane in bytes.chunks(8) {
+            self.to_sparse
+                .assign_lane(layouter, lane.try_into().unwrap())?;
+        }
+        Ok(())
+    }
+
+    /// Pads the `bytes` of a block, see
+    /// [`PaddingConfig::assign_block_with_constants`], and returns the base 9
+    /// lanes of the padded block.
+    fn assign_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        is_finalize: bool,
+        input_len: usize,
+        acc_len: usize,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let padded = self.padding.assign_block_with_constants(
+            layouter,
+            is_finalize,
+            input_len,
+            acc_len,
+            bytes,
+        )?;
+        padded
+            .chunks(8)
+            .map(|lane| {
+                self.to_sparse
+                    .assign_lane(layouter, lane.try_into().unwrap())
+            })
+            .collect()
+    }
+
+    /// Witnesses the bytes of `input`, 25 per row.
+    fn assign_input(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &[u8],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        layouter.assign_region(
+            || "Input bytes",
+            |mut region| {
+                input
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &byte)| {
+                        region.assign_advice(
+                            || "input byte",
+                            self.keccak_f.state[i % 25],
+                            i / 25,
+                            || Ok(F::from(byte as u64)),
+                        )
+                    })
+                    .collect()
+            },
+        )
+    }
+}
+
+/// Hashes an input given over several [`update`](Self::update) calls, like
+/// the `Digest` interface of the `sha3` crate.
+///
+/// The hasher only holds the sponge state and the bytes of a partial block:
+/// every full block is absorbed right away. [`finalize`](Self::finalize) pads
+/// the last block and squeezes the digest.
+#[derive(Debug, Clone)]
+pub struct KeccakHasher<F: Field> {
+    /// The base 13 input state of the next permutation, `None` until the
+    /// first block is absorbed.
+    state: Option<[AssignedCell<F, F>; 25]>,
+    /// The bytes of the block being filled, fewer than a block.
+    block: Vec<AssignedCell<F, F>>,
+    /// The number of bytes absorbed in the full blocks.
+    acc_len: usize,
+}
+
+impl<F: Field> Default for KeccakHasher<F> {
+    fn default() -> Self {
+        Self {
+            state: None,
+            block: Vec::with_capacity(BYTES_LEN_17_WORDS),
+            acc_len: 0,
+        }
+    }
+}
+
+impl<F: Field> KeccakHasher<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Witnesses `bytes` and appends them to the input. They aren't exposed,
+    /// see [`Self::update_assigned`] to hash bytes bound elsewhere.
+    pub fn update(
+        &mut self,
+        config: &Keccak256Config<F>,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[u8],
+    ) -> Result<(), Error> {
+        let bytes = config.assign_input(layouter, bytes)?;
+        self.update_assigned(config, layouter, &bytes)
+    }
+
+    /// Appends the assigned `bytes` to the input, absorbing every block they
+    /// complete.
+    pub fn update_assigned(
+        &mut self,
+        config: &Keccak256Config<F>,
+        layouter: &mut impl Layouter<F>,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<(), Error> {
+        for byte in bytes {
+            self.block.push(byte.clone());
+            if self.block.len() == BYTES_LEN_17_WORDS {
+                // More bytes may follow, the pad goes in a later block
+                let lanes = config.assign_block(
+                    layouter,
+                    false,
+                    self.acc_len + BYTES_LEN_17_WORDS,
+                    self.acc_len,
+                    &self.block,
+                )?;
+                self.state = Some(match self.state.take() {
+                    None => config.keccak_f.absorb_initial(layouter, [0; 25], &lanes)?,
+                    Some(state) => config.keccak_f.absorb_block(layouter, state, &lanes)?,
+                });
+                self.acc_len += BYTES_LEN_17_WORDS;
+                self.block.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Pads the last block and returns the 4 digest lanes, each the `u64` of
+    /// its 8 bytes in little endian. The lanes aren't exposed, see
+    /// [`Keccak256Config::assign_hash`] for that.
+    pub fn finalize(
+        self,
+        config: &Keccak256Config<F>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let input_len = self.acc_len + self.block.len();
+        let lanes = config.assign_block(layouter, true, input_len, self.acc_len, &self.block)?;
+        let out_state = match self.state {
+            None => {
+                let state = config.keccak_f.absorb_initial(layouter, [0; 25], &lanes)?;
+                config
+                    .keccak_f
+                    .absorb_blocks(layouter, state, iter::empty())?
+            }
+            Some(state) => config
+                .keccak_f
+                .absorb_blocks(layouter, state, iter::once(lanes))?,
+        };
+        config
+            .squeeze
+            .assign_state(layouter, &out_state, DIGEST_LANES)
+    }
+}
+
+impl<F: Field> Circuit<F> for Keccak256Circuit<F> {
+    type Config = Keccak256Config<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        // Keep the length, the layout depends on it
+        Self::new(vec![0; self.input.len()]).with_initial_state(self.initial_state)
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Keccak256Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        config.assign_hash_with_state(&mut layouter, self.initial_state, &self.input, 0)?;
+        Ok(())
+    }
+}
+
+/// Renders the layout of a [`Keccak256Circuit`] hashing the empty input to
+/// the PNG at `path`, to spot unused columns and overlapping regions.
+#[cfg(feature = "dev-graph")]
+pub fn render_layout(path: impl AsRef<std::path::Path>) -> Result<(), Box<dyn std::error::Error>> {
+    use halo2_proofs::{dev::CircuitLayout, pairing::bn256::Fr};
+    use plotters::prelude::*;
+
+    let circuit = Keccak256Circuit::<Fr>::new(vec![]);
+    let root = BitMapBackend::new(path.as_ref(), (1024, 16384)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let root = root.titled("Keccak-256", ("sans-serif", 60))?;
+    CircuitLayout::default()
+        .show_labels(false)
+        .render(circuit.min_k(), &circuit, &root)?;
+    root.present()?;
+    Ok(())
+}
+
+/// Proves the Keccak-256 digests of several independent inputs.
+///
+/// All the inputs go through one [`Keccak256Config`], so the lookup tables are
+/// configured and loaded once for all of them while each input has its own
+/// states. The digests and the inputs are exposed one after the other, see
+/// [`MultiHashCircuit::instance`]. As for [`Keccak256Circuit`], the keys
+/// depend on the length of each input.
+#[derive(Default)]
+pub struct MultiHashCircuit<F> {
+    inputs: Vec<Vec<u8>>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> MultiHashCircuit<F> {
+    pub fn new(inputs: Vec<Vec<u8>>) -> Self {
+        Self {
+            inputs,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The digest lanes and the bytes of each input, see
+    /// [`Keccak256Circuit::instance`].
+    pub fn instance(&self) -> Vec<F> {
+        self.inputs
+            .iter()
+            .flat_map(|input| Keccak256Circuit::<F>::new(input.clone()).instance())
+            .collect()
+    }
+}
+
+impl<F: Field> Circuit<F> for MultiHashCircuit<F> {
+    type Config = Keccak256Config<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(
+            self.inputs
+                .iter()
+                .map(|input| vec![0; input.len()])
+                .collect(),
+        )
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Keccak256Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        let mut first_row = 0;
+        for input in self.inputs.iter() {
+            config.assign_hash(&mut layouter, input, first_row)?;
+            first_row += DIGEST_LANES + input.len();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        dev::MockProver,
+        pairing::bn256::{Bn256, Fr as Fp, G1Affine},
+        plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier},
+        poly::commitment::{Params, ParamsVerifier},
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+    };
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_keccak256_circuit_mock() {
+        for input in [vec![], b"abc".to_vec()] {
+            let circuit = Keccak256Circuit::<Fp>::new(input);
+            let instance = circuit.instance();
+            let prover = MockProver::<Fp>::run(17, &circuit, vec![instance.clone()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let mut wrong_instance = instance;
+            wrong_instance[0] += Fp::one();
+            let prover = MockProver::<Fp>::run(17, &circuit, vec![wrong_instance]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_wrong_digest() {
+        // The digest of "abd" for the input "abc"
+        let circuit = Keccak256Circuit::<Fp>::new(b"abc".to_vec());
+        let mut instance = Keccak256Circuit::<Fp>::new(b"abd".to_vec()).instance();
+        instance[DIGEST_LANES + 2] = Fp::from(b'c' as u64);
+        assert_eq!(instance[DIGEST_LANES..], circuit.instance()[DIGEST_LANES..]);
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The digest of "abc" for the input "abd"
+        let mut instance = circuit.instance();
+        instance[DIGEST_LANES + 2] = Fp::from(b'd' as u64);
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_initial_state() {
+        let state: [u64; 25] = rand::random();
+        let circuit = Keccak256Circuit::<Fp>::new(b"abc".to_vec()).with_initial_state(state);
+        let instance = circuit.instance();
+        assert_ne!(
+            instance,
+            Keccak256Circuit::<Fp>::new(b"abc".to_vec()).instance()
+        );
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The digest of the zero state doesn't verify
+        let circuit = Keccak256Circuit::<Fp>::new(b"abc".to_vec());
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[cfg(feature = "dev-graph")]
+    #[test]
+    fn test_render_layout() {
+        let path = std::env::temp_dir().join("keccak256-layout.png");
+        render_layout(&path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_keccak_hasher() {
+        #[derive(Default)]
+        struct HasherCircuit<F> {
+            parts: Vec<Vec<u8>>,
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for HasherCircuit<F> {
+            type Config = Keccak256Config<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    parts: self.parts.iter().map(|part| vec![0; part.len()]).collect(),
+                    _marker: PhantomData,
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Keccak256Config::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let mut hasher = KeccakHasher::new();
+                for part in self.parts.iter() {
+                    hasher.update(&config, &mut layouter, part)?;
+                    assert!(hasher.block.len() < BYTES_LEN_17_WORDS);
+                }
+                let digest = hasher.finalize(&config, &mut layouter)?;
+                // The single-shot hash of the whole input, in the same circuit
+                let input = self.parts.concat();
+                let expected = config.assign_hash(&mut layouter, &input, 0)?;
+                layouter.assign_region(
+                    || "Same digests",
+                    |mut region| {
+                        for (a, b) in digest.iter().zip(expected.iter()) {
+                            region.constrain_equal(a.cell(), b.cell())?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // The parts straddle the first block boundary
+        let parts = vec![b"abc".to_vec(), vec![0x42; 140], vec![], vec![7; 20]];
+        let instance = Keccak256Circuit::<Fp>::new(parts.concat()).instance();
+        let expected = crate::reference::keccak256(&parts.concat())
+            .chunks(8)
+            .map(|lane| Fp::from(u64::from_le_bytes(lane.try_into().unwrap())))
+            .collect_vec();
+        assert_eq!(instance[..DIGEST_LANES], expected);
+        let circuit = HasherCircuit::<Fp> {
+            parts,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(18, &circuit, vec![instance]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_absorb_stream() {
+        #[derive(Default)]
+        struct StreamCircuit<F> {
+            input: Vec<u8>,
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for StreamCircuit<F> {
+            type Config = Keccak256Config<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    input: vec![0; self.input.len()],
+                    _marker: PhantomData,
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Keccak256Config::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                // Chunks that don't line up with the blocks
+                let streamed = config.absorb_stream(&mut layouter, self.input.chunks(50))?;
+                let buffered = config.assign_hash(&mut layouter, &self.input, 0)?;
+                layouter.assign_region(
+                    || "Same digests",
+                    |mut region| {
+                        for (a, b) in streamed.iter().zip(buffered.iter()) {
+                            region.constrain_equal(a.cell(), b.cell())?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // Three blocks, the last one holds the end of the input and the pad
+        let input: Vec<u8> = (0..300).map(|_| rand::random::<u8>()).collect();
+        let circuit = StreamCircuit::<Fp> {
+            input: input.clone(),
+            _marker: PhantomData,
+        };
+        let instance = Keccak256Circuit::<Fp>::new(input).instance();
+        let prover = MockProver::<Fp>::run(18, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The buffered digest is exposed, so a wrong one fails for both
+        let mut wrong_instance = instance;
+        wrong_instance[1] += Fp::one();
+        let prover = MockProver::<Fp>::run(18, &circuit, vec![wrong_instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_min_k() {
+        use crate::permutation::circuit::estimate_min_k;
+
+        for input_len in [0, 3, 135, 136, 1000] {
+            let circuit = Keccak256Circuit::<Fp>::new(vec![0; input_len]);
+            assert!(circuit.min_k() >= 17);
+            assert!(circuit.min_k() >= estimate_min_k(input_len));
+        }
+    }
+
+    #[test]
+    fn test_min_k_is_enough() {
+        for input in [b"abc".to_vec(), vec![7; 200]] {
+            let circuit = Keccak256Circuit::<Fp>::new(input);
+            let prover =
+                MockProver::<Fp>::run(circuit.min_k(), &circuit, vec![circuit.instance()]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_multi_hash() {
+        let inputs = vec![b"abc".to_vec(), vec![0x42; 150]];
+        let circuit = MultiHashCircuit::<Fp>::new(inputs.clone());
+        let instance = circuit.instance();
+        let instances = inputs
+            .into_iter()
+            .map(|input| Keccak256Circuit::<Fp>::new(input).instance())
+            .collect_vec();
+        assert_eq!(instance, instances.concat());
+        let prover = MockProver::<Fp>::run(18, &circuit, vec![instance]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Swapping the digests fails
+        let mut swapped = instances;
+        let (first, second) = swapped.split_at_mut(1);
+        first[0][..DIGEST_LANES].swap_with_slice(&mut second[0][..DIGEST_LANES]);
+        let prover = MockProver::<Fp>::run(18, &circuit, vec![swapped.concat()]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_multi_hash_shares_tables() {
+        // The config doesn't depend on the number of inputs
+        let mut single = ConstraintSystem::<Fp>::default();
+        Keccak256Circuit::<Fp>::configure(&mut single);
+        let mut multi = ConstraintSystem::<Fp>::default();
+        MultiHashCircuit::<Fp>::configure(&mut multi);
+        assert_eq!(multi.num_fixed_columns(), single.num_fixed_columns());
+        assert_eq!(multi.num_advice_columns(), single.num_advice_columns());
+        assert_eq!(multi.lookups().len(), single.lookups().len());
+    }
+
+    // Creates a real proof, which takes minutes: run it with `--ignored`.
+    #[ignore]
+    #[test]
+    fn test_keccak256_circuit_proof() {
+        let k = 17;
+        let circuit = Keccak256Circuit::<Fp>::new(b"abc".to_vec());
+        let instance = circuit.instance();
+        // The first lane of keccak256("abc") is 4e03657aea45a94f
+        assert_eq!(instance[0], Fp::from(0x4fa9_45ea_7a65_034e));
+
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(k);
+        let vk = keygen_vk(&params, &circuit.without_witnesses()).unwrap();
+        let pk = keygen_pk(&params, vk, &circuit.without_witnesses()).unwrap();
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[circuit],
+            &[&[instance.as_slice()]],
+            rand::rngs::OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let proof = transcript.finalize();
+
+        let verifier_params: ParamsVerifier<Bn256> = params.verifier(instance.len()).unwrap();
+        let verify = |instance: &[Fp]| {
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            verify_proof(
+                &verifier_params,
+                pk.get_vk(),
+                SingleVerifier::new(&verifier_params),
+                &[&[instance]],
+                &mut transcript,
+            )
+        };
+        assert!(verify(&instance).is_ok());
+        let wrong_instance = instance.iter().map(|lane| *lane + Fp::one()).collect_vec();
+        assert!(verify(&wrong_instance).is_err());
+    }
+}
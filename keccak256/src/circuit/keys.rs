@@ -0,0 +1,286 @@
+//! Stores the keys of [`Keccak256Circuit`], so applications don't generate
+//! them again for every run.
+//!
+//! The keys only hold for inputs of the same length and the same initial state
+//! of the sponge, see [`Keccak256Circuit::with_initial_state`], so both are
+//! written in front of the verifying key and checked when it's loaded. This
+//! version of halo2 can't serialize a proving key, it is derived from the
+//! loaded verifying key with [`proving_key`] instead, which skips the
+//! commitments to the fixed columns. [`KeyCache`] keeps the derived proving
+//! keys, one per input length and initial state, so they are only derived once
+//! per process.
+use crate::circuit::hash::Keccak256Circuit;
+use crate::reference::keccak256;
+use halo2_proofs::{
+    pairing::bn256::{Fr, G1Affine},
+    plonk::{keygen_pk, keygen_vk, Error, ProvingKey, VerifyingKey},
+    poly::commitment::Params,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `vk`, the verifying key of [`Keccak256Circuit`] for inputs of
+/// `input_len` bytes absorbed into `initial_state`, to `path`.
+pub fn dump_verifying_key(
+    path: impl AsRef<Path>,
+    vk: &VerifyingKey<G1Affine>,
+    input_len: usize,
+    initial_state: [u64; 25],
+) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(&(input_len as u64).to_le_bytes())?;
+    for lane in initial_state.iter() {
+        file.write_all(&lane.to_le_bytes())?;
+    }
+    vk.write(&mut file)?;
+    file.flush()
+}
+
+/// Reads the verifying key written by [`dump_verifying_key`].
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the key was generated for
+/// inputs of another length than `input_len` bytes, or for another initial
+/// state than `initial_state`.
+/// `params` have to be the ones the key was generated with.
+pub fn load_verifying_key(
+    path: impl AsRef<Path>,
+    params: &Params<G1Affine>,
+    input_len: usize,
+    initial_state: [u64; 25],
+) -> io::Result<VerifyingKey<G1Affine>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut read_u64 = || -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        file.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    };
+    let key_len = read_u64()?;
+    if key_len != input_len as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "the key is for inputs of {} bytes, not {}",
+                key_len, input_len
+            ),
+        ));
+    }
+    let mut key_state = [0u64; 25];
+    for lane in key_state.iter_mut() {
+        *lane = read_u64()?;
+    }
+    if key_state != initial_state {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the key is for another initial state",
+        ));
+    }
+    VerifyingKey::read::<_, Keccak256Circuit<Fr>>(&mut file, params)
+}
+
+/// Derives the proving key of [`Keccak256Circuit`] for inputs of `input_len`
+/// bytes absorbed into `initial_state` from its verifying key.
+pub fn proving_key(
+    params: &Params<G1Affine>,
+    vk: VerifyingKey<G1Affine>,
+    input_len: usize,
+    initial_state: [u64; 25],
+) -> Result<ProvingKey<G1Affine>, Error> {
+    keygen_pk(params, vk, &keygen_circuit(input_len, initial_state))
+}
+
+/// The circuit the keys are generated from, the input bytes don't matter.
+fn keygen_circuit(input_len: usize, initial_state: [u64; 25]) -> Keccak256Circuit<Fr> {
+    Keccak256Circuit::new(vec![0; input_len]).with_initial_state(initial_state)
+}
+
+/// The proving keys of [`Keccak256Circuit`] by input length and initial
+/// state, backed by the verifying keys stored in a directory.
+///
+/// A missing verifying key is generated and stored. The proving key is
+/// derived from it the first time a length and state are asked for, and kept.
+pub struct KeyCache {
+    params: Params<G1Affine>,
+    dir: PathBuf,
+    keys: HashMap<(usize, [u64; 25]), ProvingKey<G1Affine>>,
+}
+
+impl KeyCache {
+    /// Stores the verifying keys in `dir`. `params` have to be the ones the
+    /// stored keys were generated with.
+    pub fn new(params: Params<G1Affine>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            params,
+            dir: dir.into(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// The path of the verifying key for inputs of `input_len` bytes absorbed
+    /// into `initial_state`.
+    ///
+    /// The keys of other initial states than the zero state are told apart by
+    /// the first 8 bytes of the Keccak-256 digest of the state.
+    pub fn verifying_key_path(&self, input_len: usize, initial_state: [u64; 25]) -> PathBuf {
+        if initial_state == [0; 25] {
+            return self.dir.join(format!("keccak256_{}.vk", input_len));
+        }
+        let state_bytes = initial_state
+            .iter()
+            .flat_map(|lane| lane.to_le_bytes())
+            .collect::<Vec<_>>();
+        let fingerprint = keccak256(&state_bytes)[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        self.dir
+            .join(format!("keccak256_{}_{}.vk", input_len, fingerprint))
+    }
+
+    /// Returns the proving key for inputs of `input_len` bytes absorbed into
+    /// `initial_state`.
+    pub fn proving_key(
+        &mut self,
+        input_len: usize,
+        initial_state: [u64; 25],
+    ) -> io::Result<&ProvingKey<G1Affine>> {
+        let key = (input_len, initial_state);
+        if !self.keys.contains_key(&key) {
+            let path = self.verifying_key_path(input_len, initial_state);
+            let vk = if path.exists() {
+                load_verifying_key(&path, &self.params, input_len, initial_state)?
+            } else {
+                let circuit = keygen_circuit(input_len, initial_state);
+                let vk = keygen_vk(&self.params, &circuit).map_err(to_io_error)?;
+                dump_verifying_key(&path, &vk, input_len, initial_state)?;
+                vk
+            };
+            let pk =
+                proving_key(&self.params, vk, input_len, initial_state).map_err(to_io_error)?;
+            self.keys.insert(key, pk);
+        }
+        Ok(&self.keys[&key])
+    }
+}
+
+fn to_io_error(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        pairing::bn256::Bn256,
+        plonk::{create_proof, verify_proof, SingleVerifier},
+        poly::commitment::ParamsVerifier,
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+    };
+
+    #[test]
+    fn test_verifying_key_round_trip() {
+        let input_len = 3;
+        let circuit = Keccak256Circuit::<Fr>::new(vec![0; input_len]);
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(17);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let path = std::env::temp_dir().join("keccak256_verifying_key.bin");
+        dump_verifying_key(&path, &vk, input_len, [0; 25]).unwrap();
+
+        // The key doesn't hold for a longer input
+        let err = load_verifying_key(&path, &params, input_len + 1, [0; 25]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let loaded = load_verifying_key(&path, &params, input_len, [0; 25]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(vk_bytes(&loaded), vk_bytes(&vk));
+    }
+
+    fn vk_bytes(vk: &VerifyingKey<G1Affine>) -> Vec<u8> {
+        let mut bytes = vec![];
+        vk.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_keys_depend_on_initial_state() {
+        let input_len = 3;
+        let mut state = [0u64; 25];
+        state[0] = 0x0123_4567_89ab_cdef;
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(17);
+        let dir = std::env::temp_dir().join("keccak256_key_cache_state");
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut cache = KeyCache::new(params.clone(), &dir);
+        let zero_path = cache.verifying_key_path(input_len, [0; 25]);
+        let state_path = cache.verifying_key_path(input_len, state);
+        assert_ne!(zero_path, state_path);
+        let _ = std::fs::remove_file(&zero_path);
+        let _ = std::fs::remove_file(&state_path);
+
+        let zero_vk = vk_bytes(cache.proving_key(input_len, [0; 25]).unwrap().get_vk());
+        // The zero state key isn't served for another state
+        let state_vk = vk_bytes(cache.proving_key(input_len, state).unwrap().get_vk());
+        assert_ne!(zero_vk, state_vk);
+        let circuit = Keccak256Circuit::<Fr>::new(vec![0; input_len]).with_initial_state(state);
+        assert_eq!(state_vk, vk_bytes(&keygen_vk(&params, &circuit).unwrap()));
+
+        // Nor loaded from the file of the zero state
+        let err = load_verifying_key(&zero_path, &params, input_len, state).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&zero_path).unwrap();
+        std::fs::remove_file(&state_path).unwrap();
+    }
+
+    // Creates a real proof, which takes minutes: run it with `--ignored`.
+    #[ignore]
+    #[test]
+    fn test_key_cache_proof() {
+        let input = b"abc".to_vec();
+        let circuit = Keccak256Circuit::<Fr>::new(input.clone());
+        let instance = circuit.instance();
+
+        let dir = std::env::temp_dir();
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(17);
+        let mut cache = KeyCache::new(params.clone(), &dir);
+        let path = cache.verifying_key_path(input.len(), [0; 25]);
+        let _ = std::fs::remove_file(&path);
+        // Generates and stores the verifying key
+        let vk = cache
+            .proving_key(input.len(), [0; 25])
+            .unwrap()
+            .get_vk()
+            .clone();
+        assert!(path.exists());
+
+        // A new cache derives the proving key from the stored verifying key
+        let mut cache = KeyCache::new(params.clone(), &dir);
+        let pk = cache.proving_key(input.len(), [0; 25]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            &params,
+            pk,
+            &[circuit],
+            &[&[instance.as_slice()]],
+            rand::rngs::OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let proof = transcript.finalize();
+
+        // The proof verifies against the generated key too
+        let verifier_params: ParamsVerifier<Bn256> = params.verifier(instance.len()).unwrap();
+        for vk in [pk.get_vk(), &vk] {
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            assert!(verify_proof(
+                &verifier_params,
+                vk,
+                SingleVerifier::new(&verifier_params),
+                &[&[instance.as_slice()]],
+                &mut transcript,
+            )
+            .is_ok());
+        }
+    }
+}
@@ -60,10 +60,41 @@ impl<F: Field> WordConfig<F> {
     }
 }
 
+/// The hash function whose padding rule is applied
+///
+/// Both variants pad with pad10*1, they only differ in the domain separation
+/// bits in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashVariant {
+    /// Keccak-256 as used by Ethereum
+    Keccak256,
+    /// NIST SHA3-256
+    Sha3_256,
+}
+
+impl HashVariant {
+    /// The value added to the first padding byte: the domain separation bits,
+    /// if any, then the first bit of pad10*1.
+    ///
+    /// The bytes are in the spec order, the bits of a byte are taken from the
+    /// least significant one.
+    pub fn first_pad_byte(&self) -> u8 {
+        match self {
+            HashVariant::Keccak256 => 0x01,
+            HashVariant::Sha3_256 => 0x06,
+        }
+    }
+}
+
+/// The value added to the last byte of the final block, the last bit of
+/// pad10*1. It is the same for every variant.
+pub const LAST_PAD_BYTE: u8 = 0x80;
+
 // TODO: byteRLC
 #[derive(Debug, Clone)]
 pub struct PaddingConfig<F> {
     q_all: Selector,
+    q_first: Selector,
     q_without_first: Selector,
     q_without_last: Selector,
     q_last: Selector,
@@ -75,11 +106,13 @@ pub struct PaddingConfig<F> {
     is_pad_zone: Column<Advice>,
     padded_byte: Column<Advice>,
     word_config: WordConfig<F>,
+    variant: HashVariant,
 }
 
 impl<F: Field> PaddingConfig<F> {
-    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+    pub fn configure(meta: &mut ConstraintSystem<F>, variant: HashVariant) -> Self {
         let q_all = meta.selector();
+        let q_first = meta.selector();
         let q_without_first = meta.selector();
         let q_without_last = meta.selector();
         let q_last = meta.selector();
@@ -92,9 +125,11 @@ impl<F: Field> PaddingConfig<F> {
         let padded_byte = meta.advice_column();
         let word = meta.advice_column();
         meta.enable_equality(is_finalize);
+        meta.enable_equality(byte);
         meta.enable_equality(input_len);
         meta.enable_equality(acc_len);
         let one = Expression::Constant(F::one());
+        let first_pad_byte = Expression::Constant(F::from(variant.first_pad_byte() as u64));
         let diff_is_zero = IsZeroChip::configure(
             meta,
             |meta| meta.query_selector(q_all),
@@ -115,7 +150,7 @@ impl<F: Field> PaddingConfig<F> {
         });
         // check that
         // 1. acc_len is increasing by one in each row
-        // 2. padded_byte is correctly padded 0x80 from byte
+        // 2. padded_byte is correctly padded with the first pad byte from byte
         meta.create_gate("without last", |meta| {
             let q_without_last = meta.query_selector(q_without_last);
             let acc_len_cur = meta.query_advice(acc_len, Rotation::cur());
@@ -128,11 +163,16 @@ impl<F: Field> PaddingConfig<F> {
                     "check padded byte",
                     padded_byte_cur
                         - byte_cur
-                        - diff_is_zero.clone().is_zero_expression
-                            * Expression::Constant(F::from(0x80)),
+                        - diff_is_zero.clone().is_zero_expression * first_pad_byte.clone(),
                 )))
                 .map(move |(name, poly)| (name, q_without_last.clone() * poly))
         });
+        // The pad zone starts at the first row if the pad does.
+        meta.create_gate("first", |meta| {
+            let q_first = meta.query_selector(q_first);
+            let is_pad_zone = meta.query_advice(is_pad_zone, Rotation::cur());
+            vec![q_first * (is_pad_zone - diff_is_zero.clone().is_zero_expression)]
+        });
         // Check that cells in the pad_zone column are 0 before the pad, and are 1 after
         // the pad.
         meta.create_gate("without first", |meta| {
@@ -147,8 +187,9 @@ impl<F: Field> PaddingConfig<F> {
                         - diff_is_zero.clone().is_zero_expression),
             )]
         });
-        // padded_byte is padded 0x80 if pad happens here. padded_byte is also padded
-        // 0x01 if the state_tag is Finalize
+        // padded_byte is padded with the first pad byte if pad happens here.
+        // padded_byte is also padded 0x80 if the state_tag is Finalize
+        let last_pad_byte = Expression::Constant(F::from(LAST_PAD_BYTE as u64));
         meta.create_gate("last", |meta| {
             let q_last = meta.query_selector(q_last);
             let is_finalize = meta.query_advice(is_finalize, Rotation::cur());
@@ -158,13 +199,13 @@ impl<F: Field> PaddingConfig<F> {
                 q_last
                     * (padded_byte_cur
                         - byte_cur
-                        - diff_is_zero.clone().is_zero_expression
-                            * Expression::Constant(F::from(0x80))
-                        - is_finalize),
+                        - diff_is_zero.clone().is_zero_expression * first_pad_byte
+                        - is_finalize * last_pad_byte),
             ]
         });
         Self {
             q_all,
+            q_first,
             q_without_first,
             q_without_last,
             q_last,
@@ -176,8 +217,18 @@ impl<F: Field> PaddingConfig<F> {
             is_pad_zone,
             padded_byte,
             word_config,
+            variant,
         }
     }
+
+    /// Pads one 136 byte block of the input and returns its 17 words.
+    ///
+    /// `acc_len_cell` is the number of input bytes absorbed in the previous
+    /// blocks. The pad starts at the byte where the accumulated length reaches
+    /// `input_len_cell`, so a message whose length is a multiple of the rate
+    /// gets a full padding block with `acc_len == input_len`. When the pad
+    /// starts at the last byte of the final block both pad bits land in the
+    /// same byte.
     pub fn assign_region(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -186,15 +237,112 @@ impl<F: Field> PaddingConfig<F> {
         acc_len_cell: AssignedCell<F, F>,
         bytes: [u8; BYTES_LEN_17_WORDS],
     ) -> Result<[AssignedCell<F, F>; 17], Error> {
+        let (words, _) = self.assign_padding(
+            layouter,
+            is_finalize,
+            input_len_cell,
+            acc_len_cell,
+            |region, offset| {
+                region.assign_advice(
+                    || "byte",
+                    self.byte,
+                    offset,
+                    || Ok(F::from(bytes[offset] as u64)),
+                )
+            },
+        )?;
+        Ok(words)
+    }
+
+    /// Pads the input `bytes` of one block, see [`Self::assign_region`], and
+    /// returns the 136 padded bytes.
+    ///
+    /// The `bytes` cells are copied in front of the block and the rest of the
+    /// block is zero, so at most a block of bytes can be passed. The padded
+    /// bytes are not range checked here, the caller converting them to lanes
+    /// does it.
+    pub fn assign_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        is_finalize: AssignedCell<F, F>,
+        input_len_cell: AssignedCell<F, F>,
+        acc_len_cell: AssignedCell<F, F>,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert!(bytes.len() <= BYTES_LEN_17_WORDS);
+        let (_, padded_bytes) = self.assign_padding(
+            layouter,
+            is_finalize,
+            input_len_cell,
+            acc_len_cell,
+            |region, offset| match bytes.get(offset) {
+                Some(byte) => byte.copy_advice(|| "byte", region, self.byte, offset),
+                None => region.assign_advice(|| "byte", self.byte, offset, || Ok(F::zero())),
+            },
+        )?;
+        Ok(padded_bytes)
+    }
+
+    /// Same as [`Self::assign_block`] with the lengths and the finalize flag
+    /// fixed in the circuit, for layouts where they are known at key
+    /// generation.
+    pub fn assign_block_with_constants(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        is_finalize: bool,
+        input_len: usize,
+        acc_len: usize,
+        bytes: &[AssignedCell<F, F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let (is_finalize, input_len, acc_len) = layouter.assign_region(
+            || "padding constants",
+            |mut region| {
+                let is_finalize = region.assign_advice_from_constant(
+                    || "is finalize",
+                    self.is_finalize,
+                    0,
+                    F::from(is_finalize),
+                )?;
+                let input_len = region.assign_advice_from_constant(
+                    || "input len",
+                    self.input_len,
+                    0,
+                    F::from(input_len as u64),
+                )?;
+                let acc_len = region.assign_advice_from_constant(
+                    || "acc len",
+                    self.acc_len,
+                    0,
+                    F::from(acc_len as u64),
+                )?;
+                Ok((is_finalize, input_len, acc_len))
+            },
+        )?;
+        self.assign_block(layouter, is_finalize, input_len, acc_len, bytes)
+    }
+
+    /// Assigns the padding region with the byte of each row assigned by
+    /// `assign_byte`, and returns the words and the padded bytes.
+    fn assign_padding(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        is_finalize: AssignedCell<F, F>,
+        input_len_cell: AssignedCell<F, F>,
+        acc_len_cell: AssignedCell<F, F>,
+        mut assign_byte: impl FnMut(&mut Region<'_, F>, usize) -> Result<AssignedCell<F, F>, Error>,
+    ) -> Result<([AssignedCell<F, F>; 17], Vec<AssignedCell<F, F>>), Error> {
         let diff_is_zero_chip = IsZeroChip::construct(self.diff_is_zero.clone());
         layouter.assign_region(
             || "padding validation",
             |mut region| {
                 const LAST: usize = BYTES_LEN_17_WORDS - 1;
+                self.q_first.enable(&mut region, 0)?;
                 self.q_last.enable(&mut region, LAST)?;
+                let first_pad_byte = F::from(self.variant.first_pad_byte() as u64);
+                let is_finalize_value = is_finalize.value().cloned().unwrap_or_default();
                 let mut is_pad_zone = F::zero();
-                let mut padded_bytes = [0u8; BYTES_LEN_17_WORDS];
-                for (offset, &byte) in bytes.iter().enumerate().take(BYTES_LEN_17_WORDS) {
+                let mut padded_byte_cells = Vec::with_capacity(BYTES_LEN_17_WORDS);
+                for offset in 0..BYTES_LEN_17_WORDS {
                     self.q_all.enable(&mut region, offset)?;
                     if offset != 0 {
                         self.q_without_first.enable(&mut region, offset)?;
@@ -216,12 +364,17 @@ impl<F: Field> PaddingConfig<F> {
                     )?;
                     let acc_len =
                         acc_len_cell.value().cloned().unwrap_or_default() + F::from(offset as u64);
-                    region.assign_advice(
-                        || "acc_len_rest",
-                        self.acc_len,
-                        offset,
-                        || Ok(acc_len),
-                    )?;
+                    // The gates carry the accumulated length from the first row
+                    if offset == 0 {
+                        acc_len_cell.copy_advice(|| "acc_len", &mut region, self.acc_len, 0)?;
+                    } else {
+                        region.assign_advice(
+                            || "acc_len_rest",
+                            self.acc_len,
+                            offset,
+                            || Ok(acc_len),
+                        )?;
+                    }
                     let diff_value =
                         Some(input_len_cell.value().cloned().unwrap_or_default() - acc_len);
                     let is_zero = diff_value
@@ -229,8 +382,7 @@ impl<F: Field> PaddingConfig<F> {
                         .unwrap_or_default();
                     diff_is_zero_chip.assign(&mut region, offset, diff_value)?;
 
-                    let byte_f = F::from(byte as u64);
-                    region.assign_advice(|| "byte", self.byte, offset, || Ok(byte_f))?;
+                    let byte = assign_byte(&mut region, offset)?;
                     is_pad_zone += is_zero;
                     region.assign_advice(
                         || "is pad zone",
@@ -238,29 +390,18 @@ impl<F: Field> PaddingConfig<F> {
                         offset,
                         || Ok(is_pad_zone),
                     )?;
-                    let is_finalize_bit =
-                        is_finalize.value().cloned().unwrap_or_default() == F::one();
-                    padded_bytes[offset] = byte
-                        + diff_value
-                            .map(|diff_value| (diff_value == F::zero()) as u8)
-                            .unwrap_or_default()
-                            * 0x80u8
-                        + (((offset == LAST) && is_finalize_bit) as u8);
+                    let mut padded_byte =
+                        byte.value().cloned().unwrap_or_default() + is_zero * first_pad_byte;
+                    if offset == LAST {
+                        padded_byte += is_finalize_value * F::from(LAST_PAD_BYTE as u64);
+                    }
+                    padded_byte_cells.push(region.assign_advice(
+                        || "padded byte",
+                        self.padded_byte,
+                        offset,
+                        || Ok(padded_byte),
+                    )?);
                 }
-                let padded_byte_cells: Result<Vec<_>, _> = padded_bytes
-                    .iter()
-                    .take(BYTES_LEN_17_WORDS)
-                    .enumerate()
-                    .map(|(offset, &padded_byte)| {
-                        region.assign_advice(
-                            || "padded byte",
-                            self.padded_byte,
-                            offset,
-                            || Ok(F::from(padded_byte as u64)),
-                        )
-                    })
-                    .collect();
-                let padded_byte_cells = padded_byte_cells?;
                 let words: Result<Vec<_>, _> = padded_byte_cells
                     .iter()
                     .chunks(8)
@@ -274,7 +415,7 @@ impl<F: Field> PaddingConfig<F> {
                     .collect();
                 let words: [AssignedCell<F, F>; 17] = words?.try_into().unwrap();
 
-                Ok(words)
+                Ok((words, padded_byte_cells))
             },
         )
     }
@@ -283,6 +424,7 @@ impl<F: Field> PaddingConfig<F> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arith_helpers::PaddedBlocks;
     use halo2_proofs::{
         circuit::SimpleFloorPlanner,
         dev::MockProver,
@@ -320,15 +462,9 @@ mod tests {
         acc_len: Column<Advice>,
     }
 
-    impl<F: Field> Circuit<F> for MyCircuit<F> {
-        type Config = MyConfig<F>;
-        type FloorPlanner = SimpleFloorPlanner;
-
-        fn without_witnesses(&self) -> Self {
-            Self::default()
-        }
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let padding_conf = PaddingConfig::configure(meta);
+    impl<F: Field> MyConfig<F> {
+        fn configure(meta: &mut ConstraintSystem<F>, variant: HashVariant) -> Self {
+            let padding_conf = PaddingConfig::configure(meta, variant);
             let is_finalize = meta.advice_column();
             let input_len = meta.advice_column();
             let acc_len = meta.advice_column();
@@ -336,19 +472,99 @@ mod tests {
             meta.enable_equality(input_len);
             meta.enable_equality(acc_len);
 
-            Self::Config {
+            Self {
                 padding_conf,
                 is_finalize,
                 acc_len,
                 input_len,
             }
         }
+    }
+
+    impl<F: Field> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            MyConfig::configure(meta, HashVariant::Keccak256)
+        }
 
         fn synthesize(
             &self,
             config: Self::Config,
-            mut layouter: impl Layouter<F>,
+            layouter: impl Layouter<F>,
         ) -> Result<(), Error> {
+            self.assign(config, layouter).map(|_| ())
+        }
+    }
+
+    /// Same as [`MyCircuit`] with the SHA3-256 padding
+    #[derive(Default)]
+    struct Sha3Circuit<F>(MyCircuit<F>);
+
+    impl<F: Field> Circuit<F> for Sha3Circuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            MyConfig::configure(meta, HashVariant::Sha3_256)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            self.0.assign(config, layouter).map(|_| ())
+        }
+    }
+
+    /// Same as [`MyCircuit`], checking the padded words against the padding of
+    /// the spec
+    #[derive(Default)]
+    struct SpecCircuit<F> {
+        circuit: MyCircuit<F>,
+        words: [u64; 17],
+    }
+
+    impl<F: Field> Circuit<F> for SpecCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            MyConfig::configure(meta, HashVariant::Keccak256)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let words = self.circuit.assign(config, layouter)?;
+            for (word, expected) in words.iter().zip(self.words) {
+                if let Some(word) = word.value() {
+                    assert_eq!(*word, F::from(expected));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<F: Field> MyCircuit<F> {
+        fn assign(
+            &self,
+            config: MyConfig<F>,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<[AssignedCell<F, F>; 17], Error> {
             let (is_finalize, input_len, acc_len) = layouter.assign_region(
                 || "external values",
                 |mut region| {
@@ -381,9 +597,7 @@ mod tests {
                 input_len,
                 acc_len,
                 self.bytes,
-            )?;
-
-            Ok(())
+            )
         }
     }
     #[test]
@@ -446,4 +660,111 @@ mod tests {
         let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_pad_after_full_block() {
+        // The input fills the previous block entirely, so this whole block is
+        // padding.
+        let circuit = MyCircuit::<Fr> {
+            bytes: [0; BYTES_LEN_17_WORDS],
+            is_finalize: true,
+            input_len: 136,
+            acc_len: 136,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_second_block_pad() {
+        let mut bytes = [0u8; BYTES_LEN_17_WORDS];
+        bytes[0] = rand::random();
+        let circuit = MyCircuit::<Fr> {
+            bytes,
+            is_finalize: true,
+            input_len: 137,
+            acc_len: 136,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_input_in_pad_zone() {
+        // A non zero byte after the input end must be rejected
+        let mut bytes = [0u8; BYTES_LEN_17_WORDS];
+        bytes[0] = 1;
+        bytes[1] = 1;
+        let circuit = MyCircuit::<Fr> {
+            bytes,
+            is_finalize: true,
+            input_len: 137,
+            acc_len: 136,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_pad_byte_variants() {
+        assert_eq!(HashVariant::Keccak256.first_pad_byte(), 0x01);
+        assert_eq!(HashVariant::Sha3_256.first_pad_byte(), 0x06);
+        assert_eq!(LAST_PAD_BYTE, 0x80);
+    }
+
+    #[test]
+    fn test_pad_matches_spec() {
+        // The words are the big endian reading of the spec lanes
+        let mut almost_full = [0u8; BYTES_LEN_17_WORDS];
+        almost_full.try_fill(&mut thread_rng()).unwrap();
+        for input_len in [0, 3, 135] {
+            let mut bytes = [0u8; BYTES_LEN_17_WORDS];
+            bytes[..input_len].copy_from_slice(&almost_full[..input_len]);
+            let lanes = PaddedBlocks::new(bytes[..input_len].iter().copied())
+                .next()
+                .unwrap();
+            let circuit = SpecCircuit::<Fr> {
+                circuit: MyCircuit {
+                    bytes,
+                    is_finalize: true,
+                    input_len: input_len as u64,
+                    acc_len: 0,
+                    _marker: PhantomData,
+                },
+                words: lanes.map(u64::swap_bytes),
+            };
+            let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_sha3_pad() {
+        // Empty input, "abc", the combined 0x86 byte, and a full padding block
+        // after an exact multiple of the rate
+        let mut abc = [0u8; BYTES_LEN_17_WORDS];
+        abc[..3].copy_from_slice(b"abc");
+        let mut almost_full = [0u8; BYTES_LEN_17_WORDS];
+        almost_full.try_fill(&mut thread_rng()).unwrap();
+        almost_full[135] = 0;
+        for (bytes, input_len, acc_len) in [
+            ([0; BYTES_LEN_17_WORDS], 0, 0),
+            (abc, 3, 0),
+            (almost_full, 135, 0),
+            ([0; BYTES_LEN_17_WORDS], 136, 136),
+        ] {
+            let circuit = Sha3Circuit::<Fr>(MyCircuit {
+                bytes,
+                is_finalize: true,
+                input_len,
+                acc_len,
+                _marker: PhantomData,
+            });
+            let prover = MockProver::<Fr>::run(9, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
 }
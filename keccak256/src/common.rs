@@ -47,3 +47,118 @@ pub static ROTATION_CONSTANTS: [[u32; 5]; 5] = [
 ];
 
 pub const LANE_SIZE: u32 = 64;
+
+/// The position `(x, y)` of a lane in the state, with both coordinates
+/// checked to be below 5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaneIndex {
+    x: usize,
+    y: usize,
+}
+
+impl LaneIndex {
+    pub fn new(x: usize, y: usize) -> Option<Self> {
+        (x < 5 && y < 5).then(|| Self { x, y })
+    }
+
+    /// The lane at the circuit index `5 * x + y` of the state columns.
+    pub fn from_index(idx: usize) -> Option<Self> {
+        Self::new(idx / 5, idx % 5)
+    }
+
+    /// The 25 lanes of the state, in circuit index order.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..25).map(|idx| Self {
+            x: idx / 5,
+            y: idx % 5,
+        })
+    }
+
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// The circuit index of the lane, the inverse of [`Self::from_index`].
+    pub fn index(&self) -> usize {
+        5 * self.x + self.y
+    }
+
+    /// The Rho rotation of the lane, from [`ROTATION_CONSTANTS`].
+    pub fn rotation(&self) -> u32 {
+        ROTATION_CONSTANTS[self.x][self.y]
+    }
+}
+
+/// The number of base 13 chunks a slice of a lane holds at most in the Rho
+/// step.
+///
+/// The base 13 to base 9 table has `13**BASE_NUM_OF_CHUNKS` rows, so fewer
+/// chunks give a smaller table but more slices per lane. The overflow detector
+/// values in `rho_helpers` are derived for this value and have to be derived
+/// again when it changes.
+pub const BASE_NUM_OF_CHUNKS: u32 = 4;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_rotation_constants() {
+        // The rho offsets `r[x][y]` of the Keccak reference, in the same
+        // layout
+        let spec: [[u32; 5]; 5] = [
+            [0, 36, 3, 41, 18],
+            [1, 44, 10, 45, 2],
+            [62, 6, 43, 15, 61],
+            [28, 55, 25, 21, 56],
+            [27, 20, 39, 8, 14],
+        ];
+        assert_eq!(ROTATION_CONSTANTS, spec);
+
+        // They also follow from the recurrence of the spec, starting at
+        // (1, 0) and moving to (y, 2x + 3y) for 24 steps
+        let mut offsets = [[0u32; 5]; 5];
+        let (mut x, mut y) = (1, 0);
+        for t in 0..24 {
+            offsets[x][y] = ((t + 1) * (t + 2) / 2) % LANE_SIZE;
+            let next_y = (2 * x + 3 * y) % 5;
+            x = y;
+            y = next_y;
+        }
+        assert_eq!(ROTATION_CONSTANTS, offsets);
+
+        // Every lane is rotated by a different offset
+        let mut sorted = ROTATION_CONSTANTS
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 25);
+    }
+
+    #[test]
+    fn test_lane_index() {
+        assert_eq!(LaneIndex::new(5, 0), None);
+        assert_eq!(LaneIndex::new(0, 5), None);
+        assert_eq!(LaneIndex::new(usize::MAX, usize::MAX), None);
+        assert_eq!(LaneIndex::from_index(25), None);
+
+        let lane = LaneIndex::new(2, 4).unwrap();
+        assert_eq!((lane.x(), lane.y()), (2, 4));
+        assert_eq!(lane.index(), 14);
+        assert_eq!(lane.rotation(), 61);
+        for idx in 0..25 {
+            let lane = LaneIndex::from_index(idx).unwrap();
+            assert_eq!(lane.index(), idx);
+            assert_eq!(lane.rotation(), ROTATION_CONSTANTS[idx / 5][idx % 5]);
+        }
+        assert!(LaneIndex::all().map(|lane| lane.index()).eq(0..25));
+    }
+}
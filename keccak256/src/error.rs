@@ -0,0 +1,42 @@
+//! Error module for the keccak256 crate
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use halo2_proofs::plonk::Error as Halo2Error;
+use std::error::Error as StdError;
+
+/// Error type for the failures that happen while assigning the Keccak
+/// witnesses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeccakError {
+    /// A BigUint value doesn't fit in the field.
+    FieldConversion,
+    /// A BigUint value doesn't fit in the expected integer type.
+    BigUintConversion,
+    /// The lane index has no rotation offset.
+    InvalidRotation(usize),
+    /// The rotation offset is not smaller than the lane size.
+    RotationOutOfRange(u32),
+    /// A cell that is needed to compute a witness has no value.
+    MissingWitness,
+    /// The lane at this index is not a valid input of the Rho conversion.
+    InvalidRhoInput(usize),
+    /// The Rho witness doesn't match the lane at this index.
+    WitnessMismatch(usize),
+}
+
+impl Display for KeccakError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl StdError for KeccakError {}
+
+/// halo2 has no room for a custom cause, so the cause is logged before it is
+/// turned into [`Halo2Error::Synthesis`].
+impl From<KeccakError> for Halo2Error {
+    fn from(err: KeccakError) -> Self {
+        log::error!("keccak256 synthesis error: {}", err);
+        Halo2Error::Synthesis
+    }
+}
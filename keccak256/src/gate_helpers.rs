@@ -1,20 +1,32 @@
+use crate::arith_helpers::{dense_to_sparse, try_sparse_to_dense, B13};
+use crate::error::KeccakError;
 use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error},
+};
 use num_bigint::BigUint;
 use std::convert::TryInto;
+use std::fmt;
 
 /// Convert a bigUint value to FieldExt
 ///
 /// We assume the input value is smaller than the field size
 pub fn biguint_to_f<F: Field>(x: &BigUint) -> F {
+    try_biguint_to_f(x).unwrap()
+}
+
+/// Convert a bigUint value to FieldExt, failing with
+/// [`KeccakError::FieldConversion`] if the value is not smaller than the field
+/// size
+pub fn try_biguint_to_f<F: Field>(x: &BigUint) -> Result<F, KeccakError> {
     let mut x_bytes = x.to_bytes_le();
-    debug_assert!(
-        x_bytes.len() <= 32,
-        "expect len <=32 but got {}",
-        x_bytes.len()
-    );
+    if x_bytes.len() > 32 {
+        return Err(KeccakError::FieldConversion);
+    }
     x_bytes.resize(32, 0);
     let x_bytes: [u8; 32] = x_bytes.try_into().unwrap();
-    F::from_repr_vartime(x_bytes).unwrap()
+    Option::from(F::from_repr_vartime(x_bytes)).ok_or(KeccakError::FieldConversion)
 }
 
 pub fn f_to_biguint<F: Field>(x: F) -> BigUint {
@@ -27,3 +39,301 @@ pub fn biguint_mod(x: &BigUint, modulus: u8) -> u8 {
         .copied()
         .unwrap_or_default()
 }
+
+/// Witnesses the dense `value` as a sparse base 13 lane, the form the state
+/// takes at the input of Theta.
+pub fn assign_lane_base13<F: Field>(
+    region: &mut Region<'_, F>,
+    column: Column<Advice>,
+    offset: usize,
+    value: u64,
+) -> Result<AssignedCell<F, F>, Error> {
+    region.assign_advice(
+        || "base 13 lane",
+        column,
+        offset,
+        || Ok(biguint_to_f(&dense_to_sparse(value, B13))),
+    )
+}
+
+/// Decodes the sparse lane held by `cell` in `base` back to the dense lane.
+///
+/// Returns `None` when the cell has no witness, or when its value is not a
+/// sparse lane of single bits, like the output of a gate before the chunks are
+/// normalized.
+pub fn dense_u64<F: Field>(cell: &AssignedCell<F, F>, base: u8) -> Option<u64> {
+    try_sparse_to_dense(&f_to_biguint(*cell.value()?), base)
+}
+
+/// Describes a lane `value` for debugging: the raw value in decimal, followed
+/// by the dense lane it encodes in `base` when it is a sparse lane of single
+/// bits. The raw value alone is unreadable for a sparse lane.
+pub fn describe_lane<F: Field>(value: F, base: u8) -> String {
+    let raw = f_to_biguint(value);
+    match try_sparse_to_dense(&raw, base) {
+        Some(dense) => format!("{} (dense {:#018x} in base {})", raw, dense, base),
+        None => raw.to_string(),
+    }
+}
+
+/// Constrains the lanes `a` and `b` to be equal, for wiring the state from
+/// one step to the next.
+///
+/// The values are compared when both have a witness, so a miswired lane shows
+/// up where it is wired rather than as a failed copy constraint.
+pub fn constrain_equal_lanes<F: Field>(
+    region: &mut Region<'_, F>,
+    a: &AssignedCell<F, F>,
+    b: &AssignedCell<F, F>,
+) -> Result<(), Error> {
+    if let (Some(a), Some(b)) = (a.value(), b.value()) {
+        debug_assert_eq!(a, b, "the lanes to constrain equal have different values");
+    }
+    region.constrain_equal(a.cell(), b.cell())
+}
+
+/// The constraints registered by a config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConstraintCount {
+    pub gates: usize,
+    /// The polynomials of the gates, each one is a constraint on its own
+    pub polynomials: usize,
+    pub lookups: usize,
+    /// The highest degree of the gate polynomials, lookups not included
+    pub max_degree: usize,
+}
+
+/// Counts the constraints each config registers, in the order they are
+/// configured.
+#[derive(Debug, Clone, Default)]
+pub struct ConstraintReport {
+    configs: Vec<(&'static str, ConstraintCount)>,
+}
+
+impl ConstraintReport {
+    /// Runs `configure` and records the gates and lookups it adds to `meta`
+    /// under `name`.
+    pub fn measure<F: Field, T>(
+        &mut self,
+        name: &'static str,
+        meta: &mut ConstraintSystem<F>,
+        configure: impl FnOnce(&mut ConstraintSystem<F>) -> T,
+    ) -> T {
+        let (gates, lookups) = (meta.gates().len(), meta.lookups().len());
+        let config = configure(meta);
+        let new_gates = &meta.gates()[gates..];
+        let polynomials = new_gates.iter().flat_map(|gate| gate.polynomials());
+        self.configs.push((
+            name,
+            ConstraintCount {
+                gates: new_gates.len(),
+                polynomials: polynomials.clone().count(),
+                lookups: meta.lookups().len() - lookups,
+                max_degree: polynomials.map(|poly| poly.degree()).max().unwrap_or(0),
+            },
+        ));
+        config
+    }
+
+    /// The count recorded under `name`, if any
+    pub fn get(&self, name: &str) -> Option<ConstraintCount> {
+        self.configs
+            .iter()
+            .find(|(config, _)| *config == name)
+            .map(|(_, count)| *count)
+    }
+
+    /// The counts of all the configs added up
+    pub fn total(&self) -> ConstraintCount {
+        self.configs
+            .iter()
+            .fold(ConstraintCount::default(), |total, (_, count)| {
+                ConstraintCount {
+                    gates: total.gates + count.gates,
+                    polynomials: total.polynomials + count.polynomials,
+                    lookups: total.lookups + count.lookups,
+                    max_degree: total.max_degree.max(count.max_degree),
+                }
+            })
+    }
+}
+
+impl fmt::Display for ConstraintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        for (name, count) in self.configs.iter().chain([("total", total)].iter()) {
+            writeln!(
+                f,
+                "{:<20} {:>4} gates {:>5} polynomials {:>3} lookups, degree {}",
+                name, count.gates, count.polynomials, count.lookups, count.max_degree
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use halo2_proofs::plonk::Error;
+    use num_traits::One;
+
+    #[test]
+    fn test_try_biguint_to_f() {
+        let x = BigUint::from(123456789u64);
+        assert_eq!(try_biguint_to_f::<Fp>(&x), Ok(Fp::from(123456789u64)));
+
+        let too_long = BigUint::one() << 256;
+        assert_eq!(
+            try_biguint_to_f::<Fp>(&too_long),
+            Err(KeccakError::FieldConversion)
+        );
+        let modulus = f_to_biguint(-Fp::one()) + 1u64;
+        assert_eq!(
+            try_biguint_to_f::<Fp>(&modulus),
+            Err(KeccakError::FieldConversion)
+        );
+
+        let err: Error = try_biguint_to_f::<Fp>(&modulus).unwrap_err().into();
+        assert!(matches!(err, Error::Synthesis));
+    }
+
+    #[test]
+    fn test_lane_round_trip() {
+        use crate::arith_helpers::B9;
+        use halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner},
+            dev::MockProver,
+            plonk::{Circuit, ConstraintSystem},
+        };
+
+        #[derive(Default)]
+        struct MyCircuit<F> {
+            values: Vec<u64>,
+            _marker: std::marker::PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for MyCircuit<F> {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                meta.advice_column()
+            }
+
+            fn synthesize(
+                &self,
+                column: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "lanes",
+                    |mut region| {
+                        for (offset, &value) in self.values.iter().enumerate() {
+                            let lane = assign_lane_base13(&mut region, column, offset, value)?;
+                            if lane.value().is_some() {
+                                assert_eq!(dense_u64(&lane, B13), Some(value));
+                            }
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let values = [0, 1, u64::MAX]
+            .into_iter()
+            .chain((0..20).map(|_| rand::random::<u64>()))
+            .collect();
+        let circuit = MyCircuit::<Fp> {
+            values,
+            _marker: std::marker::PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(6, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A chunk of 2 is not a bit, and 65 chunks don't fit a lane
+        assert_eq!(try_sparse_to_dense(&BigUint::from(2u64), B13), None);
+        assert_eq!(try_sparse_to_dense(&BigUint::from(13u64), B13), Some(2));
+        let too_long = BigUint::from(B9).pow(64);
+        assert_eq!(try_sparse_to_dense(&too_long, B9), None);
+        assert_eq!(try_sparse_to_dense(&(too_long - 1u64), B9), None);
+        assert_eq!(
+            try_sparse_to_dense(&dense_to_sparse(u64::MAX, B9), B9),
+            Some(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_describe_lane() {
+        let lane: Fp = biguint_to_f(&dense_to_sparse(0x0123_4567_89ab_cdef, B13));
+        let description = describe_lane(lane, B13);
+        assert!(description.ends_with("(dense 0x0123456789abcdef in base 13)"));
+        assert!(description.starts_with(&f_to_biguint(lane).to_string()));
+        // A chunk of 2 is not a bit
+        assert_eq!(describe_lane(Fp::from(2), B13), "2");
+    }
+
+    #[derive(Default)]
+    struct EqualLanesCircuit<F> {
+        a: F,
+        b: F,
+    }
+
+    impl<F: Field> halo2_proofs::plonk::Circuit<F> for EqualLanesCircuit<F> {
+        type Config = Column<Advice>;
+        type FloorPlanner = halo2_proofs::circuit::SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
+            let column = meta.advice_column();
+            meta.enable_equality(column);
+            column
+        }
+
+        fn synthesize(
+            &self,
+            column: Self::Config,
+            mut layouter: impl halo2_proofs::circuit::Layouter<F>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "equal lanes",
+                |mut region| {
+                    let a = region.assign_advice(|| "a", column, 0, || Ok(self.a))?;
+                    let b = region.assign_advice(|| "b", column, 1, || Ok(self.b))?;
+                    constrain_equal_lanes(&mut region, &a, &b)
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_constrain_equal_lanes() {
+        use halo2_proofs::dev::MockProver;
+
+        let lane: Fp = biguint_to_f(&dense_to_sparse(0x0123_4567_89ab_cdef, B13));
+        let circuit = EqualLanesCircuit { a: lane, b: lane };
+        let prover = MockProver::<Fp>::run(4, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "the lanes to constrain equal have different values")]
+    fn test_constrain_equal_lanes_mismatch() {
+        use halo2_proofs::dev::MockProver;
+
+        let circuit = EqualLanesCircuit {
+            a: Fp::one(),
+            b: Fp::from(2),
+        };
+        MockProver::<Fp>::run(4, &circuit, vec![]).unwrap();
+    }
+}
@@ -10,7 +10,18 @@ impl KeccakFArith {
         a: &mut StateBigInt,
         next_inputs: Option<&State>,
     ) -> Option<StateBigInt> {
-        for rc in ROUND_CONSTANTS.iter().take(PERMUTATION - 1) {
+        KeccakFArith::permute_and_absorb_rounds(a, next_inputs, PERMUTATION)
+    }
+
+    /// Same as [`KeccakFArith::permute_and_absorb`] with only the first
+    /// `num_rounds` rounds of the permutation.
+    pub fn permute_and_absorb_rounds(
+        a: &mut StateBigInt,
+        next_inputs: Option<&State>,
+        num_rounds: usize,
+    ) -> Option<StateBigInt> {
+        assert!((1..=PERMUTATION).contains(&num_rounds));
+        for rc in ROUND_CONSTANTS.iter().take(num_rounds - 1) {
             let s1 = KeccakFArith::theta(a);
             let s2 = KeccakFArith::rho(&s1);
             let s3 = KeccakFArith::pi(&s2);
@@ -22,7 +33,7 @@ impl KeccakFArith {
         let s2 = KeccakFArith::rho(&s1);
         let s3 = KeccakFArith::pi(&s2);
         let s4 = KeccakFArith::xi(&s3);
-        let res = KeccakFArith::mixing(&s4, next_inputs, *ROUND_CONSTANTS.last().unwrap());
+        let res = KeccakFArith::mixing(&s4, next_inputs, ROUND_CONSTANTS[num_rounds - 1]);
         *a = res.clone();
         if next_inputs.is_some() {
             Some(res)
@@ -232,8 +243,10 @@ impl Sponge {
 #[cfg(test)]
 mod tests {
     use crate::arith_helpers::*;
+    use crate::common::{NEXT_INPUTS_LANES, ROUND_CONSTANTS};
     use crate::keccak_arith::{Keccak, KeccakFArith, State};
     use crate::plain::KeccakF;
+    use crate::reference::keccak_f1600;
     use itertools::Itertools;
     use num_bigint::BigUint;
     use num_traits::Zero;
@@ -314,6 +327,104 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_permutation_arith() {
+        // Keccak-f[1600] of the all-zero state
+        let mut zero_state: State = [[0; 5]; 5];
+        KeccakF::default().permutations(&mut zero_state);
+        assert_eq!(zero_state[0][0], 0xF1258F7940E1DDE7);
+        assert_eq!(zero_state[1][0], 0x84D5CCF933C0478A);
+
+        let mut random_state: State = [[0; 5]; 5];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            random_state[x][y] = rand::random();
+        }
+
+        for a in [[[0; 5]; 5], random_state] {
+            let mut expected = a;
+            KeccakF::default().permutations(&mut expected);
+
+            let mut state = StateBigInt::default();
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                state[(x, y)] = convert_b2_to_b13(a[x][y]);
+            }
+            KeccakFArith::permute_and_absorb(&mut state, None);
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                assert_eq!(convert_b9_lane_to_b2(state[(x, y)].clone()), expected[x][y]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reduced_rounds_arith() {
+        let mut a: State = [[0; 5]; 5];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            a[x][y] = rand::random();
+        }
+        for num_rounds in [1, 2, 24] {
+            let mut expected = a;
+            KeccakF::default().reduced_permutations(&mut expected, num_rounds);
+
+            let mut state = StateBigInt::default();
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                state[(x, y)] = convert_b2_to_b13(a[x][y]);
+            }
+            KeccakFArith::permute_and_absorb_rounds(&mut state, None, num_rounds);
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                assert_eq!(convert_b9_lane_to_b2(state[(x, y)].clone()), expected[x][y]);
+            }
+        }
+        // A single round is just the round function
+        let mut one_round = a;
+        KeccakF::default().reduced_permutations(&mut one_round, 1);
+        let round = KeccakF::iota(
+            KeccakF::xi(KeccakF::pi(KeccakF::rho(KeccakF::theta(a)))),
+            ROUND_CONSTANTS[0],
+        );
+        assert_eq!(one_round, round);
+    }
+
+    #[test]
+    fn test_absorb_two_blocks() {
+        let random_block = || {
+            let mut block: State = [[0; 5]; 5];
+            for i in 0..NEXT_INPUTS_LANES {
+                block[i % 5][i / 5] = rand::random();
+            }
+            block
+        };
+        let (block1, block2) = (random_block(), random_block());
+
+        // Reference: f(f(block1) ^ block2), on the flat spec layout
+        let flatten = |a: &State| -> [u64; 25] {
+            let mut out = [0; 25];
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                out[x + 5 * y] = a[x][y];
+            }
+            out
+        };
+        let mut expected = keccak_f1600(flatten(&block1));
+        for (i, lane) in flatten(&block2).iter().enumerate() {
+            expected[i] ^= lane;
+        }
+        let expected = keccak_f1600(expected);
+
+        // The first block is loaded as is, the second one is absorbed in the
+        // last round of the first permutation.
+        let mut state = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            state[(x, y)] = convert_b2_to_b13(block1[x][y]);
+        }
+        KeccakFArith::permute_and_absorb(&mut state, Some(&block2));
+        KeccakFArith::permute_and_absorb(&mut state, None);
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            assert_eq!(
+                convert_b9_lane_to_b2(state[(x, y)].clone()),
+                expected[x + 5 * y]
+            );
+        }
+    }
+
     #[test]
     fn test_empty_input_arith() {
         let output = [
@@ -4,21 +4,26 @@
 pub mod arith_helpers;
 pub mod circuit;
 pub mod common;
+pub mod error;
 pub mod gate_helpers;
 pub mod permutation;
 // We build arith module to get test cases for the circuit
 pub mod keccak_arith;
 // We build plain module for the purpose of reviewing the circuit
 pub mod plain;
+// Spec layout wrappers over plain, used as the oracle for witness values
+pub mod reference;
+// Framing of the cSHAKE and KMAC prefixes
+pub mod sp800_185;
+#[cfg(test)]
+pub(crate) mod test_utils;
+#[cfg(feature = "trace")]
+pub mod trace;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 lazy_static::lazy_static! {
-    pub static ref EMPTY_HASH: [u8; 32] = {
-        use std::convert::TryInto;
-
-        let mut keccak = plain::Keccak::default();
-        keccak.update(&[]);
-        keccak.digest().try_into().unwrap()
-    };
+    pub static ref EMPTY_HASH: [u8; 32] = reference::keccak256(&[]);
     pub static ref EMPTY_HASH_LE: [u8; 32] = {
         use std::convert::TryInto;
         use itertools::Itertools;
@@ -5,11 +5,14 @@ pub(crate) mod base_conversion;
 pub mod circuit;
 pub(crate) mod generic;
 pub(crate) mod iota;
+pub mod lane_layout;
 pub(crate) mod mixing;
 pub(crate) mod pi;
 pub(crate) mod rho;
 pub(crate) mod rho_checks;
-pub(crate) mod rho_helpers;
+pub mod rho_helpers;
+pub(crate) mod squeeze;
+pub mod step;
 pub(crate) mod tables;
 pub(crate) mod theta;
 pub(crate) mod xi;
@@ -1,32 +1,63 @@
 use crate::arith_helpers::*;
 use crate::common::*;
+use crate::error::KeccakError;
+use crate::gate_helpers::{biguint_to_f, f_to_biguint};
+use crate::sp800_185::prefix_blocks;
 use eth_types::Field;
 use halo2_proofs::circuit::{AssignedCell, Layouter, Region};
 use halo2_proofs::{
     plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
     poly::Rotation,
 };
-use itertools::Itertools;
 use std::{convert::TryInto, marker::PhantomData};
 
+/// Absorbs the next `rate` input lanes into the state.
+///
+/// Instead of XORing the block into a base-13 state before the next
+/// permutation, the block is added as `A4 * d` to the base-9 output of the Xi
+/// step in the last round, so `2a + b + 3c + 2d` is mapped back to binary by
+/// the same base conversion that feeds the next permutation. The lanes of the
+/// block fill the state in the spec order, see [`block_lane_index`], and the
+/// rest of the lanes are constrained to go through untouched.
+///
+/// The rate is [`NEXT_INPUTS_LANES`] for Keccak-256. The permutation is the
+/// same for every rate, so other rates like the 21 lanes of SHAKE128 only
+/// change this config.
+///
+/// The next inputs are packed in `byte_order` and converted to the spec lanes
+/// when they are witnessed in base 9.
 #[derive(Clone, Debug)]
 pub struct AbsorbConfig<F> {
     q_mixing: Selector,
     state: [Column<Advice>; 25],
+    rate: usize,
+    byte_order: ByteOrder,
     _marker: PhantomData<F>,
 }
 
+/// The lane `(4, 4)` is never part of a block, so its column holds the flag in
+/// the next inputs row.
+///
+/// It is the last lane in the spec order and the rate always leaves room for
+/// the capacity. The column right after the block lanes doesn't work once
+/// they are in the spec order: column `17` is the lane `(3, 2)`, the 14th
+/// lane of every Keccak-256 block.
+const FLAG_LANE: usize = 24;
+
 impl<F: Field> AbsorbConfig<F> {
     // We assume state is recieved in base-9.
     // Rows are assigned as:
     // 1) STATE (25 columns) (offset -1)
-    // 2) NEXT_INPUTS (17 columns) + is_mixing flag (1 column) (offset +0)
+    // 2) NEXT_INPUTS (`rate` columns) + is_mixing flag (1 column) (offset +0)
     // (current rotation)
     // 3) OUT_STATE (25 columns) (offset +1)
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         state: [Column<Advice>; 25],
+        rate: usize,
+        byte_order: ByteOrder,
     ) -> AbsorbConfig<F> {
+        assert!(rate < 25, "the rate must leave room for the capacity");
         // def absorb(state: List[List[int], next_input: List[List[int]):
         //     for x in range(5):
         //         for y in range(5):
@@ -41,29 +72,36 @@ impl<F: Field> AbsorbConfig<F> {
             .iter()
             .for_each(|column| meta.enable_equality(*column));
 
+        // Degree 3: the selector times the flag times a linear combination of
+        // the state.
         meta.create_gate("absorb", |meta| {
             // We do a trick which consists on multiplying an internal selector
             // which is always active by the actual `is_mixing` flag
             // which will then enable or disable the gate.
             let q_enable = {
                 // We query the flag value from the `state` `Advice` column at
-                // rotation curr and position = `NEXT_INPUTS_LANES + 1`
+                // rotation curr and position = `FLAG_LANE`
                 // and multiply to it the active selector so that we avoid the
                 // `PoisonedConstraints` and each gate equation
                 // can be satisfied while enforcing the correct gate logic.
                 //
                 // This is boolean-constrained outside of `AbsorbConfig` by `MixingConfig`.
-                let flag = meta.query_advice(state[NEXT_INPUTS_LANES], Rotation::cur());
+                let flag = meta.query_advice(state[FLAG_LANE], Rotation::cur());
                 // Note also that we want to enable the gate when `is_mixing` is
                 // true. (flag = 1). See the flag computation above.
                 meta.query_selector(q_mixing) * flag
             };
 
-            (0..NEXT_INPUTS_LANES)
-                .map(|idx| {
-                    let val = meta.query_advice(state[idx], Rotation::prev())
-                        + (Expression::Constant(F::from(A4))
-                            * meta.query_advice(state[idx], Rotation::cur()));
+            (0..25)
+                .map(|i| {
+                    let idx = block_lane_index(i);
+                    let mut val = meta.query_advice(state[idx], Rotation::prev());
+                    // The capacity lanes don't take any input
+                    if i < rate {
+                        val = val
+                            + (Expression::Constant(F::from(A4))
+                                * meta.query_advice(state[idx], Rotation::cur()));
+                    }
 
                     let next_lane = meta.query_advice(state[idx], Rotation::next());
 
@@ -75,59 +113,133 @@ impl<F: Field> AbsorbConfig<F> {
         AbsorbConfig {
             q_mixing,
             state,
+            rate,
+            byte_order,
             _marker: PhantomData,
         }
     }
 
-    fn assign_next_inp_and_flag(
+    /// Witnesses the `rate` lanes of a block in base 9.
+    ///
+    /// `next_input` holds the lanes in binary, in the spec order, each packed
+    /// in the configured byte order. The lanes are free witnesses, callers
+    /// hashing an input convert its padded bytes to lanes instead.
+    pub fn assign_next_inputs(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        next_input: &[F],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert_eq!(next_input.len(), self.rate);
+        layouter.assign_region(
+            || "Witness next inputs",
+            |mut region| {
+                next_input
+                    .iter()
+                    .enumerate()
+                    .map(|(i, lane)| {
+                        let lane: u64 = f_to_biguint(*lane)
+                            .try_into()
+                            .map_err(|_| KeccakError::BigUintConversion)?;
+                        let lane = self.byte_order.to_spec_lane(lane);
+                        let lane = biguint_to_f::<F>(&convert_b2_to_b9(lane));
+                        region.assign_advice(
+                            || format!("assign next_input {}", i),
+                            self.state[block_lane_index(i)],
+                            0,
+                            || Ok(lane),
+                        )
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Witnesses the blocks of a cSHAKE or KMAC `prefix` in base 9, see
+    /// [`crate::sp800_185`], to absorb them before the message.
+    ///
+    /// The prefix is bytepadded to the rate, so it fills whole blocks and the
+    /// message starts on a fresh one. Returns [`Error::Synthesis`] otherwise.
+    pub fn assign_prefix(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        prefix: &[u8],
+    ) -> Result<Vec<Vec<AssignedCell<F, F>>>, Error> {
+        if prefix.len() % (8 * self.rate) != 0 {
+            return Err(Error::Synthesis);
+        }
+        prefix_blocks(prefix, self.rate)
+            .into_iter()
+            .map(|block| {
+                // The blocks hold spec lanes, the next inputs are packed in the
+                // configured byte order
+                let lanes = block
+                    .into_iter()
+                    .map(|lane| F::from(self.byte_order.lane_from_bytes(lane.to_le_bytes())))
+                    .collect::<Vec<_>>();
+                self.assign_next_inputs(layouter, &lanes)
+            })
+            .collect()
+    }
+
+    /// The values of the absorb output: `in_state` with `A4` times each lane
+    /// of `next_input` added to its lane.
+    pub fn out_state(
+        &self,
+        in_state: &[AssignedCell<F, F>; 25],
+        next_input: &[AssignedCell<F, F>],
+    ) -> [F; 25] {
+        let mut out_state = in_state
+            .clone()
+            .map(|lane| lane.value().cloned().unwrap_or_default());
+        for (i, lane) in next_input.iter().enumerate() {
+            out_state[block_lane_index(i)] +=
+                F::from(A4) * lane.value().cloned().unwrap_or_default();
+        }
+        out_state
+    }
+
+    fn copy_next_inp_and_flag(
         &self,
         region: &mut Region<F>,
         offset: usize,
         flag: AssignedCell<F, F>,
-        next_input: [F; NEXT_INPUTS_LANES],
+        next_input: &[AssignedCell<F, F>],
     ) -> Result<AssignedCell<F, F>, Error> {
-        // Generate next_input in base-9.
-        let mut next_mixing = state_to_biguint::<F, NEXT_INPUTS_LANES>(next_input);
-        for (x, y) in (0..5).cartesian_product(0..5) {
-            // Assign only first 17 values.
-            if x >= 3 && y >= 1 {
-                break;
-            }
-            next_mixing[(x, y)] = convert_b2_to_b9(next_mixing[(x, y)].clone().try_into().unwrap())
-        }
-        let next_input = state_bigint_to_field::<F, NEXT_INPUTS_LANES>(next_mixing);
-
-        // Assign next_mixing.
-        for (idx, lane) in next_input.iter().enumerate() {
-            region.assign_advice(
-                || format!("assign next_input {}", idx),
-                self.state[idx],
+        // Copy next_input in base-9.
+        for (i, lane) in next_input.iter().enumerate() {
+            lane.copy_advice(
+                || format!("copy next_input {}", i),
+                region,
+                self.state[block_lane_index(i)],
                 offset,
-                || Ok(*lane),
             )?;
         }
 
-        // Assign flag at last column(17th).
+        // Assign flag at the flag lane.
         let flag_assig_cell = flag.copy_advice(
-            || format!("assign next_input {}", NEXT_INPUTS_LANES),
+            || "assign is_mixing flag",
             region,
-            self.state[NEXT_INPUTS_LANES],
+            self.state[FLAG_LANE],
             offset,
         )?;
 
         Ok(flag_assig_cell)
     }
 
-    /// Doc this $
+    /// Copies `in_state`, the next inputs and the flag, witnesses `out_state`,
+    /// and returns the `out_state` cells with the copied flag.
+    ///
+    /// `next_input` holds the `rate` lanes of the block in base 9, in the spec
+    /// order, see [`Self::assign_next_inputs`].
     pub fn copy_state_flag_next_inputs(
         &self,
         layouter: &mut impl Layouter<F>,
         in_state: &[AssignedCell<F, F>; 25],
         out_state: [F; 25],
-        // Passed in base-2 and converted internally after witnessing it.
-        next_input: [F; NEXT_INPUTS_LANES],
+        next_input: &[AssignedCell<F, F>],
         flag: AssignedCell<F, F>,
     ) -> Result<([AssignedCell<F, F>; 25], AssignedCell<F, F>), Error> {
+        assert_eq!(next_input.len(), self.rate);
         layouter.assign_region(
             || "Absorb state assignations",
             |mut region| {
@@ -148,7 +260,7 @@ impl<F: Field> AbsorbConfig<F> {
 
                 // Assign `next_inputs` and flag.
                 let flag =
-                    self.assign_next_inp_and_flag(&mut region, offset, flag.clone(), next_input)?;
+                    self.copy_next_inp_and_flag(&mut region, offset, flag.clone(), next_input)?;
 
                 offset += 1;
                 // Assign out_state at offset + 2
@@ -189,90 +301,108 @@ mod tests {
     use std::marker::PhantomData;
 
     #[test]
-    fn test_absorb_gate() {
-        #[derive(Default)]
-        struct MyCircuit<F> {
-            in_state: [F; 25],
-            out_state: [F; 25],
-            next_input: [F; NEXT_INPUTS_LANES],
-            is_mixing: bool,
-            _marker: PhantomData<F>,
+    fn test_flag_lane() {
+        for rate in 1..25 {
+            assert!((0..rate).all(|i| block_lane_index(i) != FLAG_LANE));
         }
-        impl<F: Field> Circuit<F> for MyCircuit<F>
-        where
-            F: PrimeField<Repr = [u8; 32]>,
-        {
-            type Config = AbsorbConfig<F>;
-            type FloorPlanner = SimpleFloorPlanner;
+        assert_eq!(block_lane_index(13), NEXT_INPUTS_LANES);
+    }
 
-            fn without_witnesses(&self) -> Self {
-                Self::default()
+    #[derive(Default)]
+    struct MyCircuit<F, const RATE: usize, const BIG_ENDIAN: bool> {
+        in_state: [F; 25],
+        out_state: [F; 25],
+        next_input: Vec<F>,
+        is_mixing: bool,
+        _marker: PhantomData<F>,
+    }
+    impl<F: Field, const RATE: usize, const BIG_ENDIAN: bool> Circuit<F>
+        for MyCircuit<F, RATE, BIG_ENDIAN>
+    where
+        F: PrimeField<Repr = [u8; 32]>,
+    {
+        type Config = AbsorbConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                next_input: vec![F::zero(); RATE],
+                ..Self::default()
             }
+        }
 
-            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-                let state: [Column<Advice>; 25] = (0..25)
-                    .map(|_| {
-                        let column = meta.advice_column();
-                        meta.enable_equality(column);
-                        column
-                    })
-                    .collect::<Vec<_>>()
-                    .try_into()
-                    .unwrap();
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let state: [Column<Advice>; 25] = (0..25)
+                .map(|_| {
+                    let column = meta.advice_column();
+                    meta.enable_equality(column);
+                    column
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
 
-                AbsorbConfig::configure(meta, state)
-            }
+            let byte_order = if BIG_ENDIAN {
+                ByteOrder::BigEndian
+            } else {
+                ByteOrder::LittleEndian
+            };
+            AbsorbConfig::configure(meta, state, RATE, byte_order)
+        }
 
-            fn synthesize(
-                &self,
-                config: Self::Config,
-                mut layouter: impl Layouter<F>,
-            ) -> Result<(), Error> {
-                let val: F = (self.is_mixing as u64).into();
-                let flag: AssignedCell<F, F> = layouter.assign_region(
-                    || "witness_is_mixing_flag",
-                    |mut region| {
-                        let offset = 1;
-                        region.assign_advice(
-                            || "assign is_mixing",
-                            config.state[NEXT_INPUTS_LANES + 1],
-                            offset,
-                            || Ok(val),
-                        )
-                    },
-                )?;
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let val: F = (self.is_mixing as u64).into();
+            let flag: AssignedCell<F, F> = layouter.assign_region(
+                || "witness_is_mixing_flag",
+                |mut region| {
+                    let offset = 1;
+                    region.assign_advice(
+                        || "assign is_mixing",
+                        config.state[FLAG_LANE],
+                        offset,
+                        || Ok(val),
+                    )
+                },
+            )?;
 
-                // Witness `in_state`.
-                let in_state: [AssignedCell<F, F>; 25] = layouter.assign_region(
-                    || "Witness input state",
-                    |mut region| {
-                        let mut state: Vec<AssignedCell<F, F>> = Vec::with_capacity(25);
-                        for (idx, val) in self.in_state.iter().enumerate() {
-                            let cell = region.assign_advice(
-                                || "witness input state",
-                                config.state[idx],
-                                0,
-                                || Ok(*val),
-                            )?;
-                            state.push(cell)
-                        }
-
-                        Ok(state.try_into().unwrap())
-                    },
-                )?;
+            // Witness `in_state`.
+            let in_state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+                || "Witness input state",
+                |mut region| {
+                    let mut state: Vec<AssignedCell<F, F>> = Vec::with_capacity(25);
+                    for (idx, val) in self.in_state.iter().enumerate() {
+                        let cell = region.assign_advice(
+                            || "witness input state",
+                            config.state[idx],
+                            0,
+                            || Ok(*val),
+                        )?;
+                        state.push(cell)
+                    }
+
+                    Ok(state.try_into().unwrap())
+                },
+            )?;
 
-                config.copy_state_flag_next_inputs(
-                    &mut layouter,
-                    &in_state,
-                    self.out_state,
-                    self.next_input,
-                    flag,
-                )?;
+            let next_input = config.assign_next_inputs(&mut layouter, &self.next_input)?;
+            config.copy_state_flag_next_inputs(
+                &mut layouter,
+                &in_state,
+                self.out_state,
+                &next_input,
+                flag,
+            )?;
 
-                Ok(())
-            }
+            Ok(())
         }
+    }
 
+    #[test]
+    fn test_absorb_gate() {
         let input1: State = [
             [1, 0, 0, 0, 0],
             [0, 0, 0, 0, 0],
@@ -281,13 +411,9 @@ mod tests {
             [0, 0, 0, 0, 0],
         ];
 
-        let input2: State = [
-            [2, 0, 0, 0, 0],
-            [0, 0, 0, 0, 0],
-            [0, 0, 0, 0, 0],
-            [0, 0, 0, 0, 0],
-            [0, 0, 0, 0, 0],
-        ];
+        let mut next_input = vec![Fp::zero(); NEXT_INPUTS_LANES];
+        next_input[0] = Fp::from(2);
+        let input2 = next_inputs_to_state(&next_input);
 
         // Convert the input to base9 as the gadget already expects it like this
         // since it's always the output of IotaB9.
@@ -300,16 +426,14 @@ mod tests {
         let out_state =
             state_bigint_to_field(KeccakFArith::absorb(&StateBigInt::from(input1), &input2));
 
-        let next_input = state_bigint_to_field(StateBigInt::from(input2));
-
         // With flag set to true, the gate should trigger.
         {
             // With the correct input and output witnesses, the proof should
             // pass.
-            let circuit = MyCircuit::<Fp> {
+            let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
                 in_state,
                 out_state,
-                next_input,
+                next_input: next_input.clone(),
                 is_mixing: true,
                 _marker: PhantomData,
             };
@@ -320,10 +444,10 @@ mod tests {
 
             // With wrong input and/or output witnesses, the proof should fail
             // to be verified.
-            let circuit = MyCircuit::<Fp> {
+            let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
                 in_state,
                 out_state: in_state,
-                next_input,
+                next_input: next_input.clone(),
                 is_mixing: true,
                 _marker: PhantomData,
             };
@@ -336,7 +460,7 @@ mod tests {
         // With flag set to `false`, the gate shouldn't trigger.
         // And so we can pass any witness data and the proof should pass.
         {
-            let circuit = MyCircuit::<Fp> {
+            let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
                 in_state,
                 out_state: in_state,
                 next_input,
@@ -349,4 +473,243 @@ mod tests {
             assert_eq!(prover.verify(), Ok(()));
         }
     }
+
+    #[test]
+    fn test_absorb_keeps_capacity() {
+        let in_state: [Fp; 25] = (0..25u64)
+            .map(|lane| biguint_to_f(&convert_b2_to_b9(lane * 0x0101)))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let next_input = vec![Fp::from(0x42); NEXT_INPUTS_LANES];
+        let out_state = state_bigint_to_field(KeccakFArith::absorb(
+            &state_to_biguint(in_state),
+            &next_inputs_to_state(&next_input),
+        ));
+        let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
+            in_state,
+            out_state,
+            next_input: next_input.clone(),
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Lane `(3, 4)` is part of the capacity
+        let mut wrong_state = out_state;
+        wrong_state[block_lane_index(23)] += Fp::one();
+        let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
+            in_state,
+            out_state: wrong_state,
+            next_input,
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_absorb_shake128_rate() {
+        // SHAKE128 absorbs 168 byte blocks, so 21 lanes.
+        const RATE: usize = 21;
+        let block: Vec<u8> = (0..RATE * 8).map(|_| rand::random::<u8>()).collect();
+        let next_input: Vec<Fp> = block
+            .chunks(8)
+            .map(|word| Fp::from(u64::from_le_bytes(word.try_into().unwrap())))
+            .collect();
+
+        let input1: State = [
+            [1, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 0],
+            [0, 0, 0, 0, 7],
+            [0, 0, 0, 0, 0],
+        ];
+        let mut in_state = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_state[(x, y)] = convert_b2_to_b9(input1[x][y])
+        }
+        let out_state = KeccakFArith::absorb(&in_state, &next_inputs_to_state(&next_input));
+
+        // The last byte of the block lands in lane (0, 4).
+        assert_eq!(
+            out_state[(0, 4)],
+            convert_b2_to_b9(u64::from_le_bytes(block[160..168].try_into().unwrap())) * A4
+        );
+
+        let in_state = state_bigint_to_field(in_state);
+        let out_state = state_bigint_to_field(out_state);
+        let circuit = MyCircuit::<Fp, RATE, false> {
+            in_state,
+            out_state,
+            next_input: next_input.clone(),
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Dropping the last lane of the block has to be caught.
+        let mut short_input = next_input;
+        short_input[RATE - 1] = Fp::zero();
+        let circuit = MyCircuit::<Fp, RATE, false> {
+            in_state,
+            out_state,
+            next_input: short_input,
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_absorb_kmac128_prefix() {
+        use crate::sp800_185::kmac_prefix;
+
+        /// Absorbs the first block of `prefix` into the zero state.
+        #[derive(Default)]
+        struct PrefixCircuit<F, const BIG_ENDIAN: bool> {
+            prefix: Vec<u8>,
+            out_state: [F; 25],
+        }
+
+        impl<F: Field, const BIG_ENDIAN: bool> Circuit<F> for PrefixCircuit<F, BIG_ENDIAN>
+        where
+            F: PrimeField<Repr = [u8; 32]>,
+        {
+            type Config = AbsorbConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    prefix: self.prefix.clone(),
+                    ..Self::default()
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                MyCircuit::<F, RATE, BIG_ENDIAN>::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let (flag, in_state) = layouter.assign_region(
+                    || "Witness flag and input state",
+                    |mut region| {
+                        let flag = region.assign_advice(
+                            || "assign is_mixing",
+                            config.state[FLAG_LANE],
+                            1,
+                            || Ok(F::one()),
+                        )?;
+                        let state = (0..25)
+                            .map(|idx| {
+                                region.assign_advice(
+                                    || "witness input state",
+                                    config.state[idx],
+                                    0,
+                                    || Ok(F::zero()),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        let state: [AssignedCell<F, F>; 25] = state.try_into().unwrap();
+                        Ok((flag, state))
+                    },
+                )?;
+                let blocks = config.assign_prefix(&mut layouter, &self.prefix)?;
+                config.copy_state_flag_next_inputs(
+                    &mut layouter,
+                    &in_state,
+                    self.out_state,
+                    &blocks[0],
+                    flag,
+                )?;
+                Ok(())
+            }
+        }
+
+        // The first block of a KMAC128 prefix, absorbed into the zero state
+        const RATE: usize = 21;
+        let prefix = kmac_prefix(RATE, &[0x42; 32], b"My Tagged Application");
+        let blocks = prefix_blocks(&prefix, RATE);
+        let next_input = blocks[0].iter().map(|&lane| Fp::from(lane)).collect_vec();
+        let in_state = StateBigInt::default();
+        let out_state = state_bigint_to_field(KeccakFArith::absorb(
+            &in_state,
+            &next_inputs_to_state(&next_input),
+        ));
+
+        // The prefix is read as spec lanes whatever the byte order
+        let circuit = PrefixCircuit::<Fp, false> {
+            prefix: prefix.clone(),
+            out_state,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let circuit = PrefixCircuit::<Fp, true> {
+            prefix: prefix.clone(),
+            out_state,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A prefix that isn't bytepadded to the rate is refused
+        let circuit = PrefixCircuit::<Fp, false> {
+            prefix: prefix[1..].to_vec(),
+            out_state,
+        };
+        assert!(matches!(
+            MockProver::<Fp>::run(9, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn test_absorb_big_endian_lanes() {
+        // The first lane of the block holds the bytes 01..08, the spec lane
+        // packs them in little endian.
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        let mut spec_input = vec![Fp::zero(); NEXT_INPUTS_LANES];
+        spec_input[0] = Fp::from(0x0807_0605_0403_0201);
+        let mut be_input = vec![Fp::zero(); NEXT_INPUTS_LANES];
+        be_input[0] = Fp::from(0x0102_0304_0506_0708);
+
+        let in_state = StateBigInt::default();
+        let out_state = state_bigint_to_field(KeccakFArith::absorb(
+            &in_state,
+            &next_inputs_to_state(&spec_input),
+        ));
+        assert_eq!(
+            out_state[0],
+            biguint_to_f(&(convert_b2_to_b9(u64::from_le_bytes(bytes)) * A4))
+        );
+        let in_state = state_bigint_to_field(in_state);
+
+        let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, true> {
+            in_state,
+            out_state,
+            next_input: be_input.clone(),
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Reading the big endian lanes as little endian ones absorbs other bytes
+        let circuit = MyCircuit::<Fp, NEXT_INPUTS_LANES, false> {
+            in_state,
+            out_state,
+            next_input: be_input,
+            is_mixing: true,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
@@ -5,6 +5,7 @@ use halo2_proofs::{
 };
 
 use super::tables::BaseInfo;
+use crate::gate_helpers::f_to_biguint;
 use eth_types::Field;
 use std::convert::TryInto;
 
@@ -17,6 +18,11 @@ pub(crate) struct BaseConversionConfig<F> {
     // constrained.
     flag: Column<Advice>,
     input_coef: Column<Advice>,
+    // The two accumulators could take turns on one column, the input running
+    // sum on the first rows of the region and the output one on the next.
+    // That needs a selector per phase and a copy of every output coef next to
+    // its accumulator, and doubles the rows of a lane. The columns are
+    // borrowed from the state anyway, so it would save no column.
     input_acc: Column<Advice>,
     output_coef: Column<Advice>,
     output_acc: Column<Advice>,
@@ -43,6 +49,8 @@ impl<F: Field> BaseConversionConfig<F> {
         meta.enable_equality(input_lane);
         meta.enable_equality(parent_flag);
 
+        // Degree 3 for both running sums: the selector times the flag times
+        // the accumulator, the power of base is a constant.
         meta.create_gate("input running sum", |meta| {
             let q_enable = meta.query_selector(q_running_sum);
             let flag = meta.query_advice(flag, Rotation::cur());
@@ -61,6 +69,9 @@ impl<F: Field> BaseConversionConfig<F> {
             let power_of_base = base_info.output_pob();
             vec![q_enable * flag * (acc - acc_prev * power_of_base - coef)]
         });
+        // The inputs have degree 3, the selector times the flag times the
+        // coef, so the lookup argument needs degree `2 + 3 + 1 = 6`. This is
+        // the highest degree of the Keccak-f circuit.
         meta.lookup("Lookup i/o_coeff at Base conversion table", |meta| {
             let q_enable = meta.query_selector(q_lookup);
             let flag = meta.query_advice(flag, Rotation::cur());
@@ -96,7 +107,28 @@ impl<F: Field> BaseConversionConfig<F> {
         let (input_coefs, output_coefs, _) = self
             .base_info
             .compute_coefs(input.value().copied().unwrap_or_default())?;
+        // The lookup rejects any coef out of `[0, input_pob)`, but a bad coef
+        // would still satisfy the running sum, so catch it early.
+        let input_pob = f_to_biguint(self.base_info.input_pob());
+        debug_assert!(
+            input_coefs
+                .iter()
+                .all(|&coef| f_to_biguint(coef) < input_pob),
+            "input coef exceeds base^num_chunks"
+        );
+        self.assign_coefs(layouter, &input, &input_coefs, &output_coefs, flag)
+    }
 
+    /// Assigns the running sums of the given coefs and returns the output
+    /// accumulator. The input accumulator ends on `input`.
+    fn assign_coefs(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: &AssignedCell<F, F>,
+        input_coefs: &[F],
+        output_coefs: &[F],
+        flag: AssignedCell<F, F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
         layouter.assign_region(
             || "Base conversion",
             |mut region| {
@@ -145,7 +177,7 @@ impl<F: Field> BaseConversionConfig<F> {
                         region.constrain_equal(input_acc_cell.cell(), input_coef_cell.cell())?;
                         region.constrain_equal(output_acc_cell.cell(), output_coef_cell.cell())?;
                     } else if offset == input_coefs.len() - 1 {
-                        //region.constrain_equal(input_acc_cell, input.0)?;
+                        region.constrain_equal(input_acc_cell.cell(), input.cell())?;
                         return Ok(output_acc_cell);
                     }
                 }
@@ -428,7 +460,11 @@ mod tests {
             pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
                 let table = FromBinaryTableConfig::configure(meta);
                 let state: [Column<Advice>; 25] = (0..25)
-                    .map(|_| meta.advice_column())
+                    .map(|_| {
+                        let column = meta.advice_column();
+                        meta.enable_equality(column);
+                        column
+                    })
                     .collect::<Vec<_>>()
                     .try_into()
                     .unwrap();
@@ -562,4 +598,119 @@ mod tests {
         let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_out_of_range_coef() {
+        #[derive(Debug, Clone)]
+        struct MyConfig<F> {
+            lane: Column<Advice>,
+            flag: Column<Advice>,
+            table: FromBinaryTableConfig<F>,
+            conversion: BaseConversionConfig<F>,
+        }
+
+        /// Assigns the coefs of `input` for the lane `lane`, optionally moving
+        /// one unit of a coef into the next one so that the next coef is out
+        /// of range but the running sum still holds.
+        #[derive(Default)]
+        struct MyCircuit<F> {
+            input: F,
+            lane: F,
+            tamper: bool,
+        }
+        impl<F: Field> Circuit<F> for MyCircuit<F> {
+            type Config = MyConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let table = FromBinaryTableConfig::configure(meta);
+                let lane = meta.advice_column();
+                let flag = meta.advice_column();
+                let advices = (0..5)
+                    .map(|_| meta.advice_column())
+                    .collect_vec()
+                    .try_into()
+                    .unwrap();
+                let base_info = table.get_base_info(false);
+                let conversion =
+                    BaseConversionConfig::configure(meta, base_info, lane, flag, advices);
+                MyConfig {
+                    lane,
+                    flag,
+                    table,
+                    conversion,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.table.load(&mut layouter)?;
+                let (lane, flag) = layouter.assign_region(
+                    || "Input lane",
+                    |mut region| {
+                        let lane = region.assign_advice(
+                            || "Input lane",
+                            config.lane,
+                            0,
+                            || Ok(self.lane),
+                        )?;
+                        let flag =
+                            region.assign_advice(|| "flag", config.flag, 0, || Ok(F::one()))?;
+                        Ok((lane, flag))
+                    },
+                )?;
+                let base_info = &config.conversion.base_info;
+                let (mut input_coefs, output_coefs, _) = base_info.compute_coefs(self.input)?;
+                if self.tamper {
+                    let idx = input_coefs
+                        .iter()
+                        .position(|coef| *coef != F::zero())
+                        .unwrap();
+                    input_coefs[idx] -= F::one();
+                    input_coefs[idx + 1] += base_info.input_pob();
+                }
+                config.conversion.assign_coefs(
+                    &mut layouter,
+                    &lane,
+                    &input_coefs,
+                    &output_coefs,
+                    flag,
+                )?;
+                Ok(())
+            }
+        }
+
+        let input = Fp::from(12345678u64);
+        let circuit = MyCircuit::<Fp> {
+            input,
+            lane: input,
+            tamper: false,
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = MyCircuit::<Fp> {
+            input,
+            lane: input,
+            tamper: true,
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // The coefs of another lane don't convert this one
+        let circuit = MyCircuit::<Fp> {
+            input,
+            lane: input + Fp::one(),
+            tamper: false,
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
 }
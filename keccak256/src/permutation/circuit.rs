@@ -1,15 +1,19 @@
 use crate::{
     arith_helpers::*,
-    common::{NEXT_INPUTS_LANES, PERMUTATION, ROUND_CONSTANTS},
+    common::{
+        BASE_NUM_OF_CHUNKS, NEXT_INPUTS_LANES, PERMUTATION, ROTATION_CONSTANTS, ROUND_CONSTANTS,
+    },
+    gate_helpers::{biguint_to_f, f_to_biguint, ConstraintReport},
     keccak_arith::*,
     permutation::{
         base_conversion::BaseConversionConfig,
         generic::GenericConfig,
         iota::IotaConstants,
         mixing::MixingConfig,
-        pi::pi_gate_permutation,
         rho::RhoConfig,
-        tables::{FromBase9TableConfig, StackableTable},
+        rho_helpers::slice_lane,
+        step::StateStep,
+        tables::{FromBase9TableConfig, StackableTable, MAX_CHUNKS, NUM_OF_B9_CHUNKS},
         theta::ThetaConfig,
         xi::XiConfig,
     },
@@ -17,11 +21,21 @@ use crate::{
 use eth_types::Field;
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region},
-    plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Instance, Selector, TableColumn},
     poly::Rotation,
 };
 use itertools::Itertools;
 use std::convert::TryInto;
+
+/// Converts the 25 lanes of a base 9 state to binary and exposes them as
+/// public inputs.
+#[derive(Clone, Debug)]
+struct FullStateConfig<F> {
+    base_conversion: BaseConversionConfig<F>,
+    flag: Column<Advice>,
+    instance: Column<Instance>,
+}
+
 #[derive(Clone, Debug)]
 pub struct KeccakFConfig<F: Field> {
     generic: GenericConfig<F>,
@@ -33,13 +47,37 @@ pub struct KeccakFConfig<F: Field> {
     base_conversion_config: BaseConversionConfig<F>,
     mixing_config: MixingConfig<F>,
     pub state: [Column<Advice>; 25],
+    constant: Column<Fixed>,
     q_out: Selector,
     base_conv_activator: Column<Advice>,
+    full_state: Option<FullStateConfig<F>>,
+    num_rounds: usize,
 }
 
 impl<F: Field> KeccakFConfig<F> {
     // We assume state is received in base-9.
     pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        Self::configure_with_full_state(meta, false)
+    }
+
+    /// Same as [`Self::configure`]. With `expose_full_state`, an instance
+    /// column is allocated to expose the whole output state with
+    /// [`Self::expose_state`], not only the digest.
+    pub fn configure_with_full_state(
+        meta: &mut ConstraintSystem<F>,
+        expose_full_state: bool,
+    ) -> Self {
+        Self::configure_with_report(meta, expose_full_state, &mut ConstraintReport::default())
+    }
+
+    /// Same as [`Self::configure_with_full_state`], recording in `report` the
+    /// gates and lookups of each sub-config, to see where the constraints of
+    /// the permutation come from.
+    pub fn configure_with_report(
+        meta: &mut ConstraintSystem<F>,
+        expose_full_state: bool,
+        report: &mut ConstraintReport,
+    ) -> Self {
         let state: [Column<Advice>; 25] = (0..25)
             .map(|_| {
                 let column = meta.advice_column();
@@ -51,62 +89,100 @@ impl<F: Field> KeccakFConfig<F> {
             .unwrap();
 
         let fixed = meta.fixed_column();
-        let generic = GenericConfig::configure(meta, state[0..3].try_into().unwrap(), fixed);
+        let generic = report.measure("generic", meta, |meta| {
+            GenericConfig::configure(meta, state[0..3].try_into().unwrap(), fixed)
+        });
         let table_cols: [TableColumn; 3] = (0..3)
             .map(|_| meta.lookup_table_column())
             .collect_vec()
             .try_into()
             .unwrap();
-        let stackable =
-            StackableTable::configure(meta, state[0..3].try_into().unwrap(), table_cols);
+        let stackable = report.measure("stackable table", meta, |meta| {
+            StackableTable::configure(meta, state[0..3].try_into().unwrap(), table_cols)
+        });
 
         // theta
-        let theta_config = ThetaConfig::configure(meta.selector(), meta, state);
+        let theta_config = report.measure("theta", meta, |meta| {
+            ThetaConfig::configure(meta.selector(), meta, state)
+        });
         // rho
-        let rho_config =
-            RhoConfig::configure(meta, state, fixed, generic.clone(), stackable.clone());
+        let rho_config = report.measure("rho", meta, |meta| {
+            RhoConfig::configure(meta, state, fixed, generic.clone(), stackable.clone())
+        });
         // xi
-        let xi_config = XiConfig::configure(meta.selector(), meta, state);
+        let xi_config = report.measure("xi", meta, |meta| {
+            XiConfig::configure(meta.selector(), meta, state)
+        });
 
         // Allocate space for the activation flag of the base_conversion.
         let base_conv_activator = meta.advice_column();
         meta.enable_equality(base_conv_activator);
         // Base conversion config.
-        let from_b9_table = FromBase9TableConfig::configure(meta);
+        let from_b9_table =
+            report.measure("from base 9 table", meta, FromBase9TableConfig::configure);
         let base_info = from_b9_table.get_base_info(false);
         let base_conv_lane = meta.advice_column();
-        let base_conversion_config = BaseConversionConfig::configure(
-            meta,
-            base_info,
-            base_conv_lane,
-            base_conv_activator,
-            state[0..5].try_into().unwrap(),
-        );
+        let base_conversion_config = report.measure("base conversion", meta, |meta| {
+            BaseConversionConfig::configure(
+                meta,
+                base_info,
+                base_conv_lane,
+                base_conv_activator,
+                state[0..5].try_into().unwrap(),
+            )
+        });
 
         // Mixing will make sure that the flag is binary constrained and that
         // the out state matches the expected result.
-        let mixing_config = MixingConfig::configure(
-            meta,
-            &from_b9_table,
-            state,
-            generic.clone(),
-            stackable.clone(),
-        );
+        let mixing_config = report.measure("mixing", meta, |meta| {
+            MixingConfig::configure(
+                meta,
+                &from_b9_table,
+                state,
+                generic.clone(),
+                stackable.clone(),
+            )
+        });
 
         // Allocate the `out state correctness` gate selector
         let q_out = meta.selector();
         // Constraint the out of the mixing gate to be equal to the out state
         // announced.
-        meta.create_gate("Constraint out_state correctness", |meta| {
-            (0..25usize)
-                .into_iter()
-                .map(|idx| {
-                    let q_out = meta.query_selector(q_out);
-                    let out_mixing = meta.query_advice(state[idx], Rotation::cur());
-                    let out_expected_state = meta.query_advice(state[idx], Rotation::next());
-                    q_out * (out_mixing - out_expected_state)
-                })
-                .collect_vec()
+        // Degree 2: the selector times the difference of the states.
+        report.measure("out state", meta, |meta| {
+            meta.create_gate("Constraint out_state correctness", |meta| {
+                (0..25usize)
+                    .into_iter()
+                    .map(|idx| {
+                        let q_out = meta.query_selector(q_out);
+                        let out_mixing = meta.query_advice(state[idx], Rotation::cur());
+                        let out_expected_state = meta.query_advice(state[idx], Rotation::next());
+                        q_out * (out_mixing - out_expected_state)
+                    })
+                    .collect_vec()
+            })
+        });
+
+        let full_state = expose_full_state.then(|| {
+            let flag = meta.advice_column();
+            meta.enable_equality(flag);
+            let lane = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            let base_conversion = report.measure("full state", meta, |meta| {
+                BaseConversionConfig::configure(
+                    meta,
+                    from_b9_table.get_base_info(true),
+                    lane,
+                    flag,
+                    state[0..5].try_into().unwrap(),
+                )
+            });
+            FullStateConfig {
+                base_conversion,
+                flag,
+                instance,
+            }
         });
 
         KeccakFConfig {
@@ -119,60 +195,92 @@ impl<F: Field> KeccakFConfig<F> {
             base_conversion_config,
             mixing_config,
             state,
+            constant: fixed,
             q_out,
             base_conv_activator,
+            full_state,
+            num_rounds: PERMUTATION,
         }
     }
 
+    /// The base 9 to binary table loaded by [`Self::load`], shared with the
+    /// configs reading the output state.
+    pub(crate) fn from_b9_table(&self) -> &FromBase9TableConfig<F> {
+        &self.from_b9_table
+    }
+
+    /// The fixed column holding the constants of the permutation, shared with
+    /// the configs that assign their own constants.
+    pub(crate) fn constant(&self) -> Column<Fixed> {
+        self.constant
+    }
+
+    /// Runs only the first `num_rounds` rounds of the permutation, to check a
+    /// round on its own against a reduced reference. The constraints don't
+    /// change, only the rounds assigned by [`Self::assign_all`] and the round
+    /// constants of the last one.
+    ///
+    /// Returns [`Error::Synthesis`] unless `num_rounds` is in `1..=24`.
+    pub fn with_rounds(mut self, num_rounds: usize) -> Result<Self, Error> {
+        if !(1..=PERMUTATION).contains(&num_rounds) {
+            return Err(Error::Synthesis);
+        }
+        self.mixing_config = self.mixing_config.with_rounds(num_rounds);
+        self.num_rounds = num_rounds;
+        Ok(self)
+    }
+
     pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         self.stackable.load(layouter)?;
         self.rho_config.load(layouter)?;
         self.from_b9_table.load(layouter)
     }
 
+    /// Assigns the rounds of the permutation, then the mixing.
+    ///
+    /// Every step gets its own regions and the state is carried between them
+    /// with copy constraints: Theta and Xi copy the 25 lanes into their input
+    /// row, Rho and the base conversion copy each lane into the region of the
+    /// lane, and Pi only reorders the cells. The steps can't share a region
+    /// since they lay out the same `state` columns differently, the gates of
+    /// Theta and Xi read the next row while Rho and the base conversion run
+    /// their accumulators down the first columns. The base conversion flag is
+    /// fixed to one once and copied into the conversions of every round.
     pub fn assign_all(
         &self,
         layouter: &mut impl Layouter<F>,
         in_state: [AssignedCell<F, F>; 25],
         out_state: [F; 25],
         flag: bool,
-        next_mixing: Option<[F; NEXT_INPUTS_LANES]>,
+        next_mixing: Option<&[AssignedCell<F, F>]>,
     ) -> Result<[AssignedCell<F, F>; 25], Error> {
         let mut state = in_state;
+        let activation_flag = layouter.assign_region(
+            || "Base conversion enable",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "Enable base conversion",
+                    self.base_conv_activator,
+                    0,
+                    F::one(),
+                )
+            },
+        )?;
 
         // First 23 rounds
-        for round_idx in 0..PERMUTATION {
+        for round_idx in 0..self.num_rounds {
             // State in base-13
             // theta
-            state = {
-                // Apply theta outside circuit
-                let out_state =
-                    KeccakFArith::theta(&state_to_biguint(split_state_cells(state.clone())));
-                let out_state = state_bigint_to_field(out_state);
-                // assignment
-                self.theta_config
-                    .assign_state(layouter, &state, out_state)?
-            };
-
-            // rho
-            state = self.rho_config.assign_rotation_checks(layouter, &state)?;
-            // Outputs in base-9 which is what Pi requires
+            state = self.theta_config.assign_step(layouter, &state)?;
 
-            // Apply Pi permutation
-            state = pi_gate_permutation(state.clone());
+            // rho and pi, outputs in base-9 which is what xi requires
+            state = self.rho_config.assign_step(layouter, &state)?;
 
             // xi
-            state = {
-                // Apply xi outside circuit
-                let out_state =
-                    KeccakFArith::xi(&state_to_biguint(split_state_cells(state.clone())));
-                let out_state = state_bigint_to_field(out_state);
-                // assignment
-                self.xi_config.assign_state(layouter, &state, out_state)?
-            };
+            state = self.xi_config.assign_step(layouter, &state)?;
 
             // Last round before Mixing does not run IotaB9 nor BaseConversion
-            if round_idx == PERMUTATION - 1 {
+            if round_idx == self.num_rounds - 1 {
                 break;
             }
 
@@ -187,31 +295,20 @@ impl<F: Field> KeccakFConfig<F> {
             // The resulting state is in Base-9 now. We now convert it to
             // base_13 which is what Theta requires again at the
             // start of the loop.
-            state = {
-                let activation_flag = layouter.assign_region(
-                    || "Base conversion enable",
-                    |mut region| {
-                        region.assign_advice(
-                            || "Enable base conversion",
-                            self.base_conv_activator,
-                            0,
-                            || Ok(F::one()),
-                        )
-                    },
-                )?;
-
-                self.base_conversion_config
-                    .assign_state(layouter, &state, activation_flag)?
-            }
+            state = self.base_conversion_config.assign_state(
+                layouter,
+                &state,
+                activation_flag.clone(),
+            )?;
         }
 
         // Mixing step
         let mix_res = KeccakFArith::mixing(
             &state_to_biguint(split_state_cells(state.clone())),
             next_mixing
-                .map(|state| state_to_state_bigint::<F, NEXT_INPUTS_LANES>(state))
+                .map(|inputs| next_inputs_to_state(&block_to_binary(inputs)))
                 .as_ref(),
-            *ROUND_CONSTANTS.last().unwrap(),
+            ROUND_CONSTANTS[self.num_rounds - 1],
         );
 
         let mix_res = self.mixing_config.assign_state(
@@ -225,6 +322,107 @@ impl<F: Field> KeccakFConfig<F> {
         self.constrain_out_state(layouter, &mix_res, out_state)
     }
 
+    /// Witnesses the lanes of a block given in binary, in the spec order, as
+    /// the base 9 lanes the permutations absorb. The lanes aren't bound to any
+    /// input.
+    pub fn assign_next_inputs(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        next_inputs: &[F],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        self.mixing_config
+            .absorb_config()
+            .assign_next_inputs(layouter, next_inputs)
+    }
+
+    /// Absorbs the first `block` into `initial_state` and returns the base 13
+    /// input state of the first permutation.
+    ///
+    /// The initial lanes are in the spec order `x + 5 * y` and are fixed in
+    /// the circuit. They are laid out in base 9 times `A1`, so the absorb and
+    /// the base conversion of the mixing XOR the base 9 `block` into them.
+    pub fn absorb_initial(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initial_state: [u64; 25],
+        block: &[AssignedCell<F, F>],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+            || "Initial state",
+            |mut region| {
+                let state = (0..25)
+                    .map(|idx| {
+                        // The mapping is a transpose, its own inverse
+                        let lane = initial_state[block_lane_index(idx)];
+                        region.assign_advice_from_constant(
+                            || "initial lane",
+                            self.state[idx],
+                            0,
+                            biguint_to_f::<F>(&(convert_b2_to_b9(lane) * A1)),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(state.try_into().unwrap())
+            },
+        )?;
+        self.mixing_config.absorb(layouter, &state, block)
+    }
+
+    /// Absorbs the `blocks` one per permutation, then runs the final
+    /// permutation without mixing and returns its base 9 output state.
+    ///
+    /// `state` is the base 13 input of the first permutation, with the first
+    /// block already absorbed by [`Self::absorb_initial`]. The blocks hold
+    /// the base 9 lanes of the rate, already padded. The state cells are
+    /// threaded from one permutation to the next.
+    pub fn absorb_blocks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: [AssignedCell<F, F>; 25],
+        blocks: impl Iterator<Item = Vec<AssignedCell<F, F>>>,
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let mut state = state;
+        for block in blocks {
+            state = self.absorb_block(layouter, state, &block)?;
+        }
+        self.permute(layouter, state, None)
+    }
+
+    /// Runs a permutation from the base 13 `state` and absorbs the base 9
+    /// `block` at its end, returning the base 13 input of the next one.
+    pub fn absorb_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: [AssignedCell<F, F>; 25],
+        block: &[AssignedCell<F, F>],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        self.permute(layouter, state, Some(block))
+    }
+
+    /// Computes the output state of a permutation and assigns it.
+    fn permute(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: [AssignedCell<F, F>; 25],
+        next_mixing: Option<&[AssignedCell<F, F>]>,
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let mut out_state = state_to_biguint(split_state_cells(state.clone()));
+        KeccakFArith::permute_and_absorb_rounds(
+            &mut out_state,
+            next_mixing
+                .map(|inputs| next_inputs_to_state(&block_to_binary(inputs)))
+                .as_ref(),
+            self.num_rounds,
+        );
+        self.assign_all(
+            layouter,
+            state,
+            state_bigint_to_field(out_state),
+            next_mixing.is_some(),
+            next_mixing,
+        )
+    }
+
     pub fn constrain_out_state(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -260,6 +458,49 @@ impl<F: Field> KeccakFConfig<F> {
         )
     }
 
+    /// Converts the lanes of `state` to binary and constrains them to the
+    /// instance column, lane `(x, y)` at row `x + 5 * y` as in the spec.
+    ///
+    /// The state has to be in base 9, which is the case for the output of
+    /// [`Self::assign_all`] when it doesn't mix. The state of a permutation
+    /// that mixes is in base 13 and fails the conversion lookups.
+    ///
+    /// Panics if the config wasn't built with
+    /// [`Self::configure_with_full_state`].
+    pub fn expose_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let full_state = self
+            .full_state
+            .as_ref()
+            .expect("the config doesn't expose the full state");
+        let flag = layouter.assign_region(
+            || "Full state conversion enable",
+            |mut region| {
+                region.assign_advice_from_constant(
+                    || "Enable conversion",
+                    full_state.flag,
+                    0,
+                    F::one(),
+                )
+            },
+        )?;
+        let lanes = (0..25)
+            .map(|i| {
+                let lane = full_state.base_conversion.assign_lane(
+                    layouter,
+                    state[block_lane_index(i)].clone(),
+                    flag.clone(),
+                )?;
+                layouter.constrain_instance(lane.cell(), full_state.instance, i)?;
+                Ok(lane)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(lanes.try_into().unwrap())
+    }
+
     /// Copies the `state` cells to the passed [Column<Advice>; 25].
     fn copy_state(
         &self,
@@ -281,11 +522,114 @@ impl<F: Field> KeccakFConfig<F> {
     }
 }
 
+/// The binary lanes of a base 9 block, to compute the states of a
+/// permutation absorbing it.
+fn block_to_binary<F: Field>(block: &[AssignedCell<F, F>]) -> Vec<F> {
+    block
+        .iter()
+        .map(|lane| {
+            let lane = lane
+                .value()
+                .map(|lane| sparse_to_dense(&f_to_biguint(*lane), B9))
+                .unwrap_or_default();
+            F::from(lane)
+        })
+        .collect()
+}
+
+/// Rows of a region of [`GenericConfig`] adding two cells.
+const ADD_ROWS: usize = 2;
+/// Rows of a `theta` or `xi` region.
+const STEP_ROWS: usize = 2;
+/// Rows kept by halo2 at the end of the circuit for the blinding factors.
+const BLINDING_ROWS: usize = 10;
+
+/// Rows of a linear combination of `n` cells in [`GenericConfig`].
+fn linear_combine_rows(n: usize) -> usize {
+    n + 1
+}
+
+/// Rows of the base conversion of one lane out of base 9.
+pub(crate) fn lane_conversion_rows() -> usize {
+    (MAX_CHUNKS + NUM_OF_B9_CHUNKS - 1) / NUM_OF_B9_CHUNKS
+}
+
+/// Rows of the base 9 to base 13 conversion of the 25 lanes.
+fn base_conversion_rows() -> usize {
+    25 * lane_conversion_rows()
+}
+
+/// Rows of the Rho checks of the 25 lanes, including the overflow detector
+/// range checks.
+fn rho_rows() -> usize {
+    let slices = ROTATION_CONSTANTS
+        .iter()
+        .flatten()
+        .map(|&rotation| slice_lane(rotation))
+        .collect_vec();
+    let lanes: usize = slices
+        .iter()
+        .map(|slices| {
+            let n = slices.len();
+            // Lane region with the special chunk, the input running sum, the
+            // subtraction of the special chunks, their lookup and the output
+            // running sum.
+            (n + 1) + linear_combine_rows(n) + ADD_ROWS + 1 + linear_combine_rows(n + 1)
+        })
+        .sum();
+    let step_count = |step: u32| slices.iter().flatten().filter(|&&(_, s)| s == step).count();
+    lanes + linear_combine_rows(step_count(2)) + linear_combine_rows(step_count(3)) + 2
+}
+
+/// Rows used by one call of [`KeccakFConfig::assign_all`], plus the row
+/// witnessing its input state.
+fn permutation_rows() -> usize {
+    let round = STEP_ROWS + rho_rows() + STEP_ROWS;
+    // IotaB9 and the base conversion
+    let inner_round = round + ADD_ROWS + base_conversion_rows();
+    // Boolean flag, IotaB9, the zero block of a permutation that doesn't
+    // mix, absorb, base conversion, IotaB13, out mixing states and out state
+    let mixing = 1 + ADD_ROWS + 1 + 3 + base_conversion_rows() + ADD_ROWS + 3 + 2;
+    // The input state and the base conversion enable flag
+    2 + (PERMUTATION - 1) * inner_round + round + mixing
+}
+
+/// Rows of the largest lookup table, the base 9 to base 13 table. The base 13
+/// to base 9 table of Rho and the stackable table are smaller.
+fn table_rows() -> usize {
+    let b9_to_b13 = (B9 as usize).pow(NUM_OF_B9_CHUNKS as u32);
+    let b13_to_b9 = (B13 as usize).pow(BASE_NUM_OF_CHUNKS);
+    b9_to_b13.max(b13_to_b9)
+}
+
+/// Returns an upper bound on the rows needed to hash `input_len` bytes with
+/// Keccak-256, one [`KeccakFConfig::assign_all`] per padded block.
+///
+/// The rows are counted region by region. Since the regions are laid out one
+/// after the other on shared columns, this doesn't account for the regions the
+/// floor planner manages to place side by side.
+pub fn estimate_rows(input_len: usize) -> usize {
+    (padded_blocks(input_len) * permutation_rows()).max(table_rows()) + BLINDING_ROWS
+}
+
+/// The number of blocks of an input of `input_len` bytes once padded, which
+/// is the number of permutations hashing it.
+pub(crate) fn padded_blocks(input_len: usize) -> usize {
+    // The padding always adds at least one byte
+    input_len / (NEXT_INPUTS_LANES * 8) + 1
+}
+
+/// Returns the smallest `k` whose `2^k` rows fit [`estimate_rows`].
+pub fn estimate_min_k(input_len: usize) -> u32 {
+    let rows = estimate_rows(input_len);
+    (0..usize::BITS).find(|k| 1 << k >= rows).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::common::{State, NEXT_INPUTS_LANES};
-    use crate::gate_helpers::biguint_to_f;
+    use crate::gate_helpers::{biguint_to_f, f_to_biguint};
     use halo2_proofs::circuit::Layouter;
     use halo2_proofs::pairing::bn256::Fr as Fp;
     use halo2_proofs::plonk::{ConstraintSystem, Error};
@@ -349,12 +693,16 @@ mod tests {
                     },
                 )?;
 
+                let next_mixing = self
+                    .next_mixing
+                    .map(|next_mixing| config.assign_next_inputs(&mut layouter, &next_mixing))
+                    .transpose()?;
                 config.assign_all(
                     &mut layouter,
                     in_state,
                     self.out_state,
                     self.is_mixing,
-                    self.next_mixing,
+                    next_mixing.as_deref(),
                 )?;
                 Ok(())
             }
@@ -472,4 +820,546 @@ mod tests {
             assert!(prover.verify().is_err());
         }
     }
+
+    #[test]
+    fn test_expose_full_state() {
+        #[derive(Default)]
+        struct MyCircuit<F> {
+            in_state: [F; 25],
+            out_state: [F; 25],
+        }
+
+        impl<F: Field> Circuit<F> for MyCircuit<F> {
+            type Config = KeccakFConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Self::Config::configure_with_full_state(meta, true)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let in_state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+                    || "Witness input state",
+                    |mut region| {
+                        let state: Result<Vec<AssignedCell<F, F>>, Error> = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, val)| {
+                                region.assign_advice(
+                                    || "witness input state",
+                                    config.state[idx],
+                                    0,
+                                    || Ok(*val),
+                                )
+                            })
+                            .collect();
+                        Ok(state?.try_into().unwrap())
+                    },
+                )?;
+                let out_state =
+                    config.assign_all(&mut layouter, in_state, self.out_state, false, None)?;
+                config.expose_state(&mut layouter, &out_state)?;
+                Ok(())
+            }
+        }
+
+        // The input lanes in the spec order
+        let spec_in: [u64; 25] = (0..25u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let mut in_state = StateBigInt::default();
+        for (i, &lane) in spec_in.iter().enumerate() {
+            in_state[(i % 5, i / 5)] = convert_b2_to_b13(lane);
+        }
+        let mut out_state = in_state.clone();
+        KeccakFArith::permute_and_absorb(&mut out_state, None);
+
+        let spec_out = crate::reference::keccak_f1600(spec_in);
+        let instance = spec_out.iter().map(|&lane| Fp::from(lane)).collect_vec();
+        let circuit = MyCircuit::<Fp> {
+            in_state: state_bigint_to_field(in_state),
+            out_state: state_bigint_to_field(out_state),
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong_instance = instance;
+        wrong_instance[7] += Fp::one();
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![wrong_instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_absorb_blocks() {
+        /// Hashes `input` with one permutation per padded block, checking the
+        /// digest against the reference.
+        #[derive(Default)]
+        struct StreamCircuit<F> {
+            input: Vec<u8>,
+            _marker: std::marker::PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for StreamCircuit<F> {
+            type Config = KeccakFConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                // The number of permutations depends on the input length
+                Self {
+                    input: vec![0; self.input.len()],
+                    _marker: std::marker::PhantomData,
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Self::Config::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let blocks = PaddedBlocks::new(self.input.iter().copied())
+                    .map(|block| config.assign_next_inputs(&mut layouter, &block.map(F::from)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let mut blocks = blocks.into_iter();
+                // There's always a block, the padding of the empty input fills one
+                let first = blocks.next().unwrap();
+                let in_state = config.absorb_initial(&mut layouter, [0; 25], &first)?;
+                let out_state = config.absorb_blocks(&mut layouter, in_state, blocks)?;
+
+                // The values are unknown at keygen
+                let digest = [0, 5, 10, 15]
+                    .iter()
+                    .map(|&idx| out_state[idx].value().copied())
+                    .collect::<Option<Vec<_>>>();
+                if let Some(digest) = digest {
+                    let digest = digest
+                        .into_iter()
+                        .flat_map(|lane| convert_b9_lane_to_b2(f_to_biguint(lane)).to_le_bytes())
+                        .collect_vec();
+                    assert_eq!(digest, crate::reference::keccak256(&self.input).to_vec());
+                }
+                Ok(())
+            }
+        }
+
+        // Two blocks, the second one is absorbed in the first permutation
+        let input: Vec<u8> = (0..200).map(|_| rand::random::<u8>()).collect();
+        let circuit = StreamCircuit::<Fp> {
+            input,
+            _marker: std::marker::PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        use crate::circuit::hash::Keccak256Circuit;
+
+        // A single block holding only the padding, and a single permutation
+        assert_eq!(PaddedBlocks::new(std::iter::empty()).count(), 1);
+        let empty_hash: [u8; 32] = [
+            0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7,
+            0x03, 0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04,
+            0x5d, 0x85, 0xa4, 0x70,
+        ];
+        assert_eq!(crate::reference::keccak256(&[]), empty_hash);
+
+        // The digest is exposed by the circuit, through the padding, the
+        // absorb, the permutation and the squeeze
+        let circuit = Keccak256Circuit::<Fp>::new(vec![]);
+        let instance = circuit.instance();
+        let expected = empty_hash
+            .chunks(8)
+            .map(|lane| Fp::from(u64::from_le_bytes(lane.try_into().unwrap())))
+            .collect_vec();
+        assert_eq!(instance, expected);
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![instance.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        for lane in 0..instance.len() {
+            let mut wrong_instance = instance.clone();
+            wrong_instance[lane] += Fp::one();
+            let prover = MockProver::<Fp>::run(17, &circuit, vec![wrong_instance]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn test_reduced_rounds() {
+        #[derive(Default)]
+        struct ReducedCircuit<F> {
+            in_state: [u64; 25],
+            num_rounds: usize,
+            _marker: std::marker::PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for ReducedCircuit<F> {
+            type Config = KeccakFConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    num_rounds: self.num_rounds,
+                    ..Self::default()
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Self::Config::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let config = config.with_rounds(self.num_rounds)?;
+                config.load(&mut layouter)?;
+                let in_state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+                    || "Witness input state",
+                    |mut region| {
+                        let state: Result<Vec<AssignedCell<F, F>>, Error> = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, &lane)| {
+                                region.assign_advice(
+                                    || "witness input state",
+                                    config.state[idx],
+                                    0,
+                                    || Ok(biguint_to_f(&convert_b2_to_b13(lane))),
+                                )
+                            })
+                            .collect();
+                        Ok(state?.try_into().unwrap())
+                    },
+                )?;
+                let out_state =
+                    config.absorb_blocks(&mut layouter, in_state, std::iter::empty())?;
+
+                let mut expected: State = [[0; 5]; 5];
+                for (idx, &lane) in self.in_state.iter().enumerate() {
+                    expected[idx / 5][idx % 5] = lane;
+                }
+                crate::plain::KeccakF::default()
+                    .reduced_permutations(&mut expected, self.num_rounds);
+                for (idx, lane) in out_state.iter().enumerate() {
+                    if let Some(&lane) = lane.value() {
+                        let lane = convert_b9_lane_to_b2(f_to_biguint(lane));
+                        assert_eq!(lane, expected[idx / 5][idx % 5]);
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        let in_state: [u64; 25] = (0..25)
+            .map(|_| rand::random::<u64>())
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        for num_rounds in [1, 2] {
+            let circuit = ReducedCircuit::<Fp> {
+                in_state,
+                num_rounds,
+                _marker: std::marker::PhantomData,
+            };
+            let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+
+        // No round, or more rounds than the permutation has
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let config = KeccakFConfig::<Fp>::configure(&mut meta);
+        for num_rounds in [0, PERMUTATION + 1] {
+            assert!(matches!(
+                config.clone().with_rounds(num_rounds),
+                Err(Error::Synthesis)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_state_steps() {
+        #[derive(Default)]
+        struct StepsCircuit<F> {
+            in_state: [F; 25],
+            out_state: [F; 25],
+        }
+
+        impl<F: Field> Circuit<F> for StepsCircuit<F> {
+            type Config = KeccakFConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                KeccakFConfig::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let mut state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+                    || "Witness state",
+                    |mut region| {
+                        let state = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, &value)| {
+                                region.assign_advice(|| "lane", config.state[idx], 0, || Ok(value))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        Ok(state.try_into().unwrap())
+                    },
+                )?;
+                let steps: Vec<Box<dyn StateStep<F, _>>> = vec![
+                    Box::new(config.theta_config.clone()),
+                    Box::new(config.rho_config.clone()),
+                    Box::new(config.xi_config.clone()),
+                ];
+                for step in steps.iter() {
+                    state = step.assign_step(&mut layouter, &state)?;
+                }
+                config.constrain_out_state(&mut layouter, &state, self.out_state)?;
+                Ok(())
+            }
+        }
+
+        let mut in_state = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_state[(x, y)] = convert_b2_to_b13(rand::random::<u64>());
+        }
+        let theta = KeccakFArith::theta(&in_state);
+        let out_state = KeccakFArith::xi(&KeccakFArith::pi(&KeccakFArith::rho(&theta)));
+        let circuit = StepsCircuit::<Fp> {
+            in_state: state_bigint_to_field(in_state),
+            out_state: state_bigint_to_field(out_state),
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_configure_over_fq() {
+        // The configs only ask for `eth_types::Field`, the base field of
+        // BN256 implements it as well as the scalar field.
+        use halo2_proofs::pairing::bn256::Fq;
+        let mut meta = ConstraintSystem::<Fq>::default();
+        KeccakFConfig::configure(&mut meta);
+        assert_eq!(meta.degree(), 6);
+    }
+
+    #[test]
+    fn test_degree() {
+        // The base conversion lookup sets the degree, see
+        // `BaseConversionConfig::configure`. A higher degree would need a
+        // larger extended domain for the proof.
+        const MAX_DEGREE: usize = 6;
+        for expose_full_state in [false, true] {
+            let mut meta = ConstraintSystem::<Fp>::default();
+            KeccakFConfig::configure_with_full_state(&mut meta, expose_full_state);
+            assert_eq!(meta.degree(), MAX_DEGREE);
+        }
+    }
+
+    #[test]
+    fn test_constraint_report() {
+        use crate::gate_helpers::ConstraintCount;
+
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let mut report = ConstraintReport::default();
+        KeccakFConfig::configure_with_report(&mut meta, false, &mut report);
+
+        let total = report.total();
+        assert_eq!(total.gates, meta.gates().len());
+        assert_eq!(total.lookups, meta.lookups().len());
+        assert!(report.get("full state").is_none());
+
+        let count = |gates, polynomials, lookups, max_degree| ConstraintCount {
+            gates,
+            polynomials,
+            lookups,
+            max_degree,
+        };
+        for (config, expected) in [
+            ("generic", count(1, 1, 0, 3)),
+            ("stackable table", count(0, 0, 1, 0)),
+            // A polynomial per lane
+            ("theta", count(1, 25, 0, 2)),
+            // The lane conversions only add the lookup of the base 13 to base
+            // 9 table, their sums go through the generic gate
+            ("rho", count(0, 0, 1, 0)),
+            ("xi", count(1, 25, 0, 2)),
+            ("from base 9 table", count(0, 0, 0, 0)),
+            // A running sum for the input and one for the output, and the
+            // lookup of the coefs
+            ("base conversion", count(2, 2, 1, 3)),
+            // The absorb gate, its own base conversion and the copy of the
+            // result
+            ("mixing", count(4, 28, 1, 3)),
+            ("out state", count(1, 25, 0, 2)),
+        ] {
+            assert_eq!(report.get(config), Some(expected), "{}", config);
+        }
+    }
+
+    #[test]
+    fn test_one_block_table_rows() {
+        // The base 13 table isn't the largest one, and the permutation of a
+        // single block takes more rows than any table. Loading a part of the
+        // base 13 table can't lower `k` for short inputs.
+        let base13_rows = (B13 as usize).pow(BASE_NUM_OF_CHUNKS);
+        assert_eq!(base13_rows, 28561);
+        assert!(base13_rows < table_rows());
+        assert!(permutation_rows() > table_rows());
+        assert_eq!(
+            estimate_rows(0),
+            permutation_rows().max(base13_rows) + BLINDING_ROWS
+        );
+    }
+
+    #[test]
+    fn test_padded_blocks() {
+        assert_eq!(padded_blocks(0), 1);
+        assert_eq!(padded_blocks(NEXT_INPUTS_LANES * 8 - 1), 1);
+        assert_eq!(padded_blocks(NEXT_INPUTS_LANES * 8), 2);
+    }
+
+    #[test]
+    fn test_estimate_rows() {
+        // The 9^5 rows of the largest table fit in 2^16, a single permutation
+        // doesn't
+        assert!(table_rows() < 1 << 16);
+        assert!(estimate_rows(0) > 1 << 16);
+        assert_eq!(estimate_min_k(0), 17);
+        // Same number of padded blocks
+        assert_eq!(estimate_rows(0), estimate_rows(135));
+        assert!(estimate_rows(136) >= estimate_rows(135));
+        let mut k = 0;
+        for input_len in (0..10).map(|blocks| blocks * 136) {
+            let min_k = estimate_min_k(input_len);
+            assert!(min_k >= k);
+            assert!(1 << min_k >= estimate_rows(input_len));
+            k = min_k;
+        }
+    }
+
+    #[test]
+    fn test_estimate_is_upper_bound() {
+        /// Chains one permutation per block, absorbing zero blocks.
+        #[derive(Default)]
+        struct ChainCircuit<F> {
+            in_state: [F; 25],
+            out_states: Vec<[F; 25]>,
+        }
+
+        impl<F: Field> Circuit<F> for ChainCircuit<F> {
+            type Config = KeccakFConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    in_state: [F::zero(); 25],
+                    out_states: vec![[F::zero(); 25]; self.out_states.len()],
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                Self::Config::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let mut state = layouter.assign_region(
+                    || "Witness input state",
+                    |mut region| {
+                        let state: Result<Vec<AssignedCell<F, F>>, Error> = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, val)| {
+                                region.assign_advice(
+                                    || "witness input state",
+                                    config.state[idx],
+                                    0,
+                                    || Ok(*val),
+                                )
+                            })
+                            .collect();
+                        Ok(state?.try_into().unwrap())
+                    },
+                )?;
+                // Every permutation but the last mixes, to feed the next one
+                // a base 13 state
+                for (idx, out_state) in self.out_states.iter().enumerate() {
+                    let is_mixing = idx + 1 < self.out_states.len();
+                    let block = if is_mixing {
+                        let zeros = [F::zero(); NEXT_INPUTS_LANES];
+                        Some(config.assign_next_inputs(&mut layouter, &zeros)?)
+                    } else {
+                        None
+                    };
+                    state = config.assign_all(
+                        &mut layouter,
+                        state,
+                        *out_state,
+                        is_mixing,
+                        block.as_deref(),
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut state = StateBigInt::default();
+        state[(0, 0)] = convert_b2_to_b13(1);
+        let in_state = state_bigint_to_field(state.clone());
+        for input_len in [0, 136] {
+            let blocks = input_len / 136 + 1;
+            let mut out_states = vec![];
+            let mut out_state = state.clone();
+            for idx in 0..blocks {
+                let zeros = [[0; 5]; 5];
+                let next_inputs = (idx + 1 < blocks).then(|| &zeros);
+                KeccakFArith::permute_and_absorb(&mut out_state, next_inputs);
+                out_states.push(state_bigint_to_field(out_state.clone()));
+            }
+            let circuit = ChainCircuit::<Fp> {
+                in_state,
+                out_states,
+            };
+            // Fails with `NotEnoughRowsAvailable` if the estimate is too small
+            let prover =
+                MockProver::<Fp>::run(estimate_min_k(input_len), &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::error::KeccakError;
 use eth_types::Field;
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter},
@@ -9,6 +10,10 @@ use std::marker::PhantomData;
 
 /// A versatile gate to do running sum, conditional add, and linear combination,
 /// etc.
+///
+/// The direction of a running sum is not part of the config, a sum runs down
+/// by negating its constants. Rho goes through the same gate for both its base
+/// 13 input and its base 9 output, so one config serves both.
 #[derive(Clone, Debug)]
 pub struct GenericConfig<F> {
     q_enable: Selector,
@@ -32,6 +37,7 @@ impl<F: Field> GenericConfig<F> {
         meta.enable_equality(right);
         meta.enable_constant(fixed);
 
+        // Degree 3: the selector times the product `left * right`.
         meta.create_gate("add", |meta| {
             let q_enable = meta.query_selector(q_enable);
             let input = meta.query_advice(io, Rotation::cur());
@@ -114,9 +120,9 @@ impl<F: Field> GenericConfig<F> {
                     self.io,
                     offset,
                     || {
-                        Ok(input.value().cloned().ok_or(Error::Synthesis)?
-                            + left.value().cloned().ok_or(Error::Synthesis)?
-                                * right.value().cloned().ok_or(Error::Synthesis)?)
+                        Ok(input.value().cloned().ok_or(KeccakError::MissingWitness)?
+                            + left.value().cloned().ok_or(KeccakError::MissingWitness)?
+                                * right.value().cloned().ok_or(KeccakError::MissingWitness)?)
                     },
                 )
             },
@@ -193,61 +199,57 @@ impl<F: Field> GenericConfig<F> {
         if let Some(ref ys) = ys {
             debug_assert_eq!(xs.len(), ys.len());
         }
-        layouter.assign_region(
-            || "linear combine",
-            |mut region| {
-                // | offset |        input |        x |   y |
-                // | ------ | -----------: | -------: | ------: |
-                // | 0      |            0 |       x0 |      y0 |
-                // | 1      |         x0y0 |       x1 |      y1 |
-                // | 2      |  x0y0 + x1y1 |       x2 |      y2 |
-                // | ...    |          ... |      ... |     ... |
-                // | N - 1  |              |  x_(N-1) | y_(N-1) |
-                // | N      |    (sum)     |          |         |
-                let mut acc = region.assign_advice(|| "input 0", self.io, 0, || Ok(F::zero()))?;
-                region.constrain_constant(acc.cell(), F::zero())?;
-                let mut sum = F::zero();
-                for (offset, x) in xs.iter().enumerate() {
-                    self.q_enable.enable(&mut region, offset)?;
-                    x.copy_advice(|| "x", &mut region, self.left, offset)?;
-                    let right = {
-                        match &vs {
-                            Some(vs) => region.assign_advice_from_constant(
-                                || "v",
-                                self.right,
-                                offset,
-                                vs[offset],
-                            )?,
-                            None => match &ys {
-                                Some(ys) => ys[offset].copy_advice(
-                                    || "y",
-                                    &mut region,
-                                    self.right,
-                                    offset,
-                                )?,
-                                None => {
-                                    unreachable!()
-                                }
-                            },
-                        }
-                    };
-                    acc = region.assign_advice(
-                        || "accumulation",
-                        self.io,
-                        offset + 1,
-                        || {
-                            sum += x.value().cloned().ok_or(Error::Synthesis)?
-                                * right.value().cloned().ok_or(Error::Synthesis)?;
-                            Ok(sum)
+        let region_name = || "linear combine";
+        layouter.assign_region(region_name, |mut region| {
+            // | offset |        input |        x |   y |
+            // | ------ | -----------: | -------: | ------: |
+            // | 0      |            0 |       x0 |      y0 |
+            // | 1      |         x0y0 |       x1 |      y1 |
+            // | 2      |  x0y0 + x1y1 |       x2 |      y2 |
+            // | ...    |          ... |      ... |     ... |
+            // | N - 1  |              |  x_(N-1) | y_(N-1) |
+            // | N      |    (sum)     |          |         |
+            let first = || "input 0";
+            let mut acc = region.assign_advice(first, self.io, 0, || Ok(F::zero()))?;
+            region.constrain_constant(acc.cell(), F::zero())?;
+            #[cfg(feature = "trace")]
+            crate::trace::record(region_name, first, 0, &acc);
+            let mut sum = F::zero();
+            for (offset, x) in xs.iter().enumerate() {
+                self.q_enable.enable(&mut region, offset)?;
+                x.copy_advice(|| "x", &mut region, self.left, offset)?;
+                let right = {
+                    match &vs {
+                        Some(vs) => region.assign_advice_from_constant(
+                            || "v",
+                            self.right,
+                            offset,
+                            vs[offset],
+                        )?,
+                        None => match &ys {
+                            Some(ys) => {
+                                ys[offset].copy_advice(|| "y", &mut region, self.right, offset)?
+                            }
+                            None => {
+                                unreachable!()
+                            }
                         },
-                    )?;
-                }
-                if let Some(outcome) = &outcome {
-                    region.constrain_equal(outcome.cell(), acc.cell())?;
-                }
-                Ok(acc)
-            },
-        )
+                    }
+                };
+                let accumulation = || "accumulation";
+                acc = region.assign_advice(accumulation, self.io, offset + 1, || {
+                    sum += x.value().cloned().ok_or(KeccakError::MissingWitness)?
+                        * right.value().cloned().ok_or(KeccakError::MissingWitness)?;
+                    Ok(sum)
+                })?;
+                #[cfg(feature = "trace")]
+                crate::trace::record(region_name, accumulation, offset + 1, &acc);
+            }
+            if let Some(outcome) = &outcome {
+                region.constrain_equal(outcome.cell(), acc.cell())?;
+            }
+            Ok(acc)
+        })
     }
 
     pub fn linear_combine_consts(
@@ -270,6 +272,28 @@ impl<F: Field> GenericConfig<F> {
         self.linear_combine_generic(layouter, xs, Some(ys), None, outcome)
     }
 
+    /// Same as [`Self::linear_combine_consts`], with each x witnessed in two
+    /// halves, `x = low + high * split`.
+    ///
+    /// This is for coefs that don't fit in one lookup, like a slice of more
+    /// chunks than a table holds: each half is looked up on its own, and
+    /// `split` is the power of base of the high half within the coef. The
+    /// halves go through the same gate, with `v` and `v * split` as constants.
+    pub fn linear_combine_split_consts(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lows: Vec<AssignedCell<F, F>>,
+        highs: Vec<AssignedCell<F, F>>,
+        split: F,
+        vs: Vec<F>,
+        outcome: Option<AssignedCell<F, F>>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        debug_assert_eq!(lows.len(), highs.len());
+        let xs = lows.into_iter().interleave(highs).collect_vec();
+        let vs = vs.into_iter().flat_map(|v| [v, v * split]).collect_vec();
+        self.linear_combine_consts(layouter, xs, vs, outcome)
+    }
+
     pub fn running_sum(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -285,3 +309,383 @@ impl<F: Field> GenericConfig<F> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith_helpers::{convert_b2_to_b13, convert_b2_to_b9, B13, B9};
+    use crate::gate_helpers::biguint_to_f;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner, dev::MockProver, pairing::bn256::Fr as Fp, plonk::Circuit,
+    };
+    use pretty_assertions::assert_eq;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_running_sum_both_directions() {
+        #[derive(Default)]
+        struct MyCircuit<F> {
+            lane: u64,
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for MyCircuit<F> {
+            type Config = GenericConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let advices: [Column<Advice>; 3] = (0..3)
+                    .map(|_| meta.advice_column())
+                    .collect_vec()
+                    .try_into()
+                    .unwrap();
+                let fixed = meta.fixed_column();
+                GenericConfig::configure(meta, advices, fixed)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let bits = (0..64).map(|i| F::from((self.lane >> i) & 1)).collect_vec();
+                let (lane13, lane9, coefs) = layouter.assign_region(
+                    || "lanes and coefs",
+                    |mut region| {
+                        let lane13 = region.assign_advice(
+                            || "base 13 lane",
+                            config.left,
+                            0,
+                            || Ok(biguint_to_f(&convert_b2_to_b13(self.lane))),
+                        )?;
+                        let lane9 = region.assign_advice(
+                            || "base 9 lane",
+                            config.right,
+                            0,
+                            || Ok(biguint_to_f(&convert_b2_to_b9(self.lane))),
+                        )?;
+                        let coefs = bits
+                            .iter()
+                            .enumerate()
+                            .map(|(offset, &bit)| {
+                                region.assign_advice(|| "coef", config.io, offset, || Ok(bit))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        Ok((lane13, lane9, coefs))
+                    },
+                )?;
+                let powers = |base: u8| {
+                    (0..64)
+                        .scan(F::one(), |pob, _| {
+                            let power = *pob;
+                            *pob *= F::from(base as u64);
+                            Some(power)
+                        })
+                        .collect_vec()
+                };
+
+                // Up: the base 9 output accumulates to the lane
+                config.linear_combine_consts(
+                    &mut layouter,
+                    coefs.clone(),
+                    powers(B9),
+                    Some(lane9),
+                )?;
+
+                // Down: the base 13 input runs down from the lane to zero
+                let mut xs = vec![lane13];
+                xs.extend(coefs);
+                let mut vs = vec![F::one()];
+                vs.extend(powers(B13).into_iter().map(|power| -power));
+                let rest = config.linear_combine_consts(&mut layouter, xs, vs, None)?;
+                layouter.assign_region(
+                    || "input runs down to zero",
+                    |mut region| {
+                        let rest = rest.copy_advice(|| "rest", &mut region, config.io, 0)?;
+                        region.constrain_constant(rest.cell(), F::zero())
+                    },
+                )?;
+                assert_eq!(rest.value().copied().unwrap_or_default(), F::zero());
+                Ok(())
+            }
+        }
+
+        for lane in [0, u64::MAX, rand::random::<u64>()] {
+            let circuit = MyCircuit::<Fp> {
+                lane,
+                _marker: PhantomData,
+            };
+            let prover = MockProver::<Fp>::run(8, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_random_bases_and_steps() {
+        #[derive(Default)]
+        struct SumCircuit<F> {
+            base: u64,
+            coefs: Vec<u64>,
+            sum: F,
+        }
+
+        impl<F: Field> Circuit<F> for SumCircuit<F> {
+            type Config = GenericConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let advices: [Column<Advice>; 3] = (0..3)
+                    .map(|_| {
+                        let column = meta.advice_column();
+                        meta.enable_equality(column);
+                        column
+                    })
+                    .collect_vec()
+                    .try_into()
+                    .unwrap();
+                let fixed = meta.fixed_column();
+                GenericConfig::configure(meta, advices, fixed)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let (coefs, sum) = layouter.assign_region(
+                    || "coefs and sum",
+                    |mut region| {
+                        let coefs = self
+                            .coefs
+                            .iter()
+                            .enumerate()
+                            .map(|(offset, &coef)| {
+                                region.assign_advice(
+                                    || "coef",
+                                    config.io,
+                                    offset,
+                                    || Ok(F::from(coef)),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        let sum =
+                            region.assign_advice(|| "sum", config.left, 0, || Ok(self.sum))?;
+                        Ok((coefs, sum))
+                    },
+                )?;
+                let powers = (0..self.coefs.len())
+                    .map(|i| F::from(self.base).pow(&[i as u64, 0, 0, 0]))
+                    .collect_vec();
+                config.linear_combine_consts(&mut layouter, coefs, powers, Some(sum))?;
+                Ok(())
+            }
+        }
+
+        for _ in 0..20 {
+            let base = rand::random::<u64>() % 15 + 2;
+            let step = rand::random::<usize>() % 8 + 1;
+            let coefs = (0..step)
+                .map(|_| rand::random::<u64>() % base)
+                .collect_vec();
+            let sum = coefs.iter().rev().fold(0, |acc, coef| acc * base + coef);
+            let circuit = SumCircuit::<Fp> {
+                base,
+                coefs: coefs.clone(),
+                sum: Fp::from(sum),
+            };
+            let prover = MockProver::<Fp>::run(8, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "base {} coefs {:?}", base, coefs);
+
+            // A corrupted coefficient no longer sums to the same value
+            let mut corrupted = coefs.clone();
+            let idx = rand::random::<usize>() % step;
+            corrupted[idx] = (corrupted[idx] + 1) % base;
+            let circuit = SumCircuit::<Fp> {
+                base,
+                coefs: corrupted,
+                sum: Fp::from(sum),
+            };
+            let prover = MockProver::<Fp>::run(8, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err(), "base {} coefs {:?}", base, coefs);
+        }
+    }
+
+    #[test]
+    fn test_split_coefs() {
+        use num_bigint::BigUint;
+
+        #[derive(Default)]
+        struct SplitCircuit<F> {
+            lows: Vec<u64>,
+            highs: Vec<u64>,
+            sum: F,
+        }
+
+        // 8 chunks a coef, twice the chunks of the base 13 table
+        const STEP: u64 = 8;
+
+        impl<F: Field> Circuit<F> for SplitCircuit<F> {
+            type Config = GenericConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let advices = [(); 3].map(|_| meta.advice_column());
+                let fixed = meta.fixed_column();
+                GenericConfig::configure(meta, advices, fixed)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let (lows, highs, sum) = layouter.assign_region(
+                    || "halves and sum",
+                    |mut region| {
+                        let mut assign = |column, values: &[u64]| {
+                            values
+                                .iter()
+                                .enumerate()
+                                .map(|(offset, &value)| {
+                                    region.assign_advice(
+                                        || "half",
+                                        column,
+                                        offset,
+                                        || Ok(F::from(value)),
+                                    )
+                                })
+                                .collect::<Result<Vec<_>, Error>>()
+                        };
+                        let lows = assign(config.io, &self.lows)?;
+                        let highs = assign(config.left, &self.highs)?;
+                        let sum =
+                            region.assign_advice(|| "sum", config.right, 0, || Ok(self.sum))?;
+                        Ok((lows, highs, sum))
+                    },
+                )?;
+                let base = F::from(B13 as u64);
+                let split = base.pow(&[STEP / 2, 0, 0, 0]);
+                let powers = (0..self.lows.len())
+                    .map(|i| base.pow(&[STEP * i as u64, 0, 0, 0]))
+                    .collect_vec();
+                config.linear_combine_split_consts(
+                    &mut layouter,
+                    lows,
+                    highs,
+                    split,
+                    powers,
+                    Some(sum),
+                )?;
+                Ok(())
+            }
+        }
+
+        // The halves of 8 coefs, each below the table size 13**4
+        let half = (B13 as u64).pow(STEP as u32 / 2);
+        let mut lows = (0..8).map(|_| rand::random::<u64>() % half).collect_vec();
+        let mut highs = (0..8).map(|_| rand::random::<u64>() % half).collect_vec();
+        // A whole coef is beyond the table
+        highs[0] = half - 1;
+        // Two different halves, to swap below
+        highs[3] = (lows[3] + 1) % half;
+        let sum = lows
+            .iter()
+            .zip(highs.iter())
+            .rev()
+            .fold(BigUint::from(0u8), |acc, (&low, &high)| {
+                acc * BigUint::from(B13).pow(STEP as u32) + low + high * half
+            });
+
+        let circuit = SplitCircuit::<Fp> {
+            lows: lows.clone(),
+            highs: highs.clone(),
+            sum: biguint_to_f(&sum),
+        };
+        let prover = MockProver::<Fp>::run(8, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Swapping the two different halves of a coef changes the sum
+        std::mem::swap(&mut lows[3], &mut highs[3]);
+        let circuit = SplitCircuit::<Fp> {
+            lows,
+            highs,
+            sum: circuit.sum,
+        };
+        let prover = MockProver::<Fp>::run(8, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[cfg(feature = "trace")]
+    #[test]
+    fn test_trace_running_sum() {
+        use crate::trace::{dump_witness, TracedCell};
+
+        #[derive(Default)]
+        struct SumCircuit<F> {
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for SumCircuit<F> {
+            type Config = GenericConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let advices: [Column<Advice>; 3] = (0..3)
+                    .map(|_| meta.advice_column())
+                    .collect_vec()
+                    .try_into()
+                    .unwrap();
+                let fixed = meta.fixed_column();
+                GenericConfig::configure(meta, advices, fixed)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                let xs = layouter.assign_region(
+                    || "summands",
+                    |mut region| {
+                        [2, 3, 4]
+                            .iter()
+                            .enumerate()
+                            .map(|(offset, &x)| {
+                                region.assign_advice(|| "x", config.io, offset, || Ok(F::from(x)))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()
+                    },
+                )?;
+                config.running_sum(&mut layouter, xs, None)?;
+                Ok(())
+            }
+        }
+
+        dump_witness();
+        let prover = MockProver::<Fp>::run(8, &SumCircuit::default(), vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let trace: Vec<TracedCell> = serde_json::from_str(&dump_witness()).unwrap();
+        assert!(trace.contains(&TracedCell {
+            region: "linear combine".to_string(),
+            column: "accumulation".to_string(),
+            offset: 3,
+            value: "9".to_string(),
+        }));
+    }
+}
@@ -13,8 +13,17 @@ pub struct IotaConstants<F> {
 
 impl<F: Field> Default for IotaConstants<F> {
     fn default() -> Self {
+        Self::for_rounds(PERMUTATION)
+    }
+}
+
+impl<F: Field> IotaConstants<F> {
+    /// The constants of a permutation reduced to its first `num_rounds`
+    /// rounds, where the base 13 constant is the one of the last round.
+    pub fn for_rounds(num_rounds: usize) -> Self {
+        assert!((1..=PERMUTATION).contains(&num_rounds));
         let round_constant_b13 =
-            biguint_to_f::<F>(&convert_b2_to_b13(ROUND_CONSTANTS[PERMUTATION - 1]));
+            biguint_to_f::<F>(&convert_b2_to_b13(ROUND_CONSTANTS[num_rounds - 1]));
 
         let a4_times_round_constants_b9: [F; 24] = ROUND_CONSTANTS
             .iter()
@@ -32,3 +41,43 @@ impl<F: Field> Default for IotaConstants<F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith_helpers::{convert_b9_lane_to_b2, StateBigInt};
+    use crate::common::State;
+    use crate::gate_helpers::f_to_biguint;
+    use crate::keccak_arith::KeccakFArith;
+    use crate::plain::KeccakF;
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_iota_constants() {
+        let mut state: State = [[0; 5]; 5];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            state[x][y] = rand::random();
+        }
+        let mut state_b9 = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            state_b9[(x, y)] = convert_b2_to_b9(state[x][y]);
+        }
+        let xi_b9 = KeccakFArith::xi(&state_b9);
+
+        let constants = IotaConstants::<Fp>::default();
+        for (round, &rc) in ROUND_CONSTANTS.iter().enumerate() {
+            // The constant is added on top of the Xi output, where it plays
+            // the role of the `d` term in `2*a + b + 3*c + 2*d`.
+            let lane =
+                biguint_to_f::<Fp>(&xi_b9[(0, 0)]) + constants.a4_times_round_constants_b9[round];
+            let expected = KeccakF::iota(KeccakF::xi(state), rc);
+            assert_eq!(convert_b9_lane_to_b2(f_to_biguint(lane)), expected[0][0]);
+        }
+
+        assert_eq!(
+            constants.round_constant_b13,
+            biguint_to_f::<Fp>(&convert_b2_to_b13(ROUND_CONSTANTS[PERMUTATION - 1]))
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Converts the 25 lanes of a state through `LaneRotateConversionConfig` in
+//! `WIDTH` groups of three advice columns, to weigh columns against rows.
+//!
+//! With a width of 1 the lanes are packed one after the other in the same
+//! three columns, the layout with the fewest columns. Each extra group takes
+//! three more advice columns and splits the lanes between the groups, so the
+//! conversions run side by side over fewer rows. The groups share the base 13
+//! to base 9 table, the stackable table and the fixed column.
+//!
+//! The benchmark in `benches/lane_layout.rs` proves both layouts.
+use crate::common::LaneIndex;
+use crate::gate_helpers::f_to_biguint;
+use crate::permutation::{
+    generic::GenericConfig,
+    rho_checks::LaneRotateConversionConfig,
+    rho_helpers::RhoLane,
+    tables::{Base13toBase9TableConfig, StackableTable},
+};
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, TableColumn},
+};
+
+/// The lane conversions of a whole state, over `WIDTH` groups of columns.
+///
+/// The lanes are in base 13, as Theta outputs them, and go to the groups in
+/// turn: lane `i` is converted by group `i % WIDTH`.
+#[derive(Debug, Clone)]
+pub struct LaneLayoutCircuit<F, const WIDTH: usize> {
+    pub lanes: [F; 25],
+}
+
+impl<F: Field, const WIDTH: usize> Default for LaneLayoutCircuit<F, WIDTH> {
+    fn default() -> Self {
+        Self {
+            lanes: [F::zero(); 25],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LaneLayoutConfig<F> {
+    groups: Vec<(LaneRotateConversionConfig<F>, Column<Advice>)>,
+    table: Base13toBase9TableConfig<F>,
+    stackable: StackableTable<F>,
+}
+
+impl<F: Field, const WIDTH: usize> Circuit<F> for LaneLayoutCircuit<F, WIDTH> {
+    type Config = LaneLayoutConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        assert!(WIDTH > 0, "the lanes need at least one group of columns");
+        let fixed = meta.fixed_column();
+        let table_cols: [TableColumn; 3] = [(); 3].map(|_| meta.lookup_table_column());
+        let table = Base13toBase9TableConfig::configure(meta);
+        let groups = (0..WIDTH)
+            .map(|_| {
+                let advices = [(); 3].map(|_| {
+                    let column = meta.advice_column();
+                    meta.enable_equality(column);
+                    column
+                });
+                // Every group looks up the same stackable table
+                let stackable = StackableTable::configure(meta, advices, table_cols);
+                let generic = GenericConfig::configure(meta, advices, fixed);
+                let lane_config = LaneRotateConversionConfig::configure(
+                    meta,
+                    &table,
+                    advices,
+                    fixed,
+                    generic,
+                    stackable.clone(),
+                );
+                ((lane_config, advices[0]), stackable)
+            })
+            .collect::<Vec<_>>();
+        // The table columns are shared, they are loaded once
+        let stackable = groups[0].1.clone();
+        let groups = groups.into_iter().map(|(group, _)| group).collect();
+        LaneLayoutConfig {
+            groups,
+            table,
+            stackable,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.table.load(&mut layouter)?;
+        config.stackable.load(&mut layouter)?;
+        for (group, (lane_config, input)) in config.groups.iter().enumerate() {
+            let lanes = LaneIndex::all()
+                .skip(group)
+                .step_by(WIDTH)
+                .collect::<Vec<_>>();
+            let witnesses = lanes
+                .iter()
+                .map(|lane| {
+                    RhoLane::new(f_to_biguint(self.lanes[lane.index()]), lane.rotation())
+                        .get_full_witness()
+                })
+                .collect::<Vec<_>>();
+            // The lanes of a group are packed in one region
+            let chunks = layouter.assign_region(
+                || format!("lanes of group {}", group),
+                |mut region| {
+                    let mut base_offset = 0;
+                    lanes
+                        .iter()
+                        .zip(witnesses.iter())
+                        .map(|(&lane, witness)| {
+                            let chunks = lane_config.assign_chunks_at(
+                                &mut region,
+                                base_offset,
+                                lane,
+                                witness,
+                            )?;
+                            base_offset += chunks.rows();
+                            Ok(chunks)
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+            for (lane, chunks) in lanes.iter().zip(chunks) {
+                let cell = layouter.assign_region(
+                    || format!("input lane {}", lane.index()),
+                    |mut region| {
+                        region.assign_advice(|| "lane", *input, 0, || Ok(self.lanes[lane.index()]))
+                    },
+                )?;
+                lane_config.combine_chunks(&mut layouter, cell, chunks)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith_helpers::convert_b2_to_b13;
+    use crate::gate_helpers::biguint_to_f;
+    use halo2_proofs::{dev::MockProver, pairing::bn256::Fr as Fp};
+    use pretty_assertions::assert_eq;
+
+    fn random_lanes() -> [Fp; 25] {
+        [(); 25].map(|_| biguint_to_f(&convert_b2_to_b13(rand::random())))
+    }
+
+    #[test]
+    fn test_lane_layouts() {
+        let lanes = random_lanes();
+
+        let circuit = LaneLayoutCircuit::<Fp, 1> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = LaneLayoutCircuit::<Fp, 5> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Three advice columns per group
+        let advices = |configure: fn(&mut ConstraintSystem<Fp>) -> LaneLayoutConfig<Fp>| {
+            let mut meta = ConstraintSystem::default();
+            configure(&mut meta);
+            meta.num_advice_columns()
+        };
+        assert_eq!(advices(LaneLayoutCircuit::<Fp, 1>::configure), 3);
+        assert_eq!(advices(LaneLayoutCircuit::<Fp, 5>::configure), 15);
+    }
+}
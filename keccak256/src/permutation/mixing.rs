@@ -3,7 +3,6 @@ use super::generic::GenericConfig;
 use super::tables::{FromBase9TableConfig, StackableTable};
 use super::{absorb::AbsorbConfig, base_conversion::BaseConversionConfig, iota::IotaConstants};
 use crate::common::*;
-use crate::keccak_arith::KeccakFArith;
 use eth_types::Field;
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region},
@@ -15,6 +14,7 @@ use std::convert::TryInto;
 #[derive(Clone, Debug)]
 pub struct MixingConfig<F> {
     iota_constants: IotaConstants<F>,
+    num_rounds: usize,
     absorb_config: AbsorbConfig<F>,
     base_conv_config: BaseConversionConfig<F>,
     state: [Column<Advice>; 25],
@@ -35,7 +35,8 @@ impl<F: Field> MixingConfig<F> {
         let flag = meta.advice_column();
         meta.enable_equality(flag);
         // We mix -> Flag = true
-        let absorb_config = AbsorbConfig::configure(meta, state);
+        let absorb_config =
+            AbsorbConfig::configure(meta, state, NEXT_INPUTS_LANES, ByteOrder::default());
 
         let base_info = table.get_base_info(false);
         let base_conv_lane = meta.advice_column();
@@ -49,6 +50,7 @@ impl<F: Field> MixingConfig<F> {
 
         let q_out_copy = meta.selector();
 
+        // Degree 3: the selector times the out states multiplied by the flags.
         meta.create_gate("Mixing result copies and constraints", |meta| {
             let q_enable = meta.query_selector(q_out_copy);
             // Add out mixing states together multiplied by the mixing_flag.
@@ -70,6 +72,7 @@ impl<F: Field> MixingConfig<F> {
 
         MixingConfig {
             iota_constants,
+            num_rounds: PERMUTATION,
             absorb_config,
             base_conv_config,
             state,
@@ -80,11 +83,58 @@ impl<F: Field> MixingConfig<F> {
         }
     }
 
+    /// Uses the round constants of the last of `num_rounds` rounds, to mix
+    /// after a reduced permutation.
+    pub fn with_rounds(mut self, num_rounds: usize) -> Self {
+        self.iota_constants = IotaConstants::for_rounds(num_rounds);
+        self.num_rounds = num_rounds;
+        self
+    }
+
+    /// The config absorbing the next inputs, to witness them.
+    pub(crate) fn absorb_config(&self) -> &AbsorbConfig<F> {
+        &self.absorb_config
+    }
+
+    /// Absorbs `block` into the base 9 `state` and converts the result to
+    /// base 13, without the rest of the mixing.
+    ///
+    /// This is how the first block goes into the initial state of a sponge:
+    /// the lanes of `state` hold `A1` times the initial lanes, so the base
+    /// conversion XORs the block into them.
+    pub fn absorb(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+        block: &[AssignedCell<F, F>],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let flag = layouter.assign_region(
+            || "Absorb enable",
+            |mut region| {
+                region.assign_advice_from_constant(|| "Enable absorb", self.flag, 0, F::one())
+            },
+        )?;
+        let out_state = self.absorb_config.out_state(state, block);
+        let (out_state, _) = self.absorb_config.copy_state_flag_next_inputs(
+            layouter,
+            state,
+            out_state,
+            block,
+            flag.clone(),
+        )?;
+        self.base_conv_config
+            .assign_state(layouter, &out_state, flag)
+    }
+
     /// Enforce flag constraints
+    ///
+    /// The flag is fixed to `flag_bool`: whether a permutation absorbs a block
+    /// is part of the layout, not up to the prover.
     pub fn assign_out_mixing_states(
         &self,
         layouter: &mut impl Layouter<F>,
         flag_bool: bool,
+        flag: AssignedCell<F, F>,
         negated_flag: AssignedCell<F, F>,
         out_mixing_circ: &[AssignedCell<F, F>; 25],
         out_non_mixing_circ: &[AssignedCell<F, F>; 25],
@@ -97,12 +147,8 @@ impl<F: Field> MixingConfig<F> {
                 self.q_out_copy.enable(&mut region, 0)?;
 
                 // Copy constrain flags.
-                let _flag_cell = region.assign_advice(
-                    || "witness is_mixing",
-                    self.flag,
-                    0,
-                    || Ok(F::from(flag_bool as u64)),
-                )?;
+                let flag = flag.copy_advice(|| "witness is_mixing", &mut region, self.flag, 0)?;
+                region.constrain_constant(flag.cell(), F::from(flag_bool))?;
 
                 negated_flag.copy_advice(|| "witness is_mixing", &mut region, self.flag, 1)?;
 
@@ -136,7 +182,7 @@ impl<F: Field> MixingConfig<F> {
         in_state: &[AssignedCell<F, F>; 25],
         out_state: [F; 25],
         flag_bool: bool,
-        next_mixing: Option<[F; NEXT_INPUTS_LANES]>,
+        next_mixing: Option<&[AssignedCell<F, F>]>,
     ) -> Result<[AssignedCell<F, F>; 25], Error> {
         // Enforce flag constraints and witness them.
         let (flag, negated_flag) = self
@@ -154,22 +200,24 @@ impl<F: Field> MixingConfig<F> {
                 layouter,
                 state[0].clone(),
                 negated_flag.clone(),
-                self.iota_constants.a4_times_round_constants_b9[PERMUTATION - 1],
+                self.iota_constants.a4_times_round_constants_b9[self.num_rounds - 1],
             )?;
             state
         };
 
         // If we mix:
-        // Absorb
+        // Absorb, a zero block when we don't
+        let next_mixing = match next_mixing {
+            Some(next_mixing) => next_mixing.to_vec(),
+            None => self
+                .absorb_config
+                .assign_next_inputs(layouter, &[F::zero(); NEXT_INPUTS_LANES])?,
+        };
         let (out_state_absorb_cells, _) = self.absorb_config.copy_state_flag_next_inputs(
             layouter,
             in_state,
-            // Compute out_absorb state.
-            state_bigint_to_field(KeccakFArith::absorb(
-                &state_to_biguint(split_state_cells(in_state.clone())),
-                &state_to_state_bigint::<F, NEXT_INPUTS_LANES>(next_mixing.unwrap_or_default()),
-            )),
-            next_mixing.unwrap_or_default(),
+            self.absorb_config.out_state(in_state, &next_mixing),
+            &next_mixing,
             flag.clone(),
         )?;
 
@@ -188,7 +236,7 @@ impl<F: Field> MixingConfig<F> {
             base_conv_cells[0] = self.generic.conditional_add_const(
                 layouter,
                 base_conv_cells[0].clone(),
-                flag,
+                flag.clone(),
                 self.iota_constants.round_constant_b13,
             )?;
             base_conv_cells
@@ -197,6 +245,7 @@ impl<F: Field> MixingConfig<F> {
         self.assign_out_mixing_states(
             layouter,
             flag_bool,
+            flag,
             negated_flag,
             &mix_res,
             &non_mix_res,
@@ -229,6 +278,7 @@ impl<F: Field> MixingConfig<F> {
 mod tests {
     use super::*;
     use crate::common::{State, ROUND_CONSTANTS};
+    use crate::keccak_arith::KeccakFArith;
     use halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner},
         dev::MockProver,
@@ -329,12 +379,21 @@ mod tests {
                     },
                 )?;
 
+                let next_mixing = self
+                    .next_mixing
+                    .map(|next_mixing| {
+                        config
+                            .mixing_conf
+                            .absorb_config
+                            .assign_next_inputs(&mut layouter, &next_mixing)
+                    })
+                    .transpose()?;
                 config.mixing_conf.assign_state(
                     &mut layouter,
                     &in_state,
                     self.out_state,
                     self.is_mixing,
-                    self.next_mixing,
+                    next_mixing.as_deref(),
                 )?;
 
                 Ok(())
@@ -9,11 +9,43 @@ use std::convert::TryInto;
 /// order. The copy constrain in the next gate can then enforce the Pi step
 /// permutation.
 pub fn pi_gate_permutation<F: Field>(state: [AssignedCell<F, F>; 25]) -> [AssignedCell<F, F>; 25] {
-    let state: [AssignedCell<F, F>; 25] = (0..5)
+    pi_permute(state)
+}
+
+/// Moves the lanes of a state indexed `5 * x + y` to their Pi positions,
+/// the lane at `(x, y)` goes to `(y, 2x + 3y mod 5)`.
+///
+/// This only reorders the lanes, the values are left untouched.
+pub fn pi_permute<T: Clone>(state: [T; 25]) -> [T; 25] {
+    (0..5)
         .cartesian_product(0..5)
         .map(|(x, y)| state[5 * ((x + 3 * y) % 5) + x].clone())
         .collect::<Vec<_>>()
         .try_into()
-        .unwrap();
-    state
+        .unwrap_or_else(|_| unreachable!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::State;
+    use crate::plain::KeccakF;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_pi_permute() {
+        let mut state: State = [[0; 5]; 5];
+        let mut lanes = [0u64; 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            state[x][y] = (5 * x + y) as u64;
+            lanes[5 * x + y] = (5 * x + y) as u64;
+        }
+        let expected = KeccakF::pi(state);
+        let lanes = pi_permute(lanes);
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            assert_eq!(lanes[5 * x + y], expected[x][y]);
+        }
+        // The lane at (0, 1) moves to (1, 3)
+        assert_eq!(lanes[8], 1);
+    }
 }
@@ -1,6 +1,10 @@
+use crate::common::LaneIndex;
+use crate::gate_helpers::f_to_biguint;
 use crate::permutation::{
     generic::GenericConfig,
-    rho_checks::LaneRotateConversionConfig,
+    pi::pi_gate_permutation,
+    rho_checks::{check_input_lane, LaneRotateConversionConfig},
+    rho_helpers::{compute_rho_witnesses, RhoWitness},
     tables::{Base13toBase9TableConfig, StackableTable},
 };
 
@@ -11,12 +15,18 @@ use halo2_proofs::{
 };
 use std::convert::TryInto;
 
+/// Rotates and converts the 25 lanes from base 13 to base 9.
+///
+/// The [`Base13toBase9TableConfig`] is configured here once and shared by
+/// reference with the lane config, so all the lanes look up into the same
+/// table and it is loaded once in [`RhoConfig::load`].
 #[derive(Debug, Clone)]
 pub struct RhoConfig<F> {
     lane_config: LaneRotateConversionConfig<F>,
     base13_to_9_table: Base13toBase9TableConfig<F>,
     stackable: StackableTable<F>,
     generic: GenericConfig<F>,
+    skip_final_overflow_check: bool,
 }
 
 impl<F: Field> RhoConfig<F> {
@@ -43,12 +53,50 @@ impl<F: Field> RhoConfig<F> {
             base13_to_9_table,
             stackable,
             generic,
+            skip_final_overflow_check: false,
         }
     }
+
+    /// Leaves the final overflow detector range checks out of
+    /// [`Self::assign_rotation_checks`], saving their running sums and
+    /// lookups.
+    ///
+    /// **This is not sound on its own.** The final checks are what stop a
+    /// prover from witnessing a slice that overflows into the next one, see
+    /// the overflow checks in [`crate::permutation::rho_checks`], and the
+    /// per-slice lookups still accept such a slice. Only skip them when the
+    /// Rho output is constrained by other means, or when the witness comes
+    /// from a trusted prover, like in benchmarks.
+    pub fn skip_final_overflow_check(mut self) -> Self {
+        self.skip_final_overflow_check = true;
+        self
+    }
+
     pub fn assign_rotation_checks(
         &self,
         layouter: &mut impl Layouter<F>,
         state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        // The witnesses can only be computed for valid lanes
+        for (lane, cell) in LaneIndex::all().zip(state.iter()) {
+            check_input_lane(cell, lane)?;
+        }
+        // Regions can't be shared across threads, so only the witnesses are
+        // computed ahead of the sequential assignment.
+        let lanes = state
+            .clone()
+            .map(|lane| f_to_biguint(*lane.value().unwrap_or(&F::zero())));
+        let witnesses = compute_rho_witnesses(&lanes);
+        self.assign_rotation_checks_with_witnesses(layouter, state, &witnesses)
+    }
+
+    /// Same as [`Self::assign_rotation_checks`], with the witnesses of the 25
+    /// lanes computed by the caller, in lane order.
+    pub fn assign_rotation_checks_with_witnesses(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+        witnesses: &[RhoWitness],
     ) -> Result<[AssignedCell<F, F>; 25], Error> {
         type R<F> = (
             AssignedCell<F, F>,
@@ -57,18 +105,25 @@ impl<F: Field> RhoConfig<F> {
         );
         let lane_and_ods: Result<Vec<R<F>>, Error> = state
             .iter()
-            .enumerate()
-            .map(|(idx, lane)| -> Result<R<F>, Error> {
-                let (out_lane, step2_od, step3_od) =
-                    self.lane_config
-                        .assign_region(layouter, lane.clone(), idx)?;
-                Ok((out_lane, step2_od, step3_od))
+            .zip(witnesses.iter())
+            .zip(LaneIndex::all())
+            .map(|((cell, witness), lane)| -> Result<R<F>, Error> {
+                let result = self.lane_config.assign_region_with_witness(
+                    layouter,
+                    cell.clone(),
+                    lane,
+                    witness,
+                )?;
+                Ok((result.output_lane, result.step2_od, result.step3_od))
             })
             .into_iter()
             .collect();
         let lane_and_ods = lane_and_ods?;
         let lane_and_ods: [R<F>; 25] = lane_and_ods.try_into().unwrap();
         let next_state = lane_and_ods.clone().map(|(out_lane, _, _)| out_lane);
+        if self.skip_final_overflow_check {
+            return Ok(next_state);
+        }
 
         let step2_od_join = lane_and_ods
             .iter()
@@ -85,6 +140,18 @@ impl<F: Field> RhoConfig<F> {
         Ok(next_state)
     }
 
+    /// Runs [`Self::assign_rotation_checks`] and moves the lanes to their Pi
+    /// positions, the state at the boundary between Rho and Pi and the next
+    /// step.
+    pub fn assign_rho_pi(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let state = self.assign_rotation_checks(layouter, state)?;
+        Ok(pi_gate_permutation(state))
+    }
+
     pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         self.base13_to_9_table.load(layouter)?;
         Ok(())
@@ -283,4 +350,356 @@ mod tests {
 
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    /// Configures Rho on 25 state columns, shared by the circuits of these
+    /// tests.
+    fn configure_rho<F: Field>(
+        meta: &mut ConstraintSystem<F>,
+    ) -> (RhoConfig<F>, [Column<Advice>; 25]) {
+        let state: [Column<Advice>; 25] = (0..25)
+            .map(|_| meta.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let fixed = meta.fixed_column();
+        let table_cols: [TableColumn; 3] = (0..3)
+            .map(|_| meta.lookup_table_column())
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let stackable =
+            StackableTable::configure(meta, state[0..3].try_into().unwrap(), table_cols);
+        let generic = GenericConfig::configure(meta, state[0..3].try_into().unwrap(), fixed);
+        (
+            RhoConfig::configure(meta, state, fixed, generic, stackable),
+            state,
+        )
+    }
+
+    #[test]
+    fn test_rho_pi_random_state() {
+        #[derive(Default)]
+        struct RhoPiCircuit<F> {
+            in_state: [F; 25],
+            out_state: [F; 25],
+        }
+
+        impl<F: Field> Circuit<F> for RhoPiCircuit<F> {
+            type Config = (RhoConfig<F>, [Column<Advice>; 25]);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                configure_rho(meta)
+            }
+
+            fn synthesize(
+                &self,
+                (config, state): Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                config.stackable.load(&mut layouter)?;
+                let in_state = layouter.assign_region(
+                    || "assign input state",
+                    |mut region| {
+                        let in_state = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, &value)| {
+                                region.assign_advice(|| "lane", state[idx], 0, || Ok(value))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        Ok(in_state.try_into().unwrap())
+                    },
+                )?;
+                let out_state = config.assign_rho_pi(&mut layouter, &in_state)?;
+                layouter.assign_region(
+                    || "check output state",
+                    |mut region| {
+                        for (idx, (lane, &expected)) in
+                            out_state.iter().zip(self.out_state.iter()).enumerate()
+                        {
+                            let lane =
+                                lane.copy_advice(|| "output lane", &mut region, state[idx], 0)?;
+                            let expected = region.assign_advice(
+                                || "expected lane",
+                                state[idx],
+                                1,
+                                || Ok(expected),
+                            )?;
+                            region.constrain_equal(lane.cell(), expected.cell())?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut in_biguint = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_biguint[(x, y)] = convert_b2_to_b13(rand::random::<u64>());
+        }
+        let theta = KeccakFArith::theta(&in_biguint);
+        let expected = KeccakFArith::pi(&KeccakFArith::rho(&theta));
+        let mut in_state = [Fp::zero(); 25];
+        let mut out_state = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_state[5 * x + y] = biguint_to_f(&theta[(x, y)]);
+            out_state[5 * x + y] = biguint_to_f(&expected[(x, y)]);
+        }
+        let circuit = RhoPiCircuit::<Fp> {
+            in_state,
+            out_state,
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_rho_degree() {
+        // The overflow detector sums are range checked with lookups into the
+        // stackable table instead of a product over the whole range, so the
+        // degree is bound by the lookups.
+        let mut meta = ConstraintSystem::<Fp>::default();
+        configure_rho(&mut meta);
+        assert_eq!(meta.degree(), 5);
+    }
+
+    #[test]
+    fn test_rho_shares_one_table() {
+        // One fixed column for the constants, 3 for the stackable table and 3
+        // for the base 13 to base 9 table, which all 25 lanes look up into.
+        let mut meta = ConstraintSystem::<Fp>::default();
+        configure_rho(&mut meta);
+        assert_eq!(meta.num_fixed_columns(), 7);
+    }
+
+    #[test]
+    fn test_rho_shares_selectors() {
+        // The lane config is configured once for the 25 lanes, so Rho adds a
+        // single selector on top of the generic config and the stackable
+        // table, whatever the number of lanes.
+        let mut shared = ConstraintSystem::<Fp>::default();
+        let state: [Column<Advice>; 25] = (0..25)
+            .map(|_| shared.advice_column())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let fixed = shared.fixed_column();
+        let table_cols: [TableColumn; 3] = (0..3)
+            .map(|_| shared.lookup_table_column())
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        StackableTable::configure(&mut shared, state[0..3].try_into().unwrap(), table_cols);
+        GenericConfig::configure(&mut shared, state[0..3].try_into().unwrap(), fixed);
+
+        let mut meta = ConstraintSystem::<Fp>::default();
+        configure_rho(&mut meta);
+        assert_eq!(meta.num_selectors(), shared.num_selectors() + 1);
+    }
+
+    #[test]
+    fn test_rho_advice_columns() {
+        // The lane config, the generic config and the stackable table all
+        // reuse the state columns, so Rho adds no advice column on top of the
+        // 25 lanes.
+        let mut meta = ConstraintSystem::<Fp>::default();
+        configure_rho(&mut meta);
+        assert_eq!(meta.num_advice_columns(), 25);
+    }
+
+    #[derive(Default)]
+    struct SkipCheckCircuit<const SKIP: bool> {
+        in_state: [Fp; 25],
+        // Checked against the Rho output when set
+        out_state: Option<[Fp; 25]>,
+        // Lets the slice of lane (1, 0) right before its rotation offset
+        // overflow into the next chunk
+        overflow: bool,
+    }
+
+    impl<const SKIP: bool> Circuit<Fp> for SkipCheckCircuit<SKIP> {
+        type Config = (RhoConfig<Fp>, [Column<Advice>; 25]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let (config, state) = configure_rho(meta);
+            let config = if SKIP {
+                config.skip_final_overflow_check()
+            } else {
+                config
+            };
+            (config, state)
+        }
+
+        fn synthesize(
+            &self,
+            (config, state): Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            use crate::gate_helpers::f_to_biguint;
+            use crate::permutation::rho_helpers::compute_rho_witnesses;
+            use crate::test_utils::craft_step2_overflow;
+
+            config.load(&mut layouter)?;
+            config.stackable.load(&mut layouter)?;
+            let in_state: [AssignedCell<Fp, Fp>; 25] = layouter.assign_region(
+                || "assign input state",
+                |mut region| {
+                    let in_state = self
+                        .in_state
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, &value)| {
+                            region.assign_advice(|| "lane", state[idx], 0, || Ok(value))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok(in_state.try_into().unwrap())
+                },
+            )?;
+
+            let mut witnesses = compute_rho_witnesses(&self.in_state.map(f_to_biguint));
+            if self.overflow {
+                craft_step2_overflow(&mut witnesses[5]);
+            }
+            let out_state = config.assign_rotation_checks_with_witnesses(
+                &mut layouter,
+                &in_state,
+                &witnesses,
+            )?;
+
+            if let Some(expected) = self.out_state {
+                layouter.assign_region(
+                    || "check output state",
+                    |mut region| {
+                        for (idx, (lane, &expected)) in
+                            out_state.iter().zip(expected.iter()).enumerate()
+                        {
+                            let lane =
+                                lane.copy_advice(|| "output lane", &mut region, state[idx], 0)?;
+                            let expected = region.assign_advice(
+                                || "expected lane",
+                                state[idx],
+                                1,
+                                || Ok(expected),
+                            )?;
+                            region.constrain_equal(lane.cell(), expected.cell())?;
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_skip_final_overflow_check() {
+        let mut in_biguint = StateBigInt::default();
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_biguint[(x, y)] = convert_b2_to_b13(rand::random::<u64>());
+        }
+        // Chunk 63 of lane (1, 0) has to be 1 for the crafted overflow
+        in_biguint[(1, 0)] = convert_b2_to_b13(rand::random::<u64>() | 1 << 63);
+        let expected = KeccakFArith::rho(&in_biguint);
+        let mut in_state = [Fp::zero(); 25];
+        let mut out_state = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_state[5 * x + y] = biguint_to_f(&in_biguint[(x, y)]);
+            out_state[5 * x + y] = biguint_to_f(&expected[(x, y)]);
+        }
+
+        // Skipping the check doesn't change the output of an honest witness
+        let circuit = SkipCheckCircuit::<true> {
+            in_state,
+            out_state: Some(out_state),
+            overflow: false,
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The crafted overflow is only rejected by the final check
+        let circuit = SkipCheckCircuit::<false> {
+            in_state,
+            out_state: None,
+            overflow: true,
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+
+        let circuit = SkipCheckCircuit::<true> {
+            in_state,
+            out_state: None,
+            overflow: true,
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_rho_rejects_invalid_lane() {
+        use num_bigint::BigUint;
+
+        #[derive(Default)]
+        struct InvalidLaneCircuit {
+            in_state: [Fp; 25],
+        }
+
+        impl Circuit<Fp> for InvalidLaneCircuit {
+            type Config = (RhoConfig<Fp>, [Column<Advice>; 25]);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                configure_rho(meta)
+            }
+
+            fn synthesize(
+                &self,
+                (config, state): Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                config.stackable.load(&mut layouter)?;
+                let in_state: [AssignedCell<Fp, Fp>; 25] = layouter.assign_region(
+                    || "assign input state",
+                    |mut region| {
+                        let in_state = self
+                            .in_state
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, &value)| {
+                                region.assign_advice(|| "lane", state[idx], 0, || Ok(value))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        Ok(in_state.try_into().unwrap())
+                    },
+                )?;
+                config.assign_rotation_checks(&mut layouter, &in_state)?;
+                Ok(())
+            }
+        }
+
+        let mut in_state = [(); 25].map(|_| biguint_to_f(&convert_b2_to_b13(rand::random())));
+        // The special chunks of lane 7 sum to 13
+        in_state[7] = biguint_to_f(&(BigUint::from(6u8) + BigUint::from(B13).pow(64) * 7u8));
+        let circuit = InvalidLaneCircuit { in_state };
+        assert!(matches!(
+            MockProver::<Fp>::run(15, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
 }
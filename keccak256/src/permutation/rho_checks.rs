@@ -32,7 +32,7 @@
 //! circuit we have to do a lookup check for that
 //!
 //! The lookup is more efficient when we lookup
-//! multiple([`crate::permutation::rho_helpers::BASE_NUM_OF_CHUNKS`]) chunks at
+//! multiple([`crate::common::BASE_NUM_OF_CHUNKS`]) chunks at
 //! a time.
 //!
 //! ## Checks
@@ -52,6 +52,13 @@
 //! We have the copy constraint to glue input accumulator to input lane and the
 //! output accumulator to output lane
 //!
+//! The powers of base only depend on the rotation and the slicing of the lane,
+//! so they are already fixed values: the running sums are computed with
+//! [`crate::permutation::generic::GenericConfig::linear_combine_consts`], which
+//! assigns them from the constant column. The coefs depend on the lane value,
+//! so they have to stay in advice columns and no call site can move them to a
+//! fixed column.
+//!
 //! But we have special checks to do
 //!
 //! ### Sepcial Chunks
@@ -64,6 +71,17 @@
 //! permutation::tables::SpecialChunkTableConfig`] to convert it to
 //! `convert_b13_coef(low_value + high_value)`.
 //!
+//! The special chunks are not bits. Like every chunk of a Theta output lane,
+//! each counts the bits XORed into it, so a `low_value` of 2 is valid and
+//! stands for the bit 0. A boolean constraint on them would reject honest
+//! lanes. The lookup is the range check: the table only holds the pairs with
+//! `low_value + high_value < 13`, so any other remainder fails it.
+//!
+//! The lane `(0, 0)` has rotation 0 and goes through the same path. Its slices
+//! still start at chunk 1 and end at chunk 64, there's just no slice boundary
+//! for the rotation offset. The special output chunk is placed at `9**0`,
+//! which is where both the 0th chunk and the wrapped around 64th chunk belong.
+//!
 //! ### Overflow Checks
 //!
 //! The [`crate::permutation::tables::Base13toBase9TableConfig`] table is built
@@ -104,9 +122,17 @@
 //! witness any more than 3 non-zero chunks, the
 //! [`crate::permutation::tables::Base13toBase9TableConfig`] returns a overflow
 //! detector 170 and fail the final sum check.
+//!
+//! The same bounds hold for the sums of a single lane, which has no more
+//! step 2 or step 3 slices than all the lanes together. So a design that
+//! converts the lanes one at a time can check each lane right away with
+//! [`LaneRotateConversionConfig::assign_overflow_check`] instead of keeping
+//! the detectors of all 25 lanes around for the final check. That takes two
+//! running sums and two lookups per lane instead of two in total.
 use crate::arith_helpers::*;
-use crate::common::ROTATION_CONSTANTS;
-use crate::gate_helpers::{biguint_to_f, f_to_biguint};
+use crate::common::{LaneIndex, LANE_SIZE};
+use crate::error::KeccakError;
+use crate::gate_helpers::{biguint_to_f, f_to_biguint, try_biguint_to_f};
 use crate::permutation::{
     generic::GenericConfig,
     rho_helpers::*,
@@ -114,10 +140,65 @@ use crate::permutation::{
 };
 use eth_types::Field;
 use halo2_proofs::{
-    circuit::{AssignedCell, Layouter},
-    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    circuit::{AssignedCell, Layouter, Region},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Instance, Selector},
     poly::Rotation,
 };
+use itertools::Itertools;
+use num_bigint::BigUint;
+
+/// The cells of a lane that went through [`LaneRotateConversionConfig`].
+#[derive(Debug, Clone)]
+pub struct LaneConversionResult<F: Field> {
+    /// The output lane in base 9, the final cell of the output accumulator.
+    pub output_lane: AssignedCell<F, F>,
+    /// The overflow detectors of the step 2 slices, summed up in Rho.
+    pub step2_od: Vec<AssignedCell<F, F>>,
+    /// The overflow detectors of the step 3 slices, summed up in Rho.
+    pub step3_od: Vec<AssignedCell<F, F>>,
+    /// The base 13 input lane the slices are bound to. It is the cell given
+    /// to the conversion, copied into the subtraction of the chunks, so
+    /// constraining it binds the converted lane.
+    pub input_lane: AssignedCell<F, F>,
+    /// The final cell of the input accumulator, the input lane without the
+    /// special chunks.
+    pub input_acc: AssignedCell<F, F>,
+}
+
+/// The cells of the chunks of a lane, see
+/// [`LaneRotateConversionConfig::assign_chunks_at`].
+#[derive(Debug, Clone)]
+pub struct AssignedChunks<F: Field> {
+    input_coefs: Vec<AssignedCell<F, F>>,
+    input_pobs: Vec<F>,
+    output_coefs: Vec<AssignedCell<F, F>>,
+    output_pobs: Vec<F>,
+    step2_od: Vec<AssignedCell<F, F>>,
+    step3_od: Vec<AssignedCell<F, F>>,
+    rows: usize,
+}
+
+impl<F: Field> AssignedChunks<F> {
+    /// The rows the chunks took from their base offset on, always
+    /// [`LaneRotateConversionConfig::lane_rows`] of the lane.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+}
+
+impl<F: Field> Default for AssignedChunks<F> {
+    fn default() -> Self {
+        Self {
+            input_coefs: vec![],
+            input_pobs: vec![],
+            output_coefs: vec![],
+            output_pobs: vec![],
+            step2_od: vec![],
+            step3_od: vec![],
+            rows: 0,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct LaneRotateConversionConfig<F> {
@@ -130,6 +211,25 @@ pub struct LaneRotateConversionConfig<F> {
 }
 
 impl<F: Field> LaneRotateConversionConfig<F> {
+    /// The field has to hold a whole base 13 lane of 65 chunks, so every
+    /// value below `13**65`, about `2**240.5`. The powers of base and the
+    /// accumulators stay below that bound. Both BN256 fields have 254 bits.
+    /// A smaller field fails the assignment with
+    /// [`KeccakError::FieldConversion`].
+    ///
+    /// The config does not depend on the rotation of a lane. The rotation is
+    /// picked from the [`LaneIndex`] at assignment time, and the powers of base
+    /// it implies (including `9**rotation` for the special chunk) are assigned
+    /// as constants. One config serves all 25 lanes.
+    ///
+    /// No columns are allocated here: `advices` is taken from the caller and
+    /// every lane is assigned in its own region on the same three columns. The
+    /// coefs are copied into the running sums of [`GenericConfig`], so the
+    /// columns have to be equality enabled, which `RhoConfig` already does for
+    /// the whole state.
+    ///
+    /// Panics if `advices` has the same column twice: a shared column would
+    /// make the lookup compare a coef with itself and break every lane at once.
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         base13_to_9_table: &Base13toBase9TableConfig<F>,
@@ -138,12 +238,22 @@ impl<F: Field> LaneRotateConversionConfig<F> {
         generic: GenericConfig<F>,
         stackable: StackableTable<F>,
     ) -> Self {
+        assert!(
+            advices.iter().all_unique(),
+            "the lane conversion columns have to be distinct"
+        );
         let q_normal = meta.complex_selector();
         let [input_coef, output_coef, overflow_detector] = advices;
 
         meta.enable_equality(overflow_detector);
         meta.enable_constant(constant);
 
+        // The inputs have degree 2, the selector times the advice, so the
+        // lookup argument needs degree `2 + 2 + 1 = 5`.
+        //
+        // The overflow detector is witnessed like the coefs, even though the
+        // table determines it: a lookup only checks that the row is in the
+        // table, it can't fill a cell from it.
         meta.lookup("b13 -> b9 table", |meta| {
             let q_normal = meta.query_selector(q_normal);
             let base13_coef = meta.query_advice(input_coef, Rotation::cur());
@@ -166,11 +276,112 @@ impl<F: Field> LaneRotateConversionConfig<F> {
         }
     }
 
+    /// Same as [`Self::configure`], allocating its own columns: three advice
+    /// columns, a fixed column for the constants and the stackable table.
+    ///
+    /// The stackable table is then owned by this config, load it with
+    /// [`Self::load_stackable`] next to `base13_to_9_table`. Use
+    /// [`Self::configure`] to share the columns with other configs.
+    pub fn configure_standalone(
+        meta: &mut ConstraintSystem<F>,
+        base13_to_9_table: &Base13toBase9TableConfig<F>,
+    ) -> Self {
+        let advices = [(); 3].map(|_| {
+            let column = meta.advice_column();
+            meta.enable_equality(column);
+            column
+        });
+        let constant = meta.fixed_column();
+        let table_cols = [(); 3].map(|_| meta.lookup_table_column());
+        let stackable = StackableTable::configure(meta, advices, table_cols);
+        let generic = GenericConfig::configure(meta, advices, constant);
+        Self::configure(
+            meta,
+            base13_to_9_table,
+            advices,
+            constant,
+            generic,
+            stackable,
+        )
+    }
+
+    /// Loads the stackable table of a config from
+    /// [`Self::configure_standalone`].
+    pub fn load_stackable(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.stackable.load(layouter)
+    }
+
+    /// Binds the input lane of `result` to the `row` of the `instance` column,
+    /// so the base 13 input of the conversion is public.
+    ///
+    /// The instance column has to be equality enabled.
+    pub fn bind_input_to_instance(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        result: &LaneConversionResult<F>,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        layouter.constrain_instance(result.input_lane.cell(), instance, row)
+    }
+
+    /// Computes the output lane in base 9 without assigning anything.
+    ///
+    /// This runs the same slicing and conversions as [`Self::assign_lane`],
+    /// so the result is the value of its output lane, which makes it cheap to
+    /// precompute the expected state of a whole Rho step.
+    pub fn compute(lane_base_13: BigUint, lane: LaneIndex) -> BigUint {
+        let (_, special) = RhoLane::new(lane_base_13, lane.rotation()).get_full_witness();
+        special.output_acc_post
+    }
+
+    /// The `(input coef, output coef, overflow detector)` of each slice of
+    /// the lane, in slice order, as [`Self::assign_lane`] would assign them.
+    ///
+    /// Nothing is assigned. The slices are the ones of [`slice_lane`], the
+    /// special chunks are not included.
+    pub fn chunk_triples(lane_base_13: BigUint, lane: LaneIndex) -> Vec<(BigUint, BigUint, u32)> {
+        let (conversions, _) = RhoLane::new(lane_base_13, lane.rotation()).get_full_witness();
+        conversions
+            .into_iter()
+            .map(|conv| {
+                (
+                    conv.input.coef,
+                    conv.output.coef,
+                    conv.overflow_detector.value,
+                )
+            })
+            .collect()
+    }
+
+    /// Rotates and converts a lane, see [`LaneConversionResult`] for the
+    /// returned cells.
+    ///
+    /// The output lane is the final cell of the output accumulator, so callers
+    /// can copy it straight into the next step instead of re-deriving it.
+    pub fn assign_lane(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        lane: LaneIndex,
+    ) -> Result<LaneConversionResult<F>, Error> {
+        check_input_lane(&lane_base_13, lane)?;
+        let witness = RhoLane::new(
+            f_to_biguint(*lane_base_13.value().unwrap_or(&F::zero())),
+            lane.rotation(),
+        )
+        .get_full_witness();
+        self.assign_region_with_witness(layouter, lane_base_13, lane, &witness)
+    }
+
+    /// Returns the output lane in base 9 together with the step 2 and step 3
+    /// overflow detectors.
+    #[deprecated(note = "use `assign_lane`, which also returns the input cells")]
     pub fn assign_region(
         &self,
         layouter: &mut impl Layouter<F>,
         lane_base_13: AssignedCell<F, F>,
-        lane_idx: usize,
+        lane: LaneIndex,
     ) -> Result<
         (
             AssignedCell<F, F>,
@@ -179,98 +390,1513 @@ impl<F: Field> LaneRotateConversionConfig<F> {
         ),
         Error,
     > {
-        let rotation = {
-            let x = lane_idx / 5;
-            let y = lane_idx % 5;
-            ROTATION_CONSTANTS[x][y]
-        };
-        let (conversions, special) = RhoLane::new(
+        let result = self.assign_lane(layouter, lane_base_13, lane)?;
+        Ok((result.output_lane, result.step2_od, result.step3_od))
+    }
+
+    /// Same as [`Self::assign_lane`], also returning the overflow detector
+    /// of every slice of the lane, in slice order.
+    ///
+    /// Only the step 2 and step 3 sums are checked at the end of Rho, so when
+    /// that check fails these values tell which lane and slice overflowed.
+    #[cfg(feature = "debug-witness")]
+    pub fn assign_region_with_detectors(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        lane: LaneIndex,
+    ) -> Result<(LaneConversionResult<F>, Vec<F>), Error> {
+        check_input_lane(&lane_base_13, lane)?;
+        let witness = RhoLane::new(
             f_to_biguint(*lane_base_13.value().unwrap_or(&F::zero())),
-            rotation,
+            lane.rotation(),
         )
         .get_full_witness();
+        let detectors = witness
+            .0
+            .iter()
+            .map(|conv| F::from(conv.overflow_detector.value as u64))
+            .collect();
+        let result = self.assign_region_with_witness(layouter, lane_base_13, lane, &witness)?;
+        Ok((result, detectors))
+    }
+
+    /// Same as [`Self::assign_lane`], with a witness that was computed ahead
+    /// of time by [`compute_rho_witnesses`].
+    pub fn assign_region_with_witness(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        lane: LaneIndex,
+        witness: &RhoWitness,
+    ) -> Result<LaneConversionResult<F>, Error> {
+        check_input_lane(&lane_base_13, lane)?;
+        let chunks = layouter.assign_region(
+            || chunks_region_name(lane),
+            |mut region| self.assign_chunks_at(&mut region, 0, lane, witness),
+        )?;
+        self.combine_chunks(layouter, lane_base_13, chunks)
+    }
+
+    /// The rows [`Self::assign_chunks_at`] takes for `lane`, one per slice
+    /// and one for the special chunk.
+    pub fn lane_rows(lane: LaneIndex) -> usize {
+        slice_lane(lane.rotation()).len() + 1
+    }
+
+    /// Describes the chunk assigned at `offset` of the region of `lane`, to
+    /// locate a failure MockProver reports by region offset.
+    pub fn describe_row(lane: LaneIndex, offset: usize) -> String {
+        let rotation = lane.rotation();
         let slices = slice_lane(rotation);
+        match slices.get(offset) {
+            Some(&(chunk_idx, step)) => chunk_label(chunk_idx, step, rotation),
+            None if offset == slices.len() => "special chunk".to_string(),
+            None => "outside the lane".to_string(),
+        }
+    }
 
-        let (input_coefs, input_pobs, output_coefs, output_pobs, step2_od, step3_od) = layouter
-            .assign_region(
-                || "lane rotate conversion",
-                |mut region| {
-                    let mut input_coefs: Vec<AssignedCell<F, F>> = vec![];
-                    let mut output_coefs: Vec<AssignedCell<F, F>> = vec![];
-                    let mut input_pobs: Vec<F> = vec![];
-                    let mut output_pobs: Vec<F> = vec![];
-                    let mut step2_od: Vec<AssignedCell<F, F>> = vec![];
-                    let mut step3_od: Vec<AssignedCell<F, F>> = vec![];
-                    for (offset, (&(chunk_idx, step), conv)) in
-                        slices.iter().zip(conversions.iter()).enumerate()
-                    {
-                        self.q_normal.enable(&mut region, offset)?;
-                        let input_coef = region.assign_advice(
-                            || format!("Input Coef {}", chunk_idx),
-                            self.input_coef,
-                            offset,
-                            || Ok(biguint_to_f::<F>(&conv.input.coef)),
-                        )?;
-                        input_coefs.push(input_coef);
-                        input_pobs.push(biguint_to_f::<F>(&conv.input.power_of_base));
-                        let output_coef = region.assign_advice(
-                            || "Output Coef",
-                            self.output_coef,
-                            offset,
-                            || Ok(biguint_to_f::<F>(&conv.output.coef)),
-                        )?;
-                        output_coefs.push(output_coef);
-                        output_pobs.push(biguint_to_f::<F>(&conv.output.power_of_base));
-
-                        let od = region.assign_advice(
-                            || "Overflow detector",
-                            self.overflow_detector,
-                            offset,
-                            || Ok(F::from(conv.overflow_detector.value as u64)),
-                        )?;
-                        match step {
-                            1 => region.constrain_constant(od.cell(), F::zero())?,
-                            2 => step2_od.push(od),
-                            3 => step3_od.push(od),
-                            4 => { // Do nothing
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
-                    // Special chunk
-                    let final_output_coef = region.assign_advice(
-                        || "Special output coef",
-                        self.output_coef,
-                        slices.len(),
-                        || Ok(F::from(special.output_coef as u64)),
-                    )?;
-                    let final_output_pob = F::from(B9 as u64).pow(&[rotation.into(), 0, 0, 0]);
-                    output_coefs.push(final_output_coef);
-                    output_pobs.push(final_output_pob);
-
-                    Ok((
-                        input_coefs,
-                        input_pobs,
-                        output_coefs,
-                        output_pobs,
-                        step2_od,
-                        step3_od,
-                    ))
-                },
+    /// Assigns the chunks of a lane in `region` from row `base_offset` on,
+    /// over [`Self::lane_rows`] rows.
+    ///
+    /// The gates only query the current row, so several lanes can be packed
+    /// in one region at the offsets the caller picks. Each lane is then
+    /// finished with [`Self::combine_chunks`].
+    pub fn assign_chunks_at(
+        &self,
+        region: &mut Region<'_, F>,
+        base_offset: usize,
+        lane: LaneIndex,
+        witness: &RhoWitness,
+    ) -> Result<AssignedChunks<F>, Error> {
+        let rotation = lane.rotation();
+        let (conversions, special) = witness;
+        let slices = slice_lane(rotation);
+        check_witness(lane, &slices, witness)?;
+
+        // The accumulators are summed in the field, so each of their values
+        // has to fit in it, see the field size note on the config.
+        for conv in conversions.iter() {
+            try_biguint_to_f::<F>(&conv.input.pre_acc)?;
+            try_biguint_to_f::<F>(&conv.output.pre_acc)?;
+        }
+        try_biguint_to_f::<F>(&special.output_acc_post)?;
+
+        let mut chunks = AssignedChunks::default();
+        for (row, (&(chunk_idx, step), conv)) in slices.iter().zip(conversions.iter()).enumerate() {
+            let offset = base_offset + row;
+            let label = chunk_label(chunk_idx, step, rotation);
+            self.q_normal.enable(region, offset)?;
+            let input_coef = region.assign_advice(
+                || format!("Input Coef, {}", label),
+                self.input_coef,
+                offset,
+                || Ok(try_biguint_to_f::<F>(&conv.input.coef)?),
             )?;
-        let input_from_chunks =
+            chunks.input_coefs.push(input_coef);
+            chunks
+                .input_pobs
+                .push(try_biguint_to_f::<F>(&conv.input.power_of_base)?);
+            let output_coef = region.assign_advice(
+                || format!("Output Coef, {}", label),
+                self.output_coef,
+                offset,
+                || Ok(try_biguint_to_f::<F>(&conv.output.coef)?),
+            )?;
+            chunks.output_coefs.push(output_coef);
+            chunks
+                .output_pobs
+                .push(try_biguint_to_f::<F>(&conv.output.power_of_base)?);
+
+            let od_name = || format!("Overflow detector, {}", label);
+            let od = region.assign_advice(od_name, self.overflow_detector, offset, || {
+                Ok(F::from(conv.overflow_detector.value as u64))
+            })?;
+            #[cfg(feature = "trace")]
+            crate::trace::record(|| chunks_region_name(lane), od_name, offset, &od);
+            match step {
+                1 => region.constrain_constant(od.cell(), F::zero())?,
+                2 => chunks.step2_od.push(od),
+                3 => chunks.step3_od.push(od),
+                4 => { // Do nothing
+                }
+                _ => unreachable!(),
+            }
+        }
+        // Special chunk, on the row right after the slices
+        let special_offset = base_offset + slices.len();
+        let special_name = || "Special output coef";
+        let final_output_coef =
+            region.assign_advice(special_name, self.output_coef, special_offset, || {
+                Ok(F::from(special.output_coef as u64))
+            })?;
+        // The input of the special chunk lookup
+        #[cfg(feature = "trace")]
+        crate::trace::record(
+            || chunks_region_name(lane),
+            special_name,
+            special_offset,
+            &final_output_coef,
+        );
+        chunks.rows = special_offset + 1 - base_offset;
+        let final_output_pob = F::from(B9 as u64).pow(&[rotation.into(), 0, 0, 0]);
+        chunks.output_coefs.push(final_output_coef);
+        chunks.output_pobs.push(final_output_pob);
+        Ok(chunks)
+    }
+
+    /// Binds the chunks assigned by [`Self::assign_chunks_at`] to the input
+    /// lane and sums up the output lane.
+    pub fn combine_chunks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        chunks: AssignedChunks<F>,
+    ) -> Result<LaneConversionResult<F>, Error> {
+        let AssignedChunks {
+            input_coefs,
+            input_pobs,
+            output_coefs,
+            output_pobs,
+            step2_od,
+            step3_od,
+            rows: _,
+        } = chunks;
+        let (diff, input_from_chunks) =
+            self.subtract_chunks(layouter, lane_base_13.clone(), input_coefs, input_pobs)?;
+
+        self.stackable
+            .lookup_special_chunks(layouter, &diff, output_coefs.last().unwrap())?;
+
+        let output_lane =
             self.generic
-                .linear_combine_consts(layouter, input_coefs, input_pobs, None)?;
-        let diff = self
+                .linear_combine_consts(layouter, output_coefs, output_pobs, None)?;
+        Ok(LaneConversionResult {
+            output_lane,
+            step2_od,
+            step3_od,
+            input_lane: lane_base_13,
+            input_acc: input_from_chunks,
+        })
+    }
+
+    /// Range checks the step 2 and step 3 overflow detectors of one lane, as
+    /// the final check of Rho does for all the lanes.
+    ///
+    /// A lane checked here doesn't need to go through the final check, see
+    /// the overflow checks in the module docs.
+    pub fn assign_overflow_check(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        result: &LaneConversionResult<F>,
+    ) -> Result<(), Error> {
+        let step2_sum = self
+            .generic
+            .running_sum(layouter, result.step2_od.clone(), None)?;
+        let step3_sum = self
             .generic
-            .sub_advice(layouter, lane_base_13, input_from_chunks)?;
+            .running_sum(layouter, result.step3_od.clone(), None)?;
+        self.stackable.lookup_range_12(layouter, &[step2_sum])?;
+        self.stackable.lookup_range_169(layouter, &[step3_sum])
+    }
+
+    /// Same as [`Self::assign_region_with_witness`], but the output lane stays
+    /// in base 13, see [`rotate_b13_lane`]. Each chunk only moves to its
+    /// rotated position, and the special chunks to their sum.
+    ///
+    /// The chunks still go through the base 13 to base 9 lookup, which range
+    /// checks them and gives their overflow detectors. These have to be range
+    /// checked by the caller like in Rho. The output is left for a later base
+    /// conversion, like the one in front of Chi.
+    pub fn assign_rotation_b13(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        lane: LaneIndex,
+        witness: &RhoWitness,
+    ) -> Result<LaneConversionResult<F>, Error> {
+        let rotation = lane.rotation();
+        check_input_lane(&lane_base_13, lane)?;
+        let chunks = layouter.assign_region(
+            || chunks_region_name(lane),
+            |mut region| self.assign_chunks_at(&mut region, 0, lane, witness),
+        )?;
+        let (_, special) = witness;
+        let AssignedChunks {
+            input_coefs,
+            input_pobs,
+            step2_od,
+            step3_od,
+            ..
+        } = chunks;
+        let (diff, input_from_chunks) = self.subtract_chunks(
+            layouter,
+            lane_base_13.clone(),
+            input_coefs.clone(),
+            input_pobs,
+        )?;
 
+        let special_chunks = special.input.to_radix_le(B13.into());
+        let special_sum = special_chunks.first().copied().unwrap_or(0)
+            + special_chunks.get(LANE_SIZE as usize).copied().unwrap_or(0);
+        let special_sum = layouter.assign_region(
+            || "special chunk sum",
+            |mut region| {
+                region.assign_advice(
+                    || "special chunk sum",
+                    self.output_coef,
+                    0,
+                    || Ok(F::from(special_sum as u64)),
+                )
+            },
+        )?;
         self.stackable
-            .lookup_special_chunks(layouter, &diff, output_coefs.last().unwrap())?;
+            .lookup_special_chunk_sum(layouter, &diff, &special_sum)?;
 
+        let b13_pow = |power: u32| F::from(B13 as u64).pow(&[power.into(), 0, 0, 0]);
+        let output_pobs = slice_lane(rotation)
+            .iter()
+            .map(|&(chunk_idx, _)| b13_pow((chunk_idx + rotation) % LANE_SIZE))
+            .chain(std::iter::once(b13_pow(rotation)))
+            .collect();
+        let output_coefs = input_coefs
+            .into_iter()
+            .chain(std::iter::once(special_sum))
+            .collect();
         let output_lane =
             self.generic
                 .linear_combine_consts(layouter, output_coefs, output_pobs, None)?;
-        Ok((output_lane, step2_od, step3_od))
+        Ok(LaneConversionResult {
+            output_lane,
+            step2_od,
+            step3_od,
+            input_lane: lane_base_13,
+            input_acc: input_from_chunks,
+        })
+    }
+
+    /// Sums up the input chunks and subtracts them from the input lane, which
+    /// leaves the special chunks. Returns them along with the sum.
+    fn subtract_chunks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane_base_13: AssignedCell<F, F>,
+        input_coefs: Vec<AssignedCell<F, F>>,
+        input_pobs: Vec<F>,
+    ) -> Result<(AssignedCell<F, F>, AssignedCell<F, F>), Error> {
+        let input_from_chunks =
+            self.generic
+                .linear_combine_consts(layouter, input_coefs, input_pobs, None)?;
+        let diff = self
+            .generic
+            .sub_advice(layouter, lane_base_13, input_from_chunks.clone())?;
+        // The special chunk lookup only accepts a diff made of the special
+        // chunks, so a witness that doesn't consume every normal chunk fails
+        // there.
+        Ok((diff, input_from_chunks))
+    }
+}
+
+/// The annotation of the region the chunks of a lane are assigned in, see
+/// [`LaneRotateConversionConfig::assign_chunks_at`].
+fn chunks_region_name(lane: LaneIndex) -> String {
+    format!(
+        "lane rotate conversion {} (rotation {})",
+        lane.index(),
+        lane.rotation()
+    )
+}
+
+fn chunk_label(chunk_idx: u32, step: u32, rotation: u32) -> String {
+    let at_offset = if rotation != 0 && chunk_idx == LANE_SIZE - rotation {
+        ", at the rotation offset"
+    } else {
+        ""
+    };
+    format!("chunk {} step {}{}", chunk_idx, step, at_offset)
+}
+
+/// Checks that a witness from [`RhoLane::get_full_witness`] is the one of the
+/// `lane`, so a witness computed for another lane or a broken one
+/// is refused with [`KeccakError::WitnessMismatch`] rather than showing up as
+/// a failed constraint of a whole MockProver run.
+///
+/// There is a conversion for each slice of the lane, the powers of base are
+/// those of the chunk and of its rotated position, and the input accumulator
+/// shrinks by each slice down to the special chunks.
+fn check_witness(
+    lane: LaneIndex,
+    slices: &[(u32, u32)],
+    witness: &RhoWitness,
+) -> Result<(), KeccakError> {
+    let rotation = lane.rotation();
+    let (conversions, special) = witness;
+    if conversions.len() != slices.len() {
+        return Err(KeccakError::WitnessMismatch(lane.index()));
+    }
+    for (i, (&(chunk_idx, _), conv)) in slices.iter().zip(conversions.iter()).enumerate() {
+        let next_acc = conversions
+            .get(i + 1)
+            .map_or(&special.input, |next| &next.input.pre_acc);
+        if conv.input.power_of_base != BigUint::from(B13).pow(chunk_idx)
+            || conv.output.power_of_base
+                != BigUint::from(B9).pow((chunk_idx + rotation) % LANE_SIZE)
+            || next_acc + &conv.input.coef * &conv.input.power_of_base != conv.input.pre_acc
+        {
+            return Err(KeccakError::WitnessMismatch(lane.index()));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `value` is a lane the conversion accepts: 65 chunks in base 13
+/// whose special chunks, the lowest and the highest, sum to less than 13.
+///
+/// The Theta output lanes have this shape. In the circuit every normal chunk
+/// is looked up in the [`Base13toBase9TableConfig`] and the special chunks in
+/// the special chunk table, so any other value fails there. This catches the
+/// mistake when the lane is witnessed instead.
+pub fn is_valid_base13_lane<F: Field>(value: F) -> bool {
+    let value = f_to_biguint(value);
+    if value >= BigUint::from(B13).pow(LANE_SIZE + 1) {
+        return false;
+    }
+    let chunks = value.to_radix_le(B13.into());
+    let low = chunks.first().copied().unwrap_or(0);
+    let high = chunks.get(LANE_SIZE as usize).copied().unwrap_or(0);
+    low + high < B13
+}
+
+/// Fails with [`KeccakError::InvalidRhoInput`] if the witnessed `lane` isn't
+/// a valid base 13 lane, see [`is_valid_base13_lane`].
+pub fn check_input_lane<F: Field>(
+    lane_base_13: &AssignedCell<F, F>,
+    lane: LaneIndex,
+) -> Result<(), KeccakError> {
+    match lane_base_13.value() {
+        Some(value) if !is_valid_base13_lane(*value) => {
+            Err(KeccakError::InvalidRhoInput(lane.index()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{BASE_NUM_OF_CHUNKS, ROTATION_CONSTANTS};
+    use crate::test_utils::{
+        craft_step2_overflow, test_lane_conversion, LaneCircuit, LaneCircuitConfig,
+    };
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::{FailureLocation, MockProver, VerifyFailure},
+        pairing::bn256::Fr as Fp,
+        plonk::Circuit,
+    };
+    use pretty_assertions::assert_eq;
+
+    fn lane_at(idx: usize) -> LaneIndex {
+        LaneIndex::from_index(idx).unwrap()
+    }
+
+    fn lane_case(lane_idx: usize, value: u64) -> (LaneIndex, Fp, Fp) {
+        let index = lane_at(lane_idx);
+        let lane = convert_b2_to_b13(value);
+        let expected = convert_b13_lane_to_b9(lane.clone(), index.rotation());
+        (index, biguint_to_f(&lane), biguint_to_f(&expected))
+    }
+
+    #[test]
+    fn test_output_lane_copy() {
+        let (lane_idx, lane, expected) = lane_case(1, 0x0123_4567_89ab_cdef);
+
+        let circuit = LaneCircuit::<Fp> {
+            lanes: vec![(lane_idx, lane, expected)],
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = LaneCircuit::<Fp> {
+            lanes: vec![(lane_idx, lane, expected + Fp::one())],
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_zero_rotation_lane() {
+        // A Theta output lane with both special chunks set
+        let mut chunks: Vec<u8> = (0..65).map(|_| rand::random::<u8>() % B13).collect();
+        for (low, high) in [(5, 3), (0, 12), (12, 0)] {
+            chunks[0] = low;
+            chunks[64] = high;
+            let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+            let expected = convert_b13_lane_to_b9(lane.clone(), 0);
+            let circuit = LaneCircuit::<Fp> {
+                lanes: vec![(lane_at(0), biguint_to_f(&lane), biguint_to_f(&expected))],
+            };
+            let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+        assert_eq!(ROTATION_CONSTANTS[0][0], 0);
+    }
+
+    #[test]
+    fn test_lane_conversion_result() {
+        #[derive(Default)]
+        struct InputCircuit<F> {
+            lane_idx: usize,
+            lane: F,
+            special: F,
+            /// Copied into the input lane of the result
+            bound: F,
+        }
+
+        impl<F: Field> Circuit<F> for InputCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let result = config.lane_config.assign_lane(
+                    &mut layouter,
+                    lane.clone(),
+                    lane_at(self.lane_idx),
+                )?;
+                if let (Some(lane), Some(acc)) = (lane.value(), result.input_acc.value()) {
+                    // The input accumulator leaves the special chunks out
+                    assert_eq!(*lane - *acc, self.special);
+                }
+                layouter.assign_region(
+                    || "bound input lane",
+                    |mut region| {
+                        let bound = region.assign_advice(
+                            || "bound lane",
+                            config.copied,
+                            0,
+                            || Ok(self.bound),
+                        )?;
+                        region.constrain_equal(result.input_lane.cell(), bound.cell())
+                    },
+                )
+            }
+        }
+
+        let mut chunks: Vec<u8> = (0..65).map(|i| (i % 13) as u8).collect();
+        chunks[0] = 3;
+        chunks[64] = 7;
+        let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+        let special = BigUint::from(3u64) + BigUint::from(7u64) * BigUint::from(B13).pow(64);
+        let circuit = InputCircuit::<Fp> {
+            lane_idx: 6,
+            lane: biguint_to_f(&lane),
+            special: biguint_to_f(&special),
+            bound: biguint_to_f(&lane),
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The input lane is the converted one, a tampered copy of it fails
+        let circuit = InputCircuit::<Fp> {
+            bound: biguint_to_f(&lane) + Fp::one(),
+            ..circuit
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+    }
+
+    #[test]
+    fn test_input_residual() {
+        #[derive(Default)]
+        struct ResidualCircuit<F> {
+            lane: F,
+            witness_lane: BigUint,
+        }
+
+        impl<F: Field> Circuit<F> for ResidualCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let witness = RhoLane::new(self.witness_lane.clone(), ROTATION_CONSTANTS[1][1])
+                    .get_full_witness();
+                config.lane_config.assign_region_with_witness(
+                    &mut layouter,
+                    lane,
+                    lane_at(6),
+                    &witness,
+                )?;
+                Ok(())
+            }
+        }
+
+        // The chunks of the witness miss chunk 10 of the lane, which is left
+        // over in the input accumulator.
+        let witness_lane = convert_b2_to_b13(0x0123_4567_89ab_cdef);
+        let lane = &witness_lane + BigUint::from(B13).pow(10);
+        let circuit = ResidualCircuit::<Fp> {
+            lane: biguint_to_f(&lane),
+            witness_lane: witness_lane.clone(),
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::Lookup { .. })));
+
+        // Without the residual the same witness is accepted
+        let circuit = ResidualCircuit::<Fp> {
+            lane: biguint_to_f(&witness_lane),
+            witness_lane,
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "the lane conversion columns have to be distinct")]
+    fn test_shared_lane_columns() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let table = Base13toBase9TableConfig::configure(&mut meta);
+        let [coef, od] = [(); 2].map(|_| meta.advice_column());
+        let constant = meta.fixed_column();
+        let generic = GenericConfig::configure(&mut meta, [coef, coef, od], constant);
+        let table_cols = [(); 3].map(|_| meta.lookup_table_column());
+        let stackable = StackableTable::configure(&mut meta, [coef, coef, od], table_cols);
+        LaneRotateConversionConfig::configure(
+            &mut meta,
+            &table,
+            [coef, coef, od],
+            constant,
+            generic,
+            stackable,
+        );
+    }
+
+    #[test]
+    fn test_check_witness() {
+        /// Converts `lane` as lane 6 with the witness of `rotation`.
+        #[derive(Default)]
+        struct WitnessCircuit<F> {
+            lane: F,
+            rotation: u32,
+        }
+
+        impl<F: Field> Circuit<F> for WitnessCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let witness =
+                    RhoLane::new(f_to_biguint(self.lane), self.rotation).get_full_witness();
+                config.lane_config.assign_region_with_witness(
+                    &mut layouter,
+                    lane,
+                    lane_at(6),
+                    &witness,
+                )?;
+                Ok(())
+            }
+        }
+
+        let index = lane_at(6);
+        let rotation = index.rotation();
+        let slices = slice_lane(rotation);
+        let lane = convert_b2_to_b13(0x0123_4567_89ab_cdef);
+        let mut witness = RhoLane::new(lane.clone(), rotation).get_full_witness();
+        assert_eq!(check_witness(index, &slices, &witness), Ok(()));
+
+        // The witness of another lane
+        let other_rotation = ROTATION_CONSTANTS[2][3];
+        let other = RhoLane::new(lane.clone(), other_rotation).get_full_witness();
+        assert_eq!(
+            check_witness(index, &slices, &other),
+            Err(KeccakError::WitnessMismatch(6))
+        );
+
+        // The input accumulator doesn't shrink by the slice
+        witness.0[3].input.coef += 1u64;
+        assert_eq!(
+            check_witness(index, &slices, &witness),
+            Err(KeccakError::WitnessMismatch(6))
+        );
+
+        // The conversion refuses the witness of another lane
+        let circuit = WitnessCircuit::<Fp> {
+            lane: biguint_to_f(&lane),
+            rotation: other_rotation,
+        };
+        assert!(matches!(
+            MockProver::<Fp>::run(15, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn test_pack_lanes_in_one_region() {
+        #[derive(Default)]
+        struct PackedCircuit<F> {
+            lanes: Vec<(LaneIndex, F, F)>,
+        }
+
+        impl<F: Field> Circuit<F> for PackedCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let witnesses = self
+                    .lanes
+                    .iter()
+                    .map(|&(index, lane, _)| {
+                        RhoLane::new(f_to_biguint(lane), index.rotation()).get_full_witness()
+                    })
+                    .collect_vec();
+                let chunks = layouter.assign_region(
+                    || "packed lanes",
+                    |mut region| {
+                        let mut base_offset = 0;
+                        let mut chunks = vec![];
+                        for (&(index, _, _), witness) in self.lanes.iter().zip(witnesses.iter()) {
+                            let lane_chunks = config.lane_config.assign_chunks_at(
+                                &mut region,
+                                base_offset,
+                                index,
+                                witness,
+                            )?;
+                            assert_eq!(
+                                lane_chunks.rows(),
+                                LaneRotateConversionConfig::<F>::lane_rows(index)
+                            );
+                            base_offset += lane_chunks.rows();
+                            chunks.push(lane_chunks);
+                        }
+                        Ok(chunks)
+                    },
+                )?;
+                for (&(_, lane, expected), chunks) in self.lanes.iter().zip(chunks) {
+                    let lane = config.assign_input_lane(&mut layouter, lane)?;
+                    let result = config
+                        .lane_config
+                        .combine_chunks(&mut layouter, lane, chunks)?;
+                    if let Some(output) = result.output_lane.value() {
+                        assert_eq!(*output, expected);
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        // The second lane starts right after the rows of the first one
+        let lanes = vec![
+            lane_case(1, 0x0123_4567_89ab_cdef),
+            lane_case(7, 0xfedc_ba98_7654_3210),
+        ];
+        let circuit = PackedCircuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Every rotation takes the rows it reports
+        let lanes = (0..25)
+            .map(|lane_idx| lane_case(lane_idx, rand::random()))
+            .collect();
+        let circuit = PackedCircuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_failure_chunk_metadata() {
+        struct CorruptCircuit<F> {
+            lane: F,
+            corrupt: usize,
+        }
+
+        impl<F: Field> Circuit<F> for CorruptCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    lane: F::zero(),
+                    corrupt: self.corrupt,
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let (mut conversions, special) =
+                    RhoLane::new(f_to_biguint(self.lane), ROTATION_CONSTANTS[1][1])
+                        .get_full_witness();
+                conversions[self.corrupt].output.coef += 1u64;
+                config.lane_config.assign_region_with_witness(
+                    &mut layouter,
+                    lane,
+                    lane_at(6),
+                    &(conversions, special),
+                )?;
+                Ok(())
+            }
+        }
+
+        let (_, lane, _) = lane_case(6, 0x0123_4567_89ab_cdef);
+        let corrupt = 3;
+        let circuit = CorruptCircuit::<Fp> { lane, corrupt };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        let located = failures.iter().find_map(|failure| match failure {
+            VerifyFailure::Lookup {
+                location: FailureLocation::InRegion { region, offset },
+                ..
+            } => Some((format!("{:?}", region), *offset)),
+            _ => None,
+        });
+        let (region, offset) = located.expect("the corrupted chunk fails its lookup");
+        assert!(region.contains("lane rotate conversion 6 (rotation 44)"));
+        assert_eq!(offset, corrupt);
+        let (chunk_idx, step) = slice_lane(44)[corrupt];
+        assert!(
+            LaneRotateConversionConfig::<Fp>::describe_row(lane_at(6), offset)
+                .starts_with(&format!("chunk {} step {}", chunk_idx, step))
+        );
+    }
+
+    #[test]
+    fn test_special_chunk_wraparound() {
+        let index = lane_at(6);
+        let rotation = index.rotation();
+        let mut chunks: Vec<u8> = (0..65).map(|i| (i * 7 % 13) as u8).collect();
+        for (low, high) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            chunks[0] = low;
+            chunks[64] = high;
+            let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+
+            // The reference lane takes each chunk as the parity of its bits,
+            // with bit 0 the XOR of the two special chunks.
+            let bits = (1..64).fold((low ^ high) as u64, |bits, i| {
+                bits | ((chunks[i] & 1) as u64) << i
+            });
+            let expected = convert_b2_to_b9(bits.rotate_left(rotation));
+
+            let (_, special) = RhoLane::new(lane.clone(), rotation).get_full_witness();
+            assert_eq!(special.output_coef, low ^ high);
+            assert_eq!(special.output_acc_post, expected);
+
+            let circuit = LaneCircuit::<Fp> {
+                lanes: vec![(index, biguint_to_f(&lane), biguint_to_f(&expected))],
+            };
+            let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_lane_conversion_over_fq() {
+        use halo2_proofs::pairing::bn256::Fq;
+        let lane = convert_b2_to_b13(0x0123_4567_89ab_cdef);
+        let expected = convert_b13_lane_to_b9(lane.clone(), ROTATION_CONSTANTS[1][1]);
+        let circuit = LaneCircuit::<Fq> {
+            lanes: vec![(lane_at(6), biguint_to_f(&lane), biguint_to_f(&expected))],
+        };
+        let prover = MockProver::<Fq>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_max_lane_fits_field() {
+        // Every normal chunk at 12 and the special chunks summing to 12
+        let mut chunks = vec![12u8; 65];
+        chunks[0] = 6;
+        chunks[64] = 6;
+        let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+        assert!(is_valid_base13_lane::<Fp>(biguint_to_f(&lane)));
+        let modulus = f_to_biguint(-Fp::one()) + 1u64;
+        assert!(BigUint::from(B13).pow(LANE_SIZE + 1) < modulus);
+
+        let rotation = ROTATION_CONSTANTS[1][1];
+        let (conversions, special) = RhoLane::new(lane.clone(), rotation).get_full_witness();
+        for conv in conversions.iter() {
+            assert!(try_biguint_to_f::<Fp>(&conv.input.pre_acc).is_ok());
+            assert!(try_biguint_to_f::<Fp>(&conv.output.pre_acc).is_ok());
+        }
+        let expected = special.output_acc_post;
+        let circuit = LaneCircuit::<Fp> {
+            lanes: vec![(lane_at(6), biguint_to_f(&lane), biguint_to_f(&expected))],
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_configure_standalone() {
+        #[derive(Default)]
+        struct StandaloneCircuit<F> {
+            lane: F,
+            expected: F,
+        }
+
+        impl<F: Field> Circuit<F> for StandaloneCircuit<F> {
+            type Config = (LaneRotateConversionConfig<F>, Base13toBase9TableConfig<F>);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let table = Base13toBase9TableConfig::configure(meta);
+                (
+                    LaneRotateConversionConfig::configure_standalone(meta, &table),
+                    table,
+                )
+            }
+
+            fn synthesize(
+                &self,
+                (config, table): Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                table.load(&mut layouter)?;
+                config.load_stackable(&mut layouter)?;
+                let lane = layouter.assign_region(
+                    || "input lane",
+                    |mut region| {
+                        region.assign_advice(|| "lane", config.input_coef, 0, || Ok(self.lane))
+                    },
+                )?;
+                let output = config
+                    .assign_lane(&mut layouter, lane, lane_at(1))?
+                    .output_lane;
+                layouter.assign_region(
+                    || "expected lane",
+                    |mut region| {
+                        let expected = region.assign_advice(
+                            || "expected",
+                            config.input_coef,
+                            0,
+                            || Ok(self.expected),
+                        )?;
+                        region.constrain_equal(output.cell(), expected.cell())
+                    },
+                )
+            }
+        }
+
+        let (_, lane, expected) = lane_case(1, 0x0123_4567_89ab_cdef);
+        let circuit = StandaloneCircuit::<Fp> { lane, expected };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let circuit = StandaloneCircuit::<Fp> {
+            lane,
+            expected: expected + Fp::one(),
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_special_chunk_rejects_wrong_coef() {
+        struct WrongSpecialCircuit<F> {
+            lane: F,
+        }
+
+        impl<F: Field> Circuit<F> for WrongSpecialCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self { lane: F::zero() }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let (conversions, mut special) =
+                    RhoLane::new(f_to_biguint(self.lane), ROTATION_CONSTANTS[1][1])
+                        .get_full_witness();
+                // The coef no longer matches the special chunks of the lane
+                special.output_coef ^= 1;
+                config.lane_config.assign_region_with_witness(
+                    &mut layouter,
+                    lane,
+                    lane_at(6),
+                    &(conversions, special),
+                )?;
+                Ok(())
+            }
+        }
+
+        // Special chunks 3 and 4 sum to 7, an odd coef
+        let mut chunks = vec![1u8; 65];
+        chunks[0] = 3;
+        chunks[64] = 4;
+        let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+        let circuit = WrongSpecialCircuit::<Fp> {
+            lane: biguint_to_f(&lane),
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        assert!(failures.iter().any(|failure| match failure {
+            VerifyFailure::Lookup {
+                location: FailureLocation::InRegion { region, .. },
+                ..
+            } => format!("{:?}", region).contains("lookup for special chunks"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_bind_input_to_instance() {
+        #[derive(Default)]
+        struct BoundCircuit<F> {
+            lane: F,
+        }
+
+        impl<F: Field> Circuit<F> for BoundCircuit<F> {
+            type Config = (LaneCircuitConfig<F>, Column<Instance>);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                let instance = meta.instance_column();
+                meta.enable_equality(instance);
+                (LaneCircuit::configure(meta), instance)
+            }
+
+            fn synthesize(
+                &self,
+                (config, instance): Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let result = config
+                    .lane_config
+                    .assign_lane(&mut layouter, lane, lane_at(3))?;
+                config
+                    .lane_config
+                    .bind_input_to_instance(&mut layouter, &result, instance, 0)
+            }
+        }
+
+        let (_, lane, _) = lane_case(3, 0x0123_4567_89ab_cdef);
+        let circuit = BoundCircuit::<Fp> { lane };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![vec![lane]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![vec![lane + Fp::one()]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_special_chunks_are_not_bits() {
+        // Even special chunk counts are valid and stand for an unset bit
+        let mut chunks = vec![0u8; 65];
+        for (low, high) in [(2, 0), (0, 2), (2, 3), (12, 0)] {
+            chunks[0] = low;
+            chunks[64] = high;
+            let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+            let expected =
+                convert_b2_to_b9((((low + high) & 1) as u64).rotate_left(ROTATION_CONSTANTS[1][1]));
+            assert_eq!(
+                LaneRotateConversionConfig::<Fp>::compute(lane.clone(), lane_at(6)),
+                expected
+            );
+            let circuit = LaneCircuit::<Fp> {
+                lanes: vec![(lane_at(6), biguint_to_f(&lane), biguint_to_f(&expected))],
+            };
+            let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_chunk_triples() {
+        let mut chunks: Vec<u8> = (0..65).map(|i| (i * 5 % 13) as u8).collect();
+        chunks[0] = 4;
+        chunks[64] = 3;
+        let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+        for index in LaneIndex::all() {
+            let rotation = index.rotation();
+            let triples = LaneRotateConversionConfig::<Fp>::chunk_triples(lane.clone(), index);
+            let slices = slice_lane(rotation);
+            assert_eq!(triples.len(), slices.len());
+
+            let mut input = BigUint::from(4u64) + BigUint::from(3u64) * BigUint::from(B13).pow(64);
+            let mut output =
+                BigUint::from(convert_b13_coef(4 + 3)) * BigUint::from(B9).pow(rotation);
+            for ((input_coef, output_coef, od), &(chunk_idx, step)) in
+                triples.iter().zip(slices.iter())
+            {
+                input += input_coef * BigUint::from(B13).pow(chunk_idx);
+                output += output_coef * BigUint::from(B9).pow((chunk_idx + rotation) % LANE_SIZE);
+                // The chunks of the slice, most significant first
+                let mut slice_chunks =
+                    chunks[chunk_idx as usize..(chunk_idx + step) as usize].to_vec();
+                slice_chunks.resize(BASE_NUM_OF_CHUNKS as usize, 0);
+                slice_chunks.reverse();
+                assert_eq!(*od, get_overflow_detector(slice_chunks.try_into().unwrap()));
+            }
+            assert_eq!(input, lane);
+            assert_eq!(
+                output,
+                LaneRotateConversionConfig::<Fp>::compute(lane.clone(), index)
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_valid_base13_lane() {
+        let lane = convert_b2_to_b13(0x0123_4567_89ab_cdef);
+        assert!(is_valid_base13_lane::<Fp>(biguint_to_f(&lane)));
+
+        let special = |low: u64, high: u64| {
+            BigUint::from(low) + BigUint::from(high) * BigUint::from(B13).pow(LANE_SIZE)
+        };
+        // The low chunk of the lane is 1, with both special chunks set it sums
+        // to 12 at most
+        assert!(is_valid_base13_lane::<Fp>(biguint_to_f(
+            &(&lane + special(4, 7))
+        )));
+        // The special chunks overflow
+        assert!(!is_valid_base13_lane::<Fp>(biguint_to_f(
+            &(&lane + special(5, 7))
+        )));
+        assert!(!is_valid_base13_lane::<Fp>(biguint_to_f(&special(12, 1))));
+        // More than 65 chunks
+        let too_long = BigUint::from(B13).pow(LANE_SIZE + 1);
+        assert!(!is_valid_base13_lane::<Fp>(biguint_to_f(&too_long)));
+
+        // The conversion refuses to witness an invalid lane
+        let circuit = LaneCircuit::<Fp> {
+            lanes: vec![(
+                lane_at(1),
+                biguint_to_f(&(&lane + special(5, 7))),
+                Fp::zero(),
+            )],
+        };
+        assert!(matches!(
+            MockProver::<Fp>::run(15, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn test_compute() {
+        for index in LaneIndex::all() {
+            for value in [0, u64::MAX, rand::random::<u64>()] {
+                let lane = convert_b2_to_b13(value);
+                assert_eq!(
+                    LaneRotateConversionConfig::<Fp>::compute(lane.clone(), index),
+                    convert_b13_lane_to_b9(lane, index.rotation())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_matches_assign_region() {
+        let lanes = [2, 7, 13, 19]
+            .iter()
+            .map(|&lane_idx| {
+                // A Theta output lane with random special chunks
+                let mut chunks: Vec<u8> = (0..65).map(|_| rand::random::<u8>() % B13).collect();
+                chunks[64] = rand::random::<u8>() % (B13 - chunks[0]);
+                let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+                let index = lane_at(lane_idx);
+                let expected = LaneRotateConversionConfig::<Fp>::compute(lane.clone(), index);
+                (index, biguint_to_f(&lane), biguint_to_f(&expected))
+            })
+            .collect();
+        let circuit = LaneCircuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[cfg(feature = "debug-witness")]
+    #[test]
+    fn test_overflow_detector_values() {
+        #[derive(Default)]
+        struct DetectorCircuit<F> {
+            lanes: Vec<(LaneIndex, F, F)>,
+        }
+
+        impl<F: Field> Circuit<F> for DetectorCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                for &(index, lane, _) in self.lanes.iter() {
+                    let lane = config.assign_input_lane(&mut layouter, lane)?;
+                    let (result, detectors) = config.lane_config.assign_region_with_detectors(
+                        &mut layouter,
+                        lane,
+                        index,
+                    )?;
+                    let (step2_od, step3_od) = (result.step2_od, result.step3_od);
+                    let slices = slice_lane(index.rotation());
+                    assert_eq!(detectors.len(), slices.len());
+
+                    let sum_of_step = |step: u32| {
+                        slices
+                            .iter()
+                            .zip(detectors.iter())
+                            .filter(|(&(_, s), _)| s == step)
+                            .fold(F::zero(), |acc, (_, &od)| acc + od)
+                    };
+                    let sum_of_cells = |cells: &[AssignedCell<F, F>]| {
+                        cells
+                            .iter()
+                            .fold(F::zero(), |acc, cell| acc + *cell.value().unwrap())
+                    };
+                    assert_eq!(sum_of_step(2), sum_of_cells(&step2_od));
+                    assert_eq!(sum_of_step(3), sum_of_cells(&step3_od));
+                    assert_eq!(sum_of_step(1), F::zero());
+                }
+                Ok(())
+            }
+        }
+
+        for lane_idx in [1, 6, 24] {
+            let value = rand::random::<u64>();
+            let rotation = lane_at(lane_idx).rotation();
+            let (conversions, _) =
+                RhoLane::new(convert_b2_to_b13(value), rotation).get_full_witness();
+            let last = &conversions.last().unwrap().overflow_detector;
+            let slices = slice_lane(rotation);
+            let sum_of_step = |step: u32| -> u32 {
+                slices
+                    .iter()
+                    .zip(conversions.iter())
+                    .filter(|(&(_, s), _)| s == step)
+                    .map(|(_, conv)| conv.overflow_detector.value)
+                    .sum()
+            };
+            assert_eq!(sum_of_step(2), last.step2_acc);
+            assert_eq!(sum_of_step(3), last.step3_acc);
+
+            let circuit = DetectorCircuit::<Fp> {
+                lanes: vec![lane_case(lane_idx, value)],
+            };
+            let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_per_lane_overflow_check() {
+        #[derive(Default)]
+        struct OverflowCircuit<F> {
+            lane: F,
+            // Lets the step 2 slice of the lane before its rotation offset
+            // overflow into the next chunk
+            overflow: bool,
+            check: bool,
+        }
+
+        impl<F: Field> Circuit<F> for OverflowCircuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                let lane = config.assign_input_lane(&mut layouter, self.lane)?;
+                let rotation = ROTATION_CONSTANTS[1][0];
+                let mut witness =
+                    RhoLane::new(f_to_biguint(self.lane), rotation).get_full_witness();
+                if self.overflow {
+                    craft_step2_overflow(&mut witness);
+                }
+                let result = config.lane_config.assign_region_with_witness(
+                    &mut layouter,
+                    lane,
+                    lane_at(5),
+                    &witness,
+                )?;
+                if self.check {
+                    config
+                        .lane_config
+                        .assign_overflow_check(&mut layouter, &result)?;
+                }
+                Ok(())
+            }
+        }
+
+        // Chunk 63 has to be 1 for the crafted overflow
+        let lane = biguint_to_f(&convert_b2_to_b13(rand::random::<u64>() | 1 << 63));
+        for (overflow, check, ok) in [
+            (false, true, true),
+            (true, false, true),
+            (true, true, false),
+        ] {
+            let circuit = OverflowCircuit::<Fp> {
+                lane,
+                overflow,
+                check,
+            };
+            let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify().is_ok(), ok);
+        }
+    }
+
+    #[test]
+    fn test_one_config_different_rotations() {
+        // Lane 1 rotates by 36 and lane 10 by 62 through the same config
+        let circuit = LaneCircuit::<Fp> {
+            lanes: vec![
+                lane_case(1, 0x0123_4567_89ab_cdef),
+                lane_case(10, 0xfedc_ba98_7654_3210),
+            ],
+        };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_all_zero_state() {
+        // Most lanes are zero for a short input, like the single padding block
+        // of the empty input. They must not leave any slice or region empty
+        let lanes = (0..25).map(|lane_idx| lane_case(lane_idx, 0)).collect();
+        let circuit = LaneCircuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        for rotation in ROTATION_CONSTANTS.iter().flatten() {
+            assert!(!slice_lane(*rotation).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_lane_conversion_helper() {
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (2, 3), (4, 4)] {
+            test_lane_conversion(0x0123_4567_89ab_cdef, x, y);
+        }
+        test_lane_conversion(u64::MAX, 3, 1);
+        test_lane_conversion(rand::random::<u64>(), 1, 4);
+    }
+
+    #[test]
+    fn test_extreme_rotation_lanes() {
+        // The largest rotations, 62 and 61, and the smallest non zero one
+        for (x, y) in [(2, 0), (2, 4), (1, 0)] {
+            for lane in [u64::MAX, 0x8000_0000_0000_0001, rand::random::<u64>()] {
+                test_lane_conversion(lane, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_b13() {
+        #[derive(Default)]
+        struct RotationB13Circuit<F> {
+            lanes: Vec<(LaneIndex, F, F)>,
+        }
+
+        impl<F: Field> Circuit<F> for RotationB13Circuit<F> {
+            type Config = LaneCircuitConfig<F>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                LaneCircuit::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                for &(index, lane, expected) in self.lanes.iter() {
+                    let witness =
+                        RhoLane::new(f_to_biguint(lane), index.rotation()).get_full_witness();
+                    let lane = config.assign_input_lane(&mut layouter, lane)?;
+                    let out_lane = config
+                        .lane_config
+                        .assign_rotation_b13(&mut layouter, lane, index, &witness)?
+                        .output_lane;
+                    layouter.assign_region(
+                        || "check output lane",
+                        |mut region| {
+                            let copied = out_lane.copy_advice(
+                                || "copied lane",
+                                &mut region,
+                                config.copied,
+                                0,
+                            )?;
+                            let expected = region.assign_advice(
+                                || "expected lane",
+                                config.copied,
+                                1,
+                                || Ok(expected),
+                            )?;
+                            region.constrain_equal(copied.cell(), expected.cell())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        // Theta output lanes, with both special chunks set
+        let lanes = [1, 6, 10, 14, 24]
+            .iter()
+            .map(|&lane_idx| {
+                let mut chunks = (0..65).map(|_| rand::random::<u8>() % 7).collect_vec();
+                chunks[0] = 5;
+                chunks[64] = 7;
+                let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+                let index = lane_at(lane_idx);
+                let rotated = rotate_b13_lane(lane.clone(), index.rotation());
+                // Still the same lane once converted to base 9
+                let converted = rotated
+                    .to_radix_le(B13.into())
+                    .iter()
+                    .map(|&chunk| convert_b13_coef(chunk))
+                    .collect_vec();
+                assert_eq!(
+                    BigUint::from_radix_le(&converted, B9.into()).unwrap(),
+                    LaneRotateConversionConfig::<Fp>::compute(lane.clone(), index)
+                );
+                (index, biguint_to_f(&lane), biguint_to_f(&rotated))
+            })
+            .collect();
+        let circuit = RotationB13Circuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_all_rotations() {
+        use crate::reference::reference_rho;
+
+        // Every rotation offset in one circuit, each lane checked against the
+        // reference Rho of the binary state.
+        let state: [u64; 25] = (0..25)
+            .map(|i| 0x8000_0001_dead_beef_u64 ^ (i << 8))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let expected = reference_rho(state);
+        let lanes = (0..5)
+            .cartesian_product(0..5)
+            .map(|(x, y)| {
+                // The reference is in the spec order
+                let i = x + 5 * y;
+                (
+                    LaneIndex::new(x, y).unwrap(),
+                    biguint_to_f(&convert_b2_to_b13(state[i])),
+                    biguint_to_f(&convert_b2_to_b9(expected[i])),
+                )
+            })
+            .collect();
+        let circuit = LaneCircuit::<Fp> { lanes };
+        let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
     }
 }
@@ -1,29 +1,36 @@
 use crate::{
-    arith_helpers::{convert_b13_coef, convert_b13_lane_to_b9, B13, B9},
-    common::LANE_SIZE,
+    arith_helpers::{convert_b13_coef, convert_b13_lane_to_b9, dense_to_sparse, B13, B9},
+    common::{LaneIndex, BASE_NUM_OF_CHUNKS, LANE_SIZE},
+    error::KeccakError,
 };
 use itertools::Itertools;
 use num_bigint::BigUint;
 use num_traits::Zero;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::convert::TryInto;
 
-pub const BASE_NUM_OF_CHUNKS: u32 = 4;
-
 /// Determine how many chunks in a step
 ///
-/// Usually it's a step of 4 chunks, but the number of chunks could be less near
-/// the rotation position and the end of the lane.
+/// Usually it's a step of [`BASE_NUM_OF_CHUNKS`] chunks, but the number of
+/// chunks could be less near the rotation position and the end of the lane.
 pub fn get_step_size(chunk_idx: u32, rotation: u32) -> u32 {
+    get_step_size_with_max(chunk_idx, rotation, BASE_NUM_OF_CHUNKS)
+}
+
+/// Same as [`get_step_size`], with steps of at most `max_step` chunks.
+pub fn get_step_size_with_max(chunk_idx: u32, rotation: u32, max_step: u32) -> u32 {
+    debug_assert!(max_step > 0);
     // near the rotation offset position of the lane
     let offset = LANE_SIZE - rotation;
-    if chunk_idx < offset && offset < chunk_idx + BASE_NUM_OF_CHUNKS {
+    if chunk_idx < offset && offset < chunk_idx + max_step {
         return offset - chunk_idx;
     }
     // near the end of the lane
-    if chunk_idx < LANE_SIZE && LANE_SIZE < chunk_idx + BASE_NUM_OF_CHUNKS {
+    if chunk_idx < LANE_SIZE && LANE_SIZE < chunk_idx + max_step {
         return LANE_SIZE - chunk_idx;
     }
-    BASE_NUM_OF_CHUNKS
+    max_step
 }
 
 /// Slice the lane into chunk indices and steps
@@ -31,11 +38,22 @@ pub fn get_step_size(chunk_idx: u32, rotation: u32) -> u32 {
 /// We ask what's the current chunk index and the step we need to go to the next
 /// chunk index. We start chunk_idx from 1 because the 0th chunk is from the low
 /// value from the theta step.
+///
+/// Each `(chunk_idx, step)` covers the chunks `chunk_idx..chunk_idx + step`, so
+/// the first element is the start of the slice, never the start of the next
+/// one. The slices cover chunks `1..LANE_SIZE` without gaps, and one of them
+/// starts at the rotation offset `LANE_SIZE - rotation` unless the rotation is
+/// 0.
 pub fn slice_lane(rotation: u32) -> Vec<(u32, u32)> {
+    slice_lane_with_max_step(rotation, BASE_NUM_OF_CHUNKS)
+}
+
+/// Same as [`slice_lane`], with steps of at most `max_step` chunks.
+pub fn slice_lane_with_max_step(rotation: u32, max_step: u32) -> Vec<(u32, u32)> {
     let mut chunk_idx = 1;
     let mut output = vec![];
     while chunk_idx < LANE_SIZE {
-        let step = get_step_size(chunk_idx, rotation);
+        let step = get_step_size_with_max(chunk_idx, rotation, max_step);
         output.push((chunk_idx, step));
         chunk_idx += step;
     }
@@ -45,19 +63,25 @@ pub fn slice_lane(rotation: u32) -> Vec<(u32, u32)> {
 /// A mapping from `step` to a overflow detector value
 ///
 /// See tests for the derivation of the values
-pub const OVERFLOW_TRANSFORM: [u32; 5] = [0, 0, 1, 13, 170];
+pub const OVERFLOW_TRANSFORM: [u32; BASE_NUM_OF_CHUNKS as usize + 1] = [0, 0, 1, 13, 170];
+
+/// We have 12 step 1, 12 step 2, and 13 step 3 across the slices of all 25
+/// lanes
+///
+/// See tests for the derivation of the values
+pub const STEP_COUNTS: [u32; 3] = [12, 12, 13];
 
 /// The sum of the step 2 overflow detectors across all 25 lanes should not
 /// greater than this value
 ///
-/// See tests for the derivation of the values
-pub const STEP2_RANGE: u64 = 12;
+/// Every step 2 slice contributes at most `OVERFLOW_TRANSFORM[2]`
+pub const STEP2_RANGE: u64 = (STEP_COUNTS[1] * OVERFLOW_TRANSFORM[2]) as u64;
 
 /// The sum of the step 3 overflow detectors across all 25 lanes should not
 /// greater than this value
 ///
-/// See tests for the derivation of the values
-pub const STEP3_RANGE: u64 = 169;
+/// Every step 3 slice contributes at most `OVERFLOW_TRANSFORM[3]`
+pub const STEP3_RANGE: u64 = (STEP_COUNTS[2] * OVERFLOW_TRANSFORM[3]) as u64;
 
 /// Get the overflow detector from an input chunks
 ///
@@ -111,6 +135,17 @@ pub struct Special {
 
 const RHO_LANE_SIZE: usize = 65;
 
+lazy_static::lazy_static! {
+    // 13^i for every chunk of a Rho input lane
+    static ref B13_POWERS: Vec<BigUint> = (0..RHO_LANE_SIZE as u32)
+        .map(|i| BigUint::from(B13).pow(i))
+        .collect();
+    // 9^i for every chunk of a Rho output lane
+    static ref B9_POWERS: Vec<BigUint> = (0..LANE_SIZE)
+        .map(|i| BigUint::from(B9).pow(i))
+        .collect();
+}
+
 #[derive(Debug, Clone)]
 pub struct RhoLane {
     // base 13. 65 chunks
@@ -126,26 +161,56 @@ pub struct RhoLane {
 
 impl RhoLane {
     pub fn new(input: BigUint, rotation: u32) -> Self {
+        let mut lane = Self {
+            input: BigUint::zero(),
+            output: BigUint::zero(),
+            rotation,
+            chunks: [0; RHO_LANE_SIZE],
+            special_high: 0,
+            special_low: 0,
+        };
+        lane.reset(input, rotation);
+        lane
+    }
+
+    /// Builds the lane of the dense `value`, encoded in base 13 as the input
+    /// of Rho. Fails with [`KeccakError::RotationOutOfRange`] if `rotation`
+    /// isn't a rotation of a lane.
+    pub fn from_dense_u64(value: u64, rotation: u32) -> Result<Self, KeccakError> {
+        if rotation >= LANE_SIZE {
+            return Err(KeccakError::RotationOutOfRange(rotation));
+        }
+        Ok(Self::new(dense_to_sparse(value, B13), rotation))
+    }
+
+    /// Reuses the lane for another `input` and `rotation`, leaving it in the
+    /// same state as [`RhoLane::new`] would
+    ///
+    /// Panics if `rotation` isn't smaller than the lane size, the special chunk
+    /// would land past the top of the output lane.
+    pub fn reset(&mut self, input: BigUint, rotation: u32) {
+        assert!(
+            rotation < LANE_SIZE,
+            "rotation {} is not smaller than the lane size",
+            rotation
+        );
         debug_assert!(
-            input.lt(&BigUint::from(B13).pow(RHO_LANE_SIZE as u32)),
+            input.lt(&(&B13_POWERS[64] * BigUint::from(B13))),
             "lane too big"
         );
-        let mut chunks = input.to_radix_le(B13.into());
-        chunks.resize(RHO_LANE_SIZE, 0);
-        let chunks: [u8; RHO_LANE_SIZE] = chunks.try_into().unwrap();
-        let special_high = *chunks.get(64).unwrap();
-        let special_low = *chunks.get(0).unwrap();
-        debug_assert!(special_high + special_low < B13, "invalid Rho input lane");
-        let output = convert_b13_lane_to_b9(input.clone(), rotation);
-
-        Self {
-            input,
-            output,
-            rotation,
-            chunks,
-            special_high,
-            special_low,
+        self.chunks = [0; RHO_LANE_SIZE];
+        for (chunk, digit) in self.chunks.iter_mut().zip(input.to_radix_le(B13.into())) {
+            *chunk = digit;
         }
+        self.special_high = self.chunks[64];
+        self.special_low = self.chunks[0];
+        debug_assert!(
+            self.special_high + self.special_low < B13,
+            "invalid Rho input lane"
+        );
+        self.output = convert_b13_lane_to_b9(input.clone(), rotation);
+        self.input = input;
+        self.rotation = rotation;
     }
 
     pub fn get_full_witness(&self) -> (Vec<Conversion>, Special) {
@@ -162,7 +227,7 @@ impl RhoLane {
                     .unwrap();
                 let input = {
                     let coef = BigUint::from_radix_le(chunks, B13.into()).unwrap_or_default();
-                    let power_of_base = BigUint::from(B13).pow(chunk_idx);
+                    let power_of_base = B13_POWERS[chunk_idx as usize].clone();
                     let pre_acc = input_acc.clone();
                     input_acc -= &coef * &power_of_base;
                     Slice {
@@ -176,8 +241,15 @@ impl RhoLane {
                         chunks.iter().map(|&x| convert_b13_coef(x)).collect_vec();
                     let coef =
                         BigUint::from_radix_le(&converted_chunks, B9.into()).unwrap_or_default();
+                    // The slice at the rotation offset wraps to the bottom of
+                    // the lane, the slice before it ends right at the top
                     let power = (chunk_idx + self.rotation) % LANE_SIZE;
-                    let power_of_base = BigUint::from(B9).pow(power);
+                    debug_assert!(
+                        power + step <= LANE_SIZE,
+                        "slice {} straddles the rotation offset",
+                        chunk_idx
+                    );
+                    let power_of_base = B9_POWERS[power as usize].clone();
                     let pre_acc = output_acc.clone();
                     output_acc += &coef * &power_of_base;
                     Slice {
@@ -216,9 +288,12 @@ impl RhoLane {
         let special = {
             let input = input_acc;
             let output_acc_pre = output_acc;
+            // Chunk 64 is the bit the Theta rotation pushed past the top of
+            // the lane, it wraps around to bit 0. Both count XORed bits, so
+            // the parity of their sum XOR-folds the two into one bit.
             let output_coef = convert_b13_coef(self.special_high + self.special_low);
             let output_acc_post =
-                &output_acc_pre + output_coef * BigUint::from(B9 as u64).pow(self.rotation);
+                &output_acc_pre + output_coef * &B9_POWERS[self.rotation as usize];
             Special {
                 input,
                 output_acc_pre,
@@ -234,7 +309,7 @@ impl RhoLane {
     /// represent
     fn sanity_check(&self, input_acc: &BigUint) {
         let expect = (self.special_low as u64)
-            + (self.special_high as u64) * BigUint::from(B13).pow(LANE_SIZE);
+            + (self.special_high as u64) * &B13_POWERS[LANE_SIZE as usize];
         assert_eq!(
             *input_acc, expect,
             "input_acc got: {:?}  expect: {:?} = low({:?}) + high({:?}) * 13**64",
@@ -243,16 +318,81 @@ impl RhoLane {
     }
 }
 
+/// The witness of a lane for the Rho step, see [`RhoLane::get_full_witness`]
+pub type RhoWitness = (Vec<Conversion>, Special);
+
+fn lane_witness(lane_idx: usize, lane: &BigUint) -> RhoWitness {
+    let rotation = LaneIndex::from_index(lane_idx)
+        .expect("a state has 25 lanes")
+        .rotation();
+    RhoLane::new(lane.clone(), rotation).get_full_witness()
+}
+
+/// Computes the Rho witnesses of the 25 base 13 lanes one after the other
+pub fn compute_rho_witnesses_serial(state: &[BigUint; 25]) -> Vec<RhoWitness> {
+    state
+        .iter()
+        .enumerate()
+        .map(|(idx, lane)| lane_witness(idx, lane))
+        .collect()
+}
+
+/// Computes the Rho witnesses of the 25 base 13 lanes
+///
+/// The lanes don't depend on each other until they are assigned, so with the
+/// `parallel` feature they are computed on the rayon thread pool. The
+/// witnesses are returned in lane order either way.
+pub fn compute_rho_witnesses(state: &[BigUint; 25]) -> Vec<RhoWitness> {
+    #[cfg(feature = "parallel")]
+    {
+        state
+            .par_iter()
+            .enumerate()
+            .map(|(idx, lane)| lane_witness(idx, lane))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        compute_rho_witnesses_serial(state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::arith_helpers::B2;
+    use crate::arith_helpers::{convert_b2_to_b13, convert_b2_to_b9, B2};
     use crate::common::ROTATION_CONSTANTS;
+    use num_traits::One;
 
-    /// We have 12 step 1, 12 step 2, and 13 step 3
-    ///
-    /// See tests for more detail
-    const STEP_COUNTS: [u32; 3] = [12, 12, 13];
+    #[test]
+    fn test_from_dense_u64() {
+        let value = 0x0123_4567_89ab_cdef;
+        let lane = RhoLane::from_dense_u64(value, 36).unwrap();
+        assert_eq!(lane.input, convert_b2_to_b13(value));
+        assert_eq!(lane.output, convert_b13_lane_to_b9(lane.input.clone(), 36));
+        assert_eq!(
+            RhoLane::from_dense_u64(value, LANE_SIZE).unwrap_err(),
+            KeccakError::RotationOutOfRange(LANE_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_rotation_range() {
+        let lane = convert_b2_to_b13(0x0123_4567_89ab_cdef);
+        for rotation in ROTATION_CONSTANTS.iter().flatten() {
+            let (_, special) = RhoLane::new(lane.clone(), *rotation).get_full_witness();
+            assert_eq!(
+                special.output_acc_post,
+                convert_b13_lane_to_b9(lane.clone(), *rotation)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "rotation 64 is not smaller than the lane size")]
+    fn test_rotation_out_of_range() {
+        RhoLane::new(BigUint::zero(), LANE_SIZE);
+    }
 
     #[test]
     fn test_overflow_counting() {
@@ -298,6 +438,138 @@ mod tests {
             u64::from(STEP_COUNTS[step3 - 1] * OVERFLOW_TRANSFORM[step3])
         );
     }
+
+    #[test]
+    fn test_worst_case_overflow_detector_sums() {
+        // The ranges must be the sums we get when every slice of every lane
+        // carries its largest honest overflow detector.
+        let (mut step2_sum, mut step3_sum) = (0u64, 0u64);
+        for rotation in ROTATION_CONSTANTS.iter().flat_map(|r| r.iter()) {
+            for (_, step) in slice_lane(*rotation) {
+                match step {
+                    2 => step2_sum += u64::from(OVERFLOW_TRANSFORM[2]),
+                    3 => step3_sum += u64::from(OVERFLOW_TRANSFORM[3]),
+                    _ => {}
+                }
+            }
+        }
+        assert_eq!(step2_sum, STEP2_RANGE);
+        assert_eq!(step3_sum, STEP3_RANGE);
+    }
+
+    #[test]
+    #[should_panic(expected = "input_acc got")]
+    fn test_sanity_check_residual() {
+        // A normal chunk left in the accumulator is caught
+        let lane = RhoLane::new(convert_b2_to_b13(0x0123_4567_89ab_cdef), 1);
+        let residual = BigUint::from(lane.special_low)
+            + BigUint::from(lane.special_high) * BigUint::from(B13).pow(LANE_SIZE)
+            + BigUint::from(B13).pow(10);
+        lane.sanity_check(&residual);
+    }
+
+    #[test]
+    fn test_slice_lane() {
+        assert_eq!(get_step_size(1, 0), 4);
+        assert_eq!(get_step_size(61, 0), 3);
+        assert_eq!(get_step_size(1, 62), 1);
+        assert_eq!(get_step_size(57, 1), 4);
+        assert_eq!(get_step_size(61, 1), 2);
+
+        for rotation in ROTATION_CONSTANTS.iter().flat_map(|r| r.iter()) {
+            let slices = slice_lane(*rotation);
+            // The slices are contiguous, start right after the low chunk and
+            // end at the lane size.
+            let mut next_idx = 1;
+            for &(chunk_idx, step) in slices.iter() {
+                assert_eq!(chunk_idx, next_idx);
+                assert!((1..=BASE_NUM_OF_CHUNKS).contains(&step));
+                next_idx += step;
+            }
+            assert_eq!(next_idx, LANE_SIZE);
+            let steps: u32 = slices.iter().map(|(_, step)| step).sum();
+            assert_eq!(steps + 1, LANE_SIZE);
+
+            // The rotation offset falls on a slice boundary
+            let offset = LANE_SIZE - rotation;
+            if *rotation != 0 {
+                assert!(slices.iter().any(|&(chunk_idx, _)| chunk_idx == offset));
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice_lane_extreme_rotations() {
+        // Every rotation, including the ones near LANE_SIZE where the slice at
+        // the rotation offset and the slice at the end of the lane are short
+        for rotation in 0..LANE_SIZE {
+            let mut covered = vec![0; LANE_SIZE as usize];
+            for (chunk_idx, step) in slice_lane(rotation) {
+                for i in chunk_idx..chunk_idx + step {
+                    covered[i as usize] += 1;
+                }
+                let offset = LANE_SIZE - rotation;
+                // No slice straddles the rotation offset
+                assert!(!(chunk_idx < offset && offset < chunk_idx + step));
+            }
+            // Chunk 0 is the special low chunk, the others are covered once
+            assert_eq!(covered[0], 0);
+            assert!(covered[1..].iter().all(|&count| count == 1));
+        }
+        assert_eq!(slice_lane(63)[0], (1, 4));
+        assert_eq!(slice_lane(1).last(), Some(&(63, 1)));
+    }
+
+    #[test]
+    fn test_slice_lane_with_max_step() {
+        for rotation in ROTATION_CONSTANTS.iter().flat_map(|r| r.iter()) {
+            assert_eq!(
+                slice_lane(*rotation),
+                slice_lane_with_max_step(*rotation, BASE_NUM_OF_CHUNKS)
+            );
+            for max_step in 1..=6 {
+                let slices = slice_lane_with_max_step(*rotation, max_step);
+                let mut next_idx = 1;
+                for &(chunk_idx, step) in slices.iter() {
+                    assert_eq!(chunk_idx, next_idx);
+                    assert!((1..=max_step).contains(&step));
+                    next_idx += step;
+                }
+                assert_eq!(next_idx, LANE_SIZE);
+                if *rotation != 0 {
+                    let offset = LANE_SIZE - rotation;
+                    assert!(slices.iter().any(|&(chunk_idx, _)| chunk_idx == offset));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_slices_fit_the_table() {
+        // Every slice coef is a row of the base 13 to base 9 table, which holds
+        // all the values of `BASE_NUM_OF_CHUNKS` chunks.
+        let table_rows = BigUint::from(B13).pow(BASE_NUM_OF_CHUNKS);
+        let chunks: Vec<u8> = (0..65).map(|i| (i % 13) as u8).collect();
+        let lane = BigUint::from_radix_le(&chunks, B13.into()).unwrap();
+        for rotation in ROTATION_CONSTANTS.iter().flat_map(|r| r.iter()) {
+            let (conversions, _) = RhoLane::new(lane.clone(), *rotation).get_full_witness();
+            for conv in conversions {
+                assert!(conv.input.coef < table_rows);
+                // The table converts the same chunks, padded to the table width
+                let mut b13_chunks = conv.input.coef.to_radix_le(B13.into());
+                b13_chunks.resize(BASE_NUM_OF_CHUNKS as usize, 0);
+                let b9_chunks = b13_chunks
+                    .iter()
+                    .map(|&x| convert_b13_coef(x))
+                    .collect_vec();
+                assert_eq!(
+                    BigUint::from_radix_le(&b9_chunks, B9.into()).unwrap(),
+                    conv.output.coef
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_rho_lane_rotation() {
         // Chosen such that special chunks are all 0
@@ -325,4 +597,94 @@ mod tests {
         assert_eq!(conversions.len(), slice_lane(rotation).len());
         assert_eq!(special.output_acc_post, lane.output);
     }
+
+    #[test]
+    fn test_slice_at_rotation_offset() {
+        // Lane (0, 1) rotates by 36, the slices around the offset 28 are
+        // `(25, 3)` and `(28, 4)`
+        let rotation = ROTATION_CONSTANTS[0][1];
+        assert_eq!(rotation, 36);
+        let slices = slice_lane(rotation);
+        let before = slices.iter().position(|&slice| slice == (25, 3)).unwrap();
+        assert_eq!(slices[before + 1], (28, 4));
+
+        // The bits 25..32 straddle the offset, with every chunk of both
+        // slices set
+        let value = 0xfe00_0000u64 | 1;
+        let lane = RhoLane::from_dense_u64(value, rotation).unwrap();
+        let (conversions, special) = lane.get_full_witness();
+
+        // The slice before the offset ends at the top of the lane, the one at
+        // the offset restarts from the bottom
+        let top = &conversions[before].output;
+        assert_eq!(top.coef, convert_b2_to_b9(0b111));
+        assert_eq!(top.power_of_base, BigUint::from(B9).pow(LANE_SIZE - 3));
+        let bottom = &conversions[before + 1].output;
+        assert_eq!(bottom.coef, convert_b2_to_b9(0b1111));
+        assert_eq!(bottom.power_of_base, BigUint::one());
+        assert_eq!(
+            bottom.pre_acc,
+            &top.pre_acc + &top.coef * &top.power_of_base
+        );
+        assert_eq!(
+            conversions[before + 2].output.pre_acc,
+            &bottom.pre_acc + &bottom.coef
+        );
+
+        assert_eq!(
+            special.output_acc_post,
+            convert_b2_to_b9(value.rotate_left(rotation))
+        );
+    }
+
+    #[test]
+    fn test_reset_matches_new() {
+        // A lane with both special chunks set, as Theta leaves it
+        let first = convert_b2_to_b13(rand::random()) + BigUint::from(B13).pow(LANE_SIZE) * 2u8;
+        let mut lane = RhoLane::new(first, ROTATION_CONSTANTS[4][4]);
+        for idx in 0..25 {
+            let input = convert_b2_to_b13(rand::random()) + BigUint::from(B13).pow(LANE_SIZE) * 3u8;
+            let rotation = ROTATION_CONSTANTS[idx / 5][idx % 5];
+            lane.reset(input.clone(), rotation);
+            let fresh = RhoLane::new(input, rotation);
+            assert_eq!(lane.input, fresh.input);
+            assert_eq!(lane.output, fresh.output);
+            assert_eq!(lane.rotation, fresh.rotation);
+            assert_eq!(lane.chunks, fresh.chunks);
+            assert_eq!(lane.special_high, fresh.special_high);
+            assert_eq!(lane.special_low, fresh.special_low);
+
+            let (conversions, special) = lane.get_full_witness();
+            let (fresh_conversions, fresh_special) = fresh.get_full_witness();
+            for (conv, fresh_conv) in conversions.iter().zip(fresh_conversions.iter()) {
+                assert_eq!(conv.input.pre_acc, fresh_conv.input.pre_acc);
+                assert_eq!(conv.output.pre_acc, fresh_conv.output.pre_acc);
+            }
+            assert_eq!(special.output_acc_post, fresh_special.output_acc_post);
+            assert_eq!(special.output_acc_post, lane.output);
+        }
+    }
+
+    #[test]
+    fn test_compute_rho_witnesses() {
+        let state: [BigUint; 25] = (0..25)
+            .map(|_| crate::arith_helpers::convert_b2_to_b13(rand::random()))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let witnesses = compute_rho_witnesses(&state);
+        let serial = compute_rho_witnesses_serial(&state);
+        assert_eq!(witnesses.len(), 25);
+        for (idx, ((conversions, special), (_, serial_special))) in
+            witnesses.iter().zip(serial.iter()).enumerate()
+        {
+            let rotation = ROTATION_CONSTANTS[idx / 5][idx % 5];
+            assert_eq!(conversions.len(), slice_lane(rotation).len());
+            assert_eq!(special.output_acc_post, serial_special.output_acc_post);
+            assert_eq!(
+                special.output_acc_post,
+                convert_b13_lane_to_b9(state[idx].clone(), rotation)
+            );
+        }
+    }
 }
@@ -0,0 +1,334 @@
+use crate::arith_helpers::{block_lane_index, ByteOrder};
+use crate::gate_helpers::f_to_biguint;
+use crate::permutation::{base_conversion::BaseConversionConfig, tables::FromBase9TableConfig};
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed},
+};
+use std::convert::TryInto;
+
+/// The number of lanes that make up the 256 bit digest.
+pub const DIGEST_LANES: usize = 4;
+
+/// Reads the output lanes out of the base-9 state of the last permutation.
+///
+/// The output lanes are the first lanes of the rate in the spec order, see
+/// [`block_lane_index`]. For Keccak-256 the digest is the lanes `(0, 0)`,
+/// `(1, 0)`, `(2, 0)` and `(3, 0)`, which sit at indexes 0, 5, 10 and 15 of the
+/// state. Each of them is converted back to binary, so the returned cells hold
+/// the lanes as spec `u64` values. The bytes of the output are the bytes of
+/// each lane, in order, unpacked in the configured byte order by
+/// [`SqueezeConfig::lanes_to_bytes`].
+#[derive(Clone, Debug)]
+pub struct SqueezeConfig<F> {
+    base_conversion: BaseConversionConfig<F>,
+    flag: Column<Advice>,
+    rate: usize,
+    byte_order: ByteOrder,
+}
+
+impl<F: Field> SqueezeConfig<F> {
+    /// Side effect: the `state` columns are equality enabled, `constant` holds
+    /// the squeeze flag
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        from_b9_table: &FromBase9TableConfig<F>,
+        state: [Column<Advice>; 25],
+        constant: Column<Fixed>,
+        rate: usize,
+        byte_order: ByteOrder,
+    ) -> Self {
+        assert!(rate < 25, "the rate must leave room for the capacity");
+        let flag = meta.advice_column();
+        meta.enable_equality(flag);
+        meta.enable_constant(constant);
+        let lane = meta.advice_column();
+        let base_info = from_b9_table.get_base_info(true);
+        let base_conversion = BaseConversionConfig::configure(
+            meta,
+            base_info,
+            lane,
+            flag,
+            state[0..5].try_into().unwrap(),
+        );
+        Self {
+            base_conversion,
+            flag,
+            rate,
+            byte_order,
+        }
+    }
+
+    /// Converts the first `output_lanes` lanes of the rate to binary.
+    ///
+    /// A single permutation can output at most `rate` lanes, longer XOF outputs
+    /// need to squeeze again after the next permutation.
+    pub fn assign_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+        output_lanes: usize,
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        assert!(output_lanes <= self.rate);
+        let flag = layouter.assign_region(
+            || "Squeeze enable",
+            |mut region| {
+                region.assign_advice_from_constant(|| "Enable squeeze", self.flag, 0, F::one())
+            },
+        )?;
+        (0..output_lanes)
+            .map(|i| {
+                self.base_conversion.assign_lane(
+                    layouter,
+                    state[block_lane_index(i)].clone(),
+                    flag.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Squeezes the Keccak-256 digest, see [`Self::assign_state`] for the
+    /// returned cells.
+    ///
+    /// The digest bytes are decoded from the witness, each lane in little
+    /// endian as in the spec whatever the configured byte order. They are
+    /// `None` when the lanes have no witness.
+    pub fn assign_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<(Vec<AssignedCell<F, F>>, Option<[u8; 32]>), Error> {
+        let lanes = self.assign_state(layouter, state, DIGEST_LANES)?;
+        let digest =
+            lanes_to_bytes(&lanes, ByteOrder::LittleEndian).map(|bytes| bytes.try_into().unwrap());
+        Ok((lanes, digest))
+    }
+
+    /// Unpacks the bytes of the squeezed lanes in the configured byte order.
+    ///
+    /// Returns `None` when the lanes have no witness.
+    pub fn lanes_to_bytes(&self, lanes: &[AssignedCell<F, F>]) -> Option<Vec<u8>> {
+        lanes_to_bytes(lanes, self.byte_order)
+    }
+}
+
+fn lanes_to_bytes<F: Field>(
+    lanes: &[AssignedCell<F, F>],
+    byte_order: ByteOrder,
+) -> Option<Vec<u8>> {
+    lanes
+        .iter()
+        .map(|lane| {
+            let lane: u64 = f_to_biguint(*lane.value()?).try_into().ok()?;
+            Some(byte_order.lane_to_bytes(lane))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lanes| lanes.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith_helpers::*;
+    use crate::common::NEXT_INPUTS_LANES;
+    use crate::keccak_arith::KeccakFArith;
+    use crate::reference::keccak256;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        pairing::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+
+    #[derive(Debug, Clone)]
+    struct MyConfig<F> {
+        table: FromBase9TableConfig<F>,
+        squeeze: SqueezeConfig<F>,
+        state: [Column<Advice>; 25],
+    }
+
+    #[derive(Default)]
+    struct MyCircuit<F> {
+        in_state: [F; 25],
+        digest_lanes: [F; DIGEST_LANES],
+        // Expected unpacked bytes, little and big endian
+        digest_bytes: Option<(Vec<u8>, Vec<u8>)>,
+        digest: Option<[u8; 32]>,
+    }
+
+    impl<F: Field> Circuit<F> for MyCircuit<F> {
+        type Config = MyConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let state: [Column<Advice>; 25] = (0..25)
+                .map(|_| {
+                    let column = meta.advice_column();
+                    meta.enable_equality(column);
+                    column
+                })
+                .collect_vec()
+                .try_into()
+                .unwrap();
+            let table = FromBase9TableConfig::configure(meta);
+            let constant = meta.fixed_column();
+            let squeeze = SqueezeConfig::configure(
+                meta,
+                &table,
+                state,
+                constant,
+                NEXT_INPUTS_LANES,
+                ByteOrder::default(),
+            );
+            MyConfig {
+                table,
+                squeeze,
+                state,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            config.table.load(&mut layouter)?;
+            let in_state: [AssignedCell<F, F>; 25] = layouter.assign_region(
+                || "Witness input state",
+                |mut region| {
+                    let state: Result<Vec<AssignedCell<F, F>>, Error> = self
+                        .in_state
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, val)| {
+                            region.assign_advice(
+                                || "witness input state",
+                                config.state[idx],
+                                0,
+                                || Ok(*val),
+                            )
+                        })
+                        .collect();
+                    Ok(state?.try_into().unwrap())
+                },
+            )?;
+            let (digest, digest_value) = config.squeeze.assign_digest(&mut layouter, &in_state)?;
+            if let (Some(value), Some(expected)) = (digest_value, self.digest) {
+                assert_eq!(value, expected);
+            }
+            if let (Some(bytes), Some((le_bytes, be_bytes))) = (
+                config.squeeze.lanes_to_bytes(&digest),
+                self.digest_bytes.as_ref(),
+            ) {
+                assert_eq!(&bytes, le_bytes);
+                let mut squeeze = config.squeeze.clone();
+                squeeze.byte_order = ByteOrder::BigEndian;
+                assert_eq!(&squeeze.lanes_to_bytes(&digest).unwrap(), be_bytes);
+            }
+            layouter.assign_region(
+                || "Expected digest",
+                |mut region| {
+                    for (idx, (lane, expected)) in
+                        digest.iter().zip(self.digest_lanes.iter()).enumerate()
+                    {
+                        let expected = region.assign_advice(
+                            || "expected digest lane",
+                            config.state[idx],
+                            0,
+                            || Ok(*expected),
+                        )?;
+                        region.constrain_equal(lane.cell(), expected.cell())?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_squeeze_digest() {
+        // Single block "abc" message, padded as in `plain::Keccak::update`
+        let input = b"abc";
+        let mut block = [0u8; 8 * NEXT_INPUTS_LANES];
+        block[..input.len()].copy_from_slice(input);
+        block[input.len()] = 0x01;
+        block[8 * NEXT_INPUTS_LANES - 1] |= 0x80;
+
+        let mut state = StateBigInt::default();
+        for (i, word) in block.chunks(8).enumerate() {
+            let word = u64::from_le_bytes(word.try_into().unwrap());
+            state[(i % 5, i / 5)] = convert_b2_to_b13(word);
+        }
+        KeccakFArith::permute_and_absorb(&mut state, None);
+        let in_state: [Fp; 25] = state_bigint_to_field(state);
+
+        let digest = keccak256(input);
+        let digest_lanes: [Fp; DIGEST_LANES] = digest
+            .chunks(8)
+            .map(|bytes| Fp::from(u64::from_le_bytes(bytes.try_into().unwrap())))
+            .collect_vec()
+            .try_into()
+            .unwrap();
+
+        // The first lane of the digest is 4e03657aea45a94f
+        let mut be_bytes = vec![0x4f, 0xa9, 0x45, 0xea, 0x7a, 0x65, 0x03, 0x4e];
+        be_bytes.extend(digest[8..].chunks(8).flat_map(|lane| lane.iter().rev()));
+        let circuit = MyCircuit::<Fp> {
+            in_state,
+            digest_lanes,
+            digest_bytes: Some((digest.to_vec(), be_bytes)),
+            digest: Some(digest),
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let mut wrong_lanes = digest_lanes;
+        wrong_lanes[3] += Fp::one();
+        let circuit = MyCircuit::<Fp> {
+            in_state,
+            digest_lanes: wrong_lanes,
+            digest_bytes: None,
+            digest: None,
+        };
+        let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_assign_digest() {
+        for input in [vec![], b"abc".to_vec(), vec![0x5a; 100], vec![0xff; 135]] {
+            // All single block inputs
+            let mut blocks = PaddedBlocks::new(input.iter().copied());
+            let block = blocks.next().unwrap();
+            assert!(blocks.next().is_none());
+
+            let mut state = StateBigInt::default();
+            for (i, &word) in block.iter().enumerate() {
+                state[(i % 5, i / 5)] = convert_b2_to_b13(word);
+            }
+            KeccakFArith::permute_and_absorb(&mut state, None);
+            let digest = keccak256(&input);
+            let digest_lanes: [Fp; DIGEST_LANES] = digest
+                .chunks(8)
+                .map(|bytes| Fp::from(u64::from_le_bytes(bytes.try_into().unwrap())))
+                .collect_vec()
+                .try_into()
+                .unwrap();
+            let circuit = MyCircuit::<Fp> {
+                in_state: state_bigint_to_field(state),
+                digest_lanes,
+                digest_bytes: None,
+                digest: Some(digest),
+            };
+            let prover = MockProver::<Fp>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
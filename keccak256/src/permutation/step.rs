@@ -0,0 +1,58 @@
+use crate::arith_helpers::{split_state_cells, state_bigint_to_field, state_to_biguint};
+use crate::keccak_arith::KeccakFArith;
+use crate::permutation::{rho::RhoConfig, theta::ThetaConfig, xi::XiConfig};
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter},
+    plonk::Error,
+};
+
+/// A step of a Keccak-f round that takes the 25 lanes of the state and
+/// assigns the 25 lanes of its output.
+///
+/// The steps compute their output witness from the values of the input cells,
+/// so a driver can run any sequence of them on a state. The layouter is a
+/// parameter of the trait rather than of the method, which keeps the trait
+/// usable as `dyn StateStep<F, L>`.
+pub trait StateStep<F: Field, L: Layouter<F>> {
+    fn assign_step(
+        &self,
+        layouter: &mut L,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error>;
+}
+
+/// Theta, from base 13 to base 13.
+impl<F: Field, L: Layouter<F>> StateStep<F, L> for ThetaConfig<F> {
+    fn assign_step(
+        &self,
+        layouter: &mut L,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let out_state = KeccakFArith::theta(&state_to_biguint(split_state_cells(state.clone())));
+        self.assign_state(layouter, state, state_bigint_to_field(out_state))
+    }
+}
+
+/// Rho and Pi, from base 13 to base 9, see [`RhoConfig::assign_rho_pi`].
+impl<F: Field, L: Layouter<F>> StateStep<F, L> for RhoConfig<F> {
+    fn assign_step(
+        &self,
+        layouter: &mut L,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        self.assign_rho_pi(layouter, state)
+    }
+}
+
+/// Xi, from base 9 to base 9.
+impl<F: Field, L: Layouter<F>> StateStep<F, L> for XiConfig<F> {
+    fn assign_step(
+        &self,
+        layouter: &mut L,
+        state: &[AssignedCell<F, F>; 25],
+    ) -> Result<[AssignedCell<F, F>; 25], Error> {
+        let out_state = KeccakFArith::xi(&state_to_biguint(split_state_cells(state.clone())));
+        self.assign_state(layouter, state, state_bigint_to_field(out_state))
+    }
+}
@@ -1,7 +1,8 @@
 use crate::arith_helpers::{convert_b13_coef, convert_b9_coef, f_from_radix_be, B13, B2, B9};
-use crate::common::LANE_SIZE;
+use crate::common::{BASE_NUM_OF_CHUNKS, LANE_SIZE};
+use crate::error::KeccakError;
 use crate::gate_helpers::f_to_biguint;
-use crate::permutation::rho_helpers::{get_overflow_detector, BASE_NUM_OF_CHUNKS};
+use crate::permutation::rho_helpers::{get_overflow_detector, OVERFLOW_TRANSFORM};
 use eth_types::Field;
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Table},
@@ -9,15 +10,20 @@ use halo2_proofs::{
     poly::Rotation,
 };
 use itertools::Itertools;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
 use std::marker::PhantomData;
+use std::path::Path;
 use strum_macros::{Display, EnumIter};
 
 use super::rho_helpers::{STEP2_RANGE, STEP3_RANGE};
 
-const MAX_CHUNKS: usize = 64;
+pub(crate) const MAX_CHUNKS: usize = 64;
 const NUM_OF_BINARY_CHUNKS: usize = 16;
-const NUM_OF_B9_CHUNKS: usize = 5;
+pub(crate) const NUM_OF_B9_CHUNKS: usize = 5;
 
 #[derive(EnumIter, Display, Clone, Copy)]
 enum TableTags {
@@ -25,6 +31,7 @@ enum TableTags {
     Range169,
     SpecialChunk,
     BooleanFlag,
+    SpecialChunkSum,
 }
 
 #[derive(Debug, Clone)]
@@ -46,6 +53,8 @@ impl<F: Field> StackableTable<F> {
         let col1 = (adv_cols[1], table_cols[1]);
         let col2 = (adv_cols[2], table_cols[2]);
         let q_enable = meta.complex_selector();
+        // The inputs have degree 2, the selector times the advice, so the
+        // lookup argument needs degree `2 + 2 + 1 = 5`.
         meta.lookup("stackable lookup", |meta| {
             let q_enable = meta.query_selector(q_enable);
             let tag_adv = meta.query_advice(tag.0, Rotation::cur());
@@ -101,24 +110,58 @@ impl<F: Field> StackableTable<F> {
     /// The table describes all possible combinations of these two variables:
     /// - The last input accumulator: `high_value`*(13**64) + `low_value`, and
     /// - The last output coef: `convert_b13_coef(high_value + low_value)`
-    fn load_special_chunks(&self, table: &mut Table<F>, offset: usize) -> Result<usize, Error> {
+    ///
+    /// The rows are given as in [`TableContents::special_chunks`].
+    fn load_special_chunks(
+        &self,
+        table: &mut Table<F>,
+        offset: usize,
+        rows: &[[u8; 3]],
+    ) -> Result<usize, Error> {
         let mut offset = offset;
-        for i in 0..B13 {
-            for j in 0..(B13 - i) {
-                let (low, high) = (i, j);
-                let last_chunk = F::from(low as u64)
-                    + F::from(high as u64) * F::from(B13 as u64).pow(&[LANE_SIZE as u64, 0, 0, 0]);
-                let output_coef = F::from(convert_b13_coef(low + high) as u64);
-                table.assign_cell(
-                    || "tag special chunks",
-                    self.tag.1,
-                    offset,
-                    || Ok(F::from(TableTags::SpecialChunk as u64)),
-                )?;
-                table.assign_cell(|| "last chunk", self.col1.1, offset, || Ok(last_chunk))?;
-                table.assign_cell(|| "output coef", self.col2.1, offset, || Ok(output_coef))?;
-                offset += 1;
-            }
+        for &[low, high, output_coef] in rows {
+            let last_chunk = F::from(low as u64)
+                + F::from(high as u64) * F::from(B13 as u64).pow(&[LANE_SIZE as u64, 0, 0, 0]);
+            let output_coef = F::from(output_coef as u64);
+            table.assign_cell(
+                || "tag special chunks",
+                self.tag.1,
+                offset,
+                || Ok(F::from(TableTags::SpecialChunk as u64)),
+            )?;
+            table.assign_cell(|| "last chunk", self.col1.1, offset, || Ok(last_chunk))?;
+            table.assign_cell(|| "output coef", self.col2.1, offset, || Ok(output_coef))?;
+            offset += 1;
+        }
+        Ok(offset)
+    }
+
+    /// Same as [`Self::load_special_chunks`], with the sum `high_value +
+    /// low_value` instead of its conversion, for a Rho that stays in base 13.
+    fn load_special_chunk_sums(
+        &self,
+        table: &mut Table<F>,
+        offset: usize,
+        rows: &[[u8; 3]],
+    ) -> Result<usize, Error> {
+        let mut offset = offset;
+        for &[low, high, _] in rows {
+            let last_chunk = F::from(low as u64)
+                + F::from(high as u64) * F::from(B13 as u64).pow(&[LANE_SIZE as u64, 0, 0, 0]);
+            table.assign_cell(
+                || "tag special chunk sums",
+                self.tag.1,
+                offset,
+                || Ok(F::from(TableTags::SpecialChunkSum as u64)),
+            )?;
+            table.assign_cell(|| "last chunk", self.col1.1, offset, || Ok(last_chunk))?;
+            table.assign_cell(
+                || "special chunk sum",
+                self.col2.1,
+                offset,
+                || Ok(F::from((low + high) as u64)),
+            )?;
+            offset += 1;
         }
         Ok(offset)
     }
@@ -139,6 +182,16 @@ impl<F: Field> StackableTable<F> {
         Ok(offset)
     }
     pub(crate) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.load_from(layouter, &special_chunk_rows())
+    }
+
+    /// Same as [`Self::load`], with the special chunk rows of a
+    /// [`TableContents`].
+    pub(crate) fn load_from(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        special_chunks: &[[u8; 3]],
+    ) -> Result<(), Error> {
         layouter.assign_table(
             || "stackable",
             |mut table| {
@@ -151,8 +204,9 @@ impl<F: Field> StackableTable<F> {
                 {
                     offset = self.load_range(&mut table, offset, tag, k)?;
                 }
-                offset = self.load_special_chunks(&mut table, offset)?;
-                self.load_boolean_flag(&mut table, offset)?;
+                offset = self.load_special_chunks(&mut table, offset, special_chunks)?;
+                offset = self.load_boolean_flag(&mut table, offset)?;
+                self.load_special_chunk_sums(&mut table, offset, special_chunks)?;
                 Ok(())
             },
         )
@@ -217,6 +271,27 @@ impl<F: Field> StackableTable<F> {
             },
         )
     }
+    /// Checks that `sum` is the sum of the special chunks in `last_chunk`, see
+    /// [`Self::load_special_chunk_sums`].
+    pub(crate) fn lookup_special_chunk_sum(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        last_chunk: &AssignedCell<F, F>,
+        sum: &AssignedCell<F, F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "lookup for special chunk sum",
+            |mut region| {
+                let offset = 0;
+                let tag = F::from(TableTags::SpecialChunkSum as u64);
+                self.q_enable.enable(&mut region, offset)?;
+                region.assign_advice_from_constant(|| "tag", self.tag.0, offset, tag)?;
+                last_chunk.copy_advice(|| "last chunk", &mut region, self.col1.0, offset)?;
+                sum.copy_advice(|| "special chunk sum", &mut region, self.col2.0, offset)?;
+                Ok(())
+            },
+        )
+    }
     /// Output two boolean cells. Prover can choose to enable one and disable
     /// another, but not both.
     pub(crate) fn assign_boolean_flag(
@@ -239,13 +314,21 @@ impl<F: Field> StackableTable<F> {
                     || "left",
                     self.col1.0,
                     offset,
-                    || is_left.map(|flag| F::from(flag)).ok_or(Error::Synthesis),
+                    || {
+                        is_left
+                            .map(|flag| F::from(flag))
+                            .ok_or_else(|| KeccakError::MissingWitness.into())
+                    },
                 )?;
                 let right = region.assign_advice(
                     || "right",
                     self.col2.0,
                     offset,
-                    || is_left.map(|flag| F::from(!flag)).ok_or(Error::Synthesis),
+                    || {
+                        is_left
+                            .map(|flag| F::from(!flag))
+                            .ok_or_else(|| KeccakError::MissingWitness.into())
+                    },
                 )?;
                 Ok((left, right))
             },
@@ -282,6 +365,120 @@ impl<F: Field, const K: u64> RangeCheckConfig<F, K> {
     }
 }
 
+/// Returns the `(base 13, base 9, overflow detector)` rows of the
+/// [`Base13toBase9TableConfig`], for every base 13 value of
+/// `BASE_NUM_OF_CHUNKS` chunks.
+pub fn base13_to_base9_rows() -> Vec<[u32; 3]> {
+    (0..BASE_NUM_OF_CHUNKS)
+        .map(|_| 0..B13)
+        .multi_cartesian_product()
+        .map(|b13_chunks| {
+            let b9_chunks = b13_chunks
+                .iter()
+                .map(|&x| convert_b13_coef(x))
+                .collect_vec();
+            let from_radix_be = |chunks: &[u8], base: u8| {
+                chunks
+                    .iter()
+                    .fold(0u32, |acc, &x| acc * base as u32 + x as u32)
+            };
+            [
+                from_radix_be(&b13_chunks, B13),
+                from_radix_be(&b9_chunks, B9),
+                get_overflow_detector(b13_chunks.try_into().unwrap()),
+            ]
+        })
+        .collect()
+}
+
+/// Checks a row of the [`Base13toBase9TableConfig`] against the rules of Rho.
+///
+/// Each base 9 chunk is the parity of its base 13 chunk, which is the XOR of
+/// the bits Theta summed up. The overflow detector is the
+/// [`OVERFLOW_TRANSFORM`] of the number of chunks, leading zeros (in big
+/// endian) left out.
+fn is_valid_base13_to_base9_row(&[base13, base9, overflow_detector]: &[u32; 3]) -> bool {
+    let mut b13_chunks = BigUint::from(base13).to_radix_le(B13.into());
+    let mut b9_chunks = BigUint::from(base9).to_radix_le(B9.into());
+    if b13_chunks.len() > BASE_NUM_OF_CHUNKS as usize || b9_chunks.len() > b13_chunks.len() {
+        return false;
+    }
+    b9_chunks.resize(BASE_NUM_OF_CHUNKS as usize, 0);
+    let non_zero_chunks = b13_chunks
+        .iter()
+        .rposition(|&x| x != 0)
+        .map_or(0, |i| i + 1);
+    b13_chunks.resize(BASE_NUM_OF_CHUNKS as usize, 0);
+    b13_chunks
+        .iter()
+        .zip(b9_chunks.iter())
+        .all(|(&b13, &b9)| b9 == b13 & 1)
+        && overflow_detector == OVERFLOW_TRANSFORM[non_zero_chunks]
+}
+
+/// Returns the `(low value, high value, output coef)` rows of the special
+/// chunks in the [`StackableTable`].
+pub fn special_chunk_rows() -> Vec<[u8; 3]> {
+    (0..B13)
+        .flat_map(|low| (0..(B13 - low)).map(move |high| [low, high, convert_b13_coef(low + high)]))
+        .collect()
+}
+
+/// The contents of the Rho lookup tables.
+///
+/// They only depend on constants, so they can be computed once, stored with
+/// [`dump_table`] and loaded back with [`load_table`] instead of being computed
+/// again at every synthesis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableContents {
+    /// The rows of [`base13_to_base9_rows`]
+    pub base13_to_base9: Vec<[u32; 3]>,
+    /// The rows of [`special_chunk_rows`]
+    pub special_chunks: Vec<[u8; 3]>,
+}
+
+impl TableContents {
+    pub fn compute() -> Self {
+        Self {
+            base13_to_base9: base13_to_base9_rows(),
+            special_chunks: special_chunk_rows(),
+        }
+    }
+}
+
+/// Writes the freshly computed [`TableContents`] to `path` in bincode.
+pub fn dump_table(path: impl AsRef<Path>) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(file, &TableContents::compute())
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+}
+
+/// Reads the [`TableContents`] written by [`dump_table`].
+///
+/// The contents are checked against freshly computed ones, a stale or
+/// tampered file fails with [`io::ErrorKind::InvalidData`].
+pub fn load_table(path: impl AsRef<Path>) -> io::Result<TableContents> {
+    let file = BufReader::new(File::open(path)?);
+    let contents: TableContents = bincode::deserialize_from(file)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if contents != TableContents::compute() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stale table contents",
+        ));
+    }
+    Ok(contents)
+}
+
+/// The `13**4` rows of the Rho conversion of 4 chunks.
+///
+/// The whole table is loaded whatever the input. This version of halo2 has no
+/// dynamic tables, and the fixed columns are committed in the verifying key,
+/// so a table holding only the rows an input needs would tie the keys to that
+/// input. A smaller static table, with the slices of
+/// [`slice_lane_with_max_step`](super::rho_helpers::slice_lane_with_max_step)
+/// and `13**3` rows, doesn't lower `k` either: a single permutation already
+/// takes more rows than the largest table.
 #[derive(Debug, Clone)]
 pub struct Base13toBase9TableConfig<F> {
     pub base13: TableColumn,
@@ -291,45 +488,42 @@ pub struct Base13toBase9TableConfig<F> {
 }
 
 impl<F: Field> Base13toBase9TableConfig<F> {
+    /// Returns the `(base 13, base 9, overflow detector)` rows the table is
+    /// loaded with, to audit the table outside the circuit.
+    pub fn rows() -> Vec<(u64, u64, u64)> {
+        base13_to_base9_rows()
+            .into_iter()
+            .map(|[base13, base9, od]| (base13.into(), base9.into(), od.into()))
+            .collect()
+    }
+
     pub(crate) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.load_from(layouter, &base13_to_base9_rows())
+    }
+
+    /// Same as [`Self::load`], with the rows of a [`TableContents`].
+    pub(crate) fn load_from(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        rows: &[[u32; 3]],
+    ) -> Result<(), Error> {
+        debug_assert!(rows.iter().all(is_valid_base13_to_base9_row));
         layouter.assign_table(
             || "13 -> 9",
             |mut table| {
-                // Iterate over all possible 13-ary values of size 4
-                for (i, b13_chunks) in (0..BASE_NUM_OF_CHUNKS)
-                    .map(|_| 0..B13)
-                    .multi_cartesian_product()
-                    .enumerate()
-                {
+                for (i, &[base13, base9, overflow_detector]) in rows.iter().enumerate() {
                     table.assign_cell(
                         || "base 13",
                         self.base13,
                         i,
-                        || Ok(f_from_radix_be::<F>(&b13_chunks, B13)),
-                    )?;
-
-                    table.assign_cell(
-                        || "base 9",
-                        self.base9,
-                        i,
-                        || {
-                            let converted_chunks: Vec<u8> = b13_chunks
-                                .iter()
-                                .map(|&x| convert_b13_coef(x))
-                                .collect_vec();
-                            Ok(f_from_radix_be::<F>(&converted_chunks, B9))
-                        },
+                        || Ok(F::from(base13 as u64)),
                     )?;
+                    table.assign_cell(|| "base 9", self.base9, i, || Ok(F::from(base9 as u64)))?;
                     table.assign_cell(
                         || "overflow_detector",
                         self.overflow_detector,
                         i,
-                        || {
-                            Ok(F::from(
-                                get_overflow_detector(b13_chunks.clone().try_into().unwrap())
-                                    as u64,
-                            ))
-                        },
+                        || Ok(F::from(overflow_detector as u64)),
                     )?;
                 }
                 Ok(())
@@ -546,3 +740,121 @@ impl<F: Field> FromBase9TableConfig<F> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::pairing::bn256::Fr as Fp;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_table_column_reuse() {
+        // The tables only take `TableColumn`s, which can't hold other fixed
+        // data, and halo2 rejects a table column assigned by a second table
+        use halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Circuit, ConstraintSystem},
+        };
+
+        #[derive(Default)]
+        struct ReuseCircuit<const TWICE: bool>;
+
+        impl<const TWICE: bool> Circuit<Fp> for ReuseCircuit<TWICE> {
+            type Config = RangeCheckConfig<Fp, 7>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                RangeCheckConfig::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                config.load(&mut layouter)?;
+                if TWICE {
+                    config.load(&mut layouter)?;
+                }
+                Ok(())
+            }
+        }
+
+        let prover = MockProver::<Fp>::run(4, &ReuseCircuit::<false>, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        assert!(MockProver::<Fp>::run(4, &ReuseCircuit::<true>, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_table_contents() {
+        let contents = TableContents::compute();
+        assert_eq!(
+            contents.base13_to_base9.len(),
+            (B13 as usize).pow(BASE_NUM_OF_CHUNKS)
+        );
+        // 0 <= low + high < 13
+        assert_eq!(contents.special_chunks.len(), 91);
+        // 13**3 + 2 is the chunks [1, 0, 0, 2], which converts to [1, 0, 0, 0]
+        assert_eq!(contents.base13_to_base9[2199], [2199, 729, 170]);
+        assert_eq!(contents.special_chunks[1], [0, 1, 1]);
+    }
+
+    #[test]
+    fn test_base13_to_base9_rows() {
+        let rows = Base13toBase9TableConfig::<Fp>::rows();
+        assert_eq!(rows.len(), (B13 as usize).pow(BASE_NUM_OF_CHUNKS));
+        for (i, &(base13, base9, od)) in rows.iter().enumerate() {
+            assert_eq!(base13, i as u64);
+            // Big endian chunks, as the slices are looked up
+            let chunks = (0..BASE_NUM_OF_CHUNKS)
+                .rev()
+                .map(|i| (base13 / (B13 as u64).pow(i) % B13 as u64) as u8)
+                .collect_vec();
+            let parity = chunks
+                .iter()
+                .fold(0u64, |acc, &x| acc * B9 as u64 + (x % 2) as u64);
+            assert_eq!(base9, parity);
+            let used_chunks = chunks.iter().skip_while(|&&x| x == 0).count();
+            assert_eq!(od, OVERFLOW_TRANSFORM[used_chunks] as u64);
+            assert!(is_valid_base13_to_base9_row(&[
+                base13 as u32,
+                base9 as u32,
+                od as u32
+            ]));
+        }
+        // A wrong parity or overflow detector is caught
+        assert!(!is_valid_base13_to_base9_row(&[2199, 730, 170]));
+        assert!(!is_valid_base13_to_base9_row(&[2199, 729, 13]));
+        assert!(!is_valid_base13_to_base9_row(&[13u32.pow(4), 0, 170]));
+    }
+
+    #[test]
+    fn test_table_round_trip() {
+        let path = std::env::temp_dir().join("keccak256_table_round_trip.bin");
+        dump_table(&path).unwrap();
+        let loaded = load_table(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, TableContents::compute());
+
+        // Anything else is rejected
+        let path = std::env::temp_dir().join("keccak256_table_garbage.bin");
+        std::fs::write(&path, [0xffu8; 16]).unwrap();
+        assert!(load_table(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        // So are well formed contents with a tampered row
+        let path = std::env::temp_dir().join("keccak256_table_tampered.bin");
+        let mut contents = TableContents::compute();
+        contents.base13_to_base9[1][1] += 1;
+        let file = BufWriter::new(File::create(&path).unwrap());
+        bincode::serialize_into(file, &contents).unwrap();
+        let err = load_table(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
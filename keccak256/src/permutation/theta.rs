@@ -22,6 +22,7 @@ impl<F: Field> ThetaConfig<F> {
         meta: &mut ConstraintSystem<F>,
         state: [Column<Advice>; 25],
     ) -> ThetaConfig<F> {
+        // Degree 2: the selector times a linear combination of the state.
         meta.create_gate("theta", |meta| {
             let q_enable = meta.query_selector(q_enable);
             let column_sum: Vec<Expression<F>> = (0..5)
@@ -102,6 +103,7 @@ mod tests {
     use crate::common::*;
     use crate::gate_helpers::biguint_to_f;
     use crate::keccak_arith::*;
+    use crate::plain::KeccakF;
     use eth_types::Field;
     use halo2_proofs::pairing::bn256::Fr as Fp;
     use halo2_proofs::{
@@ -220,5 +222,35 @@ mod tests {
 
         let prover = MockProver::<Fp>::run(9, &circuit2, vec![]).unwrap();
         assert!(prover.verify().is_err());
+
+        // A random state, also checked against the plain Keccak theta
+        let mut input2: State = [[0; 5]; 5];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            input2[x][y] = rand::random();
+        }
+        let expected = KeccakF::theta(input2);
+        let mut in_biguint = StateBigInt::default();
+        let mut in_state: [Fp; 25] = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_biguint[(x, y)] = convert_b2_to_b13(input2[x][y]);
+            in_state[5 * x + y] = biguint_to_f(&in_biguint[(x, y)]);
+        }
+        let s1_arith = KeccakFArith::theta(&in_biguint);
+        let mut out_state: [Fp; 25] = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            assert_eq!(
+                convert_b9_lane_to_b2_normal(convert_b13_lane_to_b9(s1_arith[(x, y)].clone(), 0)),
+                expected[x][y]
+            );
+            out_state[5 * x + y] = biguint_to_f(&s1_arith[(x, y)]);
+        }
+
+        let circuit = MyCircuit::<Fp> {
+            in_state,
+            out_state,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
     }
 }
@@ -22,6 +22,7 @@ impl<F: Field> XiConfig<F> {
         meta: &mut ConstraintSystem<F>,
         state: [Column<Advice>; 25],
     ) -> XiConfig<F> {
+        // Degree 2: the selector times a linear combination of the state.
         meta.create_gate("xi", |meta| {
             //  state in base 9, coefficient in 0~1
             //  def xi(state: List[List[int]]):
@@ -105,6 +106,7 @@ mod tests {
     use crate::common::*;
     use crate::gate_helpers::biguint_to_f;
     use crate::keccak_arith::*;
+    use crate::plain::KeccakF;
     use halo2_proofs::circuit::Layouter;
     use halo2_proofs::pairing::bn256::Fr as Fp;
     use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error};
@@ -207,5 +209,35 @@ mod tests {
         let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
 
         assert_eq!(prover.verify(), Ok(()));
+
+        // A random state, also checked against the plain Keccak chi. Each
+        // output chunk is `2*a + b + 3*c <= 6`, so it never overflows base 9.
+        let mut input2: State = [[0; 5]; 5];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            input2[x][y] = rand::random();
+        }
+        let expected = KeccakF::xi(input2);
+        let mut in_biguint = StateBigInt::default();
+        let mut in_state: [Fp; 25] = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            in_biguint[(x, y)] = convert_b2_to_b9(input2[x][y]);
+            in_state[5 * x + y] = biguint_to_f(&in_biguint[(x, y)]);
+        }
+        let s1_arith = KeccakFArith::xi(&in_biguint);
+        let mut out_state: [Fp; 25] = [Fp::zero(); 25];
+        for (x, y) in (0..5).cartesian_product(0..5) {
+            assert_eq!(
+                convert_b9_lane_to_b2(s1_arith[(x, y)].clone()),
+                expected[x][y]
+            );
+            out_state[5 * x + y] = biguint_to_f(&s1_arith[(x, y)]);
+        }
+        let circuit = MyCircuit::<Fp> {
+            in_state,
+            out_state,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::<Fp>::run(9, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
     }
 }
@@ -4,6 +4,8 @@ use itertools::Itertools;
 pub struct Keccak {
     state: State,
     sponge: Sponge,
+    // First padding byte, which carries the domain separation bits
+    domain: u8,
 }
 
 impl Default for Keccak {
@@ -14,19 +16,38 @@ impl Default for Keccak {
             state: [[0; 5]; 5],
             // rate & capacity in bytes
             sponge: Sponge::new(security_level.0 / 8, security_level.1 / 8),
+            domain: 0x01,
         }
     }
 }
 
 impl Keccak {
+    /// NIST SHA3-256, which only differs from Keccak-256 in the domain
+    /// separation bits of the padding
+    pub fn sha3_256() -> Self {
+        Self {
+            domain: 0x06,
+            ..Self::default()
+        }
+    }
+
+    /// Keccak-256 with the sponge starting from `state` instead of zeros, as
+    /// in keyed and duplex constructions
+    pub fn with_state(state: State) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
     pub fn update(&mut self, input: &[u8]) {
         let padding_total = self.sponge.rate - (input.len() % self.sponge.rate);
         let mut padding: Vec<u8>;
 
         if padding_total == 1 {
-            padding = vec![0x81];
+            padding = vec![self.domain | 0x80];
         } else {
-            padding = vec![0x01];
+            padding = vec![self.domain];
             padding.resize(padding_total - 1, 0x00);
             padding.push(0x80);
         }
@@ -46,7 +67,12 @@ pub struct KeccakF {}
 
 impl KeccakF {
     pub fn permutations(&self, a: &mut State) {
-        for rc in ROUND_CONSTANTS.iter().take(PERMUTATION) {
+        self.reduced_permutations(a, PERMUTATION)
+    }
+
+    /// Runs only the first `num_rounds` rounds of the permutation.
+    pub fn reduced_permutations(&self, a: &mut State, num_rounds: usize) {
+        for rc in ROUND_CONSTANTS.iter().take(num_rounds) {
             *a = KeccakF::round_b(*a, *rc);
         }
     }
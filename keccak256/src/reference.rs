@@ -0,0 +1,236 @@
+//! Keccak reference used as the oracle for the circuit witnesses.
+//!
+//! The functions here wrap the [`plain`](crate::plain) implementation with
+//! the flat lane layout of the Keccak spec, where lane `(x, y)` sits at index
+//! `x + 5 * y`.
+
+use crate::arith_helpers::PaddedBlocks;
+use crate::common::{State, ROTATION_CONSTANTS};
+use crate::plain::{Keccak, KeccakF};
+
+/// Applies the 24 rounds of Keccak-f\[1600\] to `state`.
+pub fn keccak_f1600(state: [u64; 25]) -> [u64; 25] {
+    let mut a: State = [[0; 5]; 5];
+    for (i, lane) in state.iter().enumerate() {
+        a[i % 5][i / 5] = *lane;
+    }
+    KeccakF::default().permutations(&mut a);
+
+    let mut out = [0; 25];
+    for (i, lane) in out.iter_mut().enumerate() {
+        *lane = a[i % 5][i / 5];
+    }
+    out
+}
+
+/// Applies the Rho step alone to `state`, rotating lane `(x, y)` by
+/// `ROTATION_CONSTANTS[x][y]`, to check the Rho circuit apart from the other
+/// steps.
+pub fn reference_rho(state: [u64; 25]) -> [u64; 25] {
+    let mut out = state;
+    for (i, lane) in out.iter_mut().enumerate() {
+        *lane = lane.rotate_left(ROTATION_CONSTANTS[i % 5][i / 5]);
+    }
+    out
+}
+
+/// Returns the Keccak-256 digest of `input`.
+pub fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut keccak = Keccak::default();
+    keccak.update(input);
+    keccak
+        .digest()
+        .try_into()
+        .expect("Keccak-256 digest is 32 bytes")
+}
+
+/// Returns the state after each permutation of the Keccak-256 sponge
+/// absorbing `input`, one per padded block. The digest is in the first 4 lanes
+/// of the last state.
+pub fn keccak256_states(input: &[u8]) -> Vec<[u64; 25]> {
+    let mut state = [0; 25];
+    PaddedBlocks::new(input.iter().copied())
+        .map(|block| {
+            for (lane, word) in state.iter_mut().zip(block.iter()) {
+                *lane ^= word;
+            }
+            state = keccak_f1600(state);
+            state
+        })
+        .collect()
+}
+
+/// Returns the Keccak-256 digest of `input` absorbed into `state` instead of
+/// the zero state.
+pub fn keccak256_with_state(state: [u64; 25], input: &[u8]) -> [u8; 32] {
+    let mut a: State = [[0; 5]; 5];
+    for (i, lane) in state.iter().enumerate() {
+        a[i % 5][i / 5] = *lane;
+    }
+    let mut keccak = Keccak::with_state(a);
+    keccak.update(input);
+    keccak
+        .digest()
+        .try_into()
+        .expect("Keccak-256 digest is 32 bytes")
+}
+
+/// Returns the NIST SHA3-256 digest of `input`.
+pub fn sha3_256(input: &[u8]) -> [u8; 32] {
+    let mut sha3 = Keccak::sha3_256();
+    sha3.update(input);
+    sha3.digest()
+        .try_into()
+        .expect("SHA3-256 digest is 32 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use pretty_assertions::assert_eq;
+
+    fn from_hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_keccak_f1600_zero_state() {
+        let expected = [
+            0xF1258F7940E1DDE7,
+            0x84D5CCF933C0478A,
+            0xD598261EA65AA9EE,
+            0xBD1547306F80494D,
+            0x8B284E056253D057,
+            0xFF97A42D7F8E6FD4,
+            0x90FEE5A0A44647C4,
+            0x8C5BDA0CD6192E76,
+            0xAD30A6F71B19059C,
+            0x30935AB7D08FFC64,
+            0xEB5AA93F2317D635,
+            0xA9A6E6260D712103,
+            0x81A57C16DBCF555F,
+            0x43B831CD0347C826,
+            0x01F22F1A11A5569F,
+            0x05E5635A21D9AE61,
+            0x64BEFEF28CC970F2,
+            0x613670957BC46611,
+            0xB87C5A554FD00ECB,
+            0x8C3EE88A1CCF32C8,
+            0x940C7922AE3A2614,
+            0x1841F924A2C509E4,
+            0x16F53526E70465C2,
+            0x75F644E97F30A13B,
+            0xEAF1FF7B5CECA249,
+        ];
+        assert_eq!(keccak_f1600([0; 25]), expected);
+    }
+
+    #[test]
+    fn test_reference_rho() {
+        // Bit 0 of every lane lands on the rotation offset of the lane
+        let rotated = reference_rho([1; 25]);
+        assert_eq!(rotated[0], 1);
+        assert_eq!(rotated[1], 1 << 1);
+        assert_eq!(rotated[5], 1 << 36);
+        assert_eq!(rotated[24], 1 << 14);
+
+        // Same as the Rho of the plain implementation
+        let state: [u64; 25] = rand::random();
+        let mut a: State = [[0; 5]; 5];
+        for (i, lane) in state.iter().enumerate() {
+            a[i % 5][i / 5] = *lane;
+        }
+        let a = KeccakF::rho(a);
+        let rotated = reference_rho(state);
+        for (i, lane) in rotated.iter().enumerate() {
+            assert_eq!(*lane, a[i % 5][i / 5]);
+        }
+    }
+
+    #[test]
+    fn test_keccak256_vectors() {
+        let vectors: [(&[u8], &str); 5] = [
+            (
+                b"",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+            ),
+            (
+                b"abc",
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45",
+            ),
+            (
+                b"The quick brown fox jumps over the lazy dog",
+                "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15",
+            ),
+            // Spans three blocks
+            (
+                b"Alice was beginning to get very tired of sitting by her sister on the bank, \
+                and of having nothing to do: once or twice she had peeped into the book her \
+                sister was reading, but it had no pictures or conversations in it, and what is \
+                the use of a book, thought Alice without pictures or conversations?",
+                "3ce38e088f876c550dfebe3a1e6a99c2bc06d031106696786482e0b1406235fc",
+            ),
+            (
+                &[0xa3; 200],
+                "3a57666b048777f2c953dc4456f45a2588e1cb6f2da760122d530ac2ce607d4a",
+            ),
+        ];
+        for (input, digest) in vectors {
+            assert_eq!(keccak256(input).to_vec(), from_hex(digest));
+        }
+    }
+
+    #[test]
+    fn test_keccak256_states() {
+        for input in [vec![], b"abc".to_vec(), vec![0xa3; 200]] {
+            let states = keccak256_states(&input);
+            assert_eq!(states.len(), input.len() / 136 + 1);
+            let digest = states.last().unwrap()[..4]
+                .iter()
+                .flat_map(|lane| lane.to_le_bytes())
+                .collect_vec();
+            assert_eq!(digest, keccak256(&input).to_vec());
+        }
+    }
+
+    #[test]
+    fn test_keccak256_with_state() {
+        assert_eq!(keccak256_with_state([0; 25], b"abc"), keccak256(b"abc"));
+
+        // The padded empty input is a single block, xored into the state
+        let state: [u64; 25] = rand::random();
+        let mut block = state;
+        block[0] ^= 0x01;
+        block[16] ^= 0x80 << 56;
+        let digest = keccak_f1600(block)[..4]
+            .iter()
+            .flat_map(|lane| lane.to_le_bytes())
+            .collect_vec();
+        assert_eq!(keccak256_with_state(state, b"").to_vec(), digest);
+    }
+
+    #[test]
+    fn test_sha3_256_vectors() {
+        let vectors: [(&[u8], &str); 3] = [
+            (
+                b"",
+                "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            ),
+            (
+                b"abc",
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                &[0xa3; 200],
+                "79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787",
+            ),
+        ];
+        for (input, digest) in vectors {
+            assert_eq!(sha3_256(input).to_vec(), from_hex(digest));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! The string encodings of NIST SP 800-185, to absorb the prefixes of cSHAKE
+//! and KMAC in front of a message.
+//!
+//! A prefix is bytepadded to the rate, so it always fills whole blocks and the
+//! message starts on a fresh one. The blocks of [`prefix_blocks`] go through
+//! [`crate::permutation::absorb::AbsorbConfig`] like any other block, see
+//! `AbsorbConfig::assign_prefix`, with the rate of the cSHAKE variant: 21
+//! lanes for cSHAKE128, 17 for cSHAKE256. Every rate here is in lanes.
+
+/// `left_encode(x)`: the bytes of `x` in big endian, preceded by their number.
+pub fn left_encode(x: u64) -> Vec<u8> {
+    let bytes = x.to_be_bytes();
+    // Zero still takes a byte
+    let skip = (x.leading_zeros() / 8).min(7) as usize;
+    let mut encoded = vec![(8 - skip) as u8];
+    encoded.extend_from_slice(&bytes[skip..]);
+    encoded
+}
+
+/// `right_encode(x)`: the bytes of `x` in big endian, followed by their
+/// number.
+pub fn right_encode(x: u64) -> Vec<u8> {
+    let mut encoded = left_encode(x);
+    encoded.rotate_left(1);
+    encoded
+}
+
+/// `encode_string(s)`: `s` preceded by its length in bits.
+pub fn encode_string(s: &[u8]) -> Vec<u8> {
+    let mut encoded = left_encode(8 * s.len() as u64);
+    encoded.extend_from_slice(s);
+    encoded
+}
+
+/// `bytepad(x, w)`: `x` preceded by `left_encode(w)` and followed by zeros up
+/// to a multiple of `w` bytes. Nothing is added when it's already aligned.
+pub fn bytepad(x: &[u8], w: usize) -> Vec<u8> {
+    assert!(w > 0, "bytepad needs a positive width");
+    let mut padded = left_encode(w as u64);
+    padded.extend_from_slice(x);
+    let len = padded.len();
+    padded.resize(len + (w - len % w) % w, 0);
+    padded
+}
+
+/// The bytes cSHAKE absorbs before the message for the `function_name` and
+/// `customization` strings, with a rate of `rate` lanes.
+///
+/// Empty when both strings are, cSHAKE is then SHAKE.
+pub fn cshake_prefix(rate: usize, function_name: &[u8], customization: &[u8]) -> Vec<u8> {
+    if function_name.is_empty() && customization.is_empty() {
+        return vec![];
+    }
+    let mut names = encode_string(function_name);
+    names.extend(encode_string(customization));
+    bytepad(&names, 8 * rate)
+}
+
+/// The bytes KMAC absorbs before the message: the cSHAKE prefix for `"KMAC"`
+/// and `customization`, then the padded `key`, with a rate of `rate` lanes.
+///
+/// The message itself is followed by `right_encode` of the output length in
+/// bits, which is left to the caller.
+pub fn kmac_prefix(rate: usize, key: &[u8], customization: &[u8]) -> Vec<u8> {
+    let mut prefix = cshake_prefix(rate, b"KMAC", customization);
+    prefix.extend(bytepad(&encode_string(key), 8 * rate));
+    prefix
+}
+
+/// Splits `prefix` in blocks of `rate` lanes, each lane packed in little
+/// endian as in the spec.
+///
+/// Panics if the prefix doesn't fill whole blocks, as a bytepadded one does.
+pub fn prefix_blocks(prefix: &[u8], rate: usize) -> Vec<Vec<u64>> {
+    assert!(
+        prefix.len() % (8 * rate) == 0,
+        "the prefix has to be bytepadded to the rate"
+    );
+    prefix
+        .chunks(8 * rate)
+        .map(|block| {
+            block
+                .chunks(8)
+                .map(|lane| u64::from_le_bytes(lane.try_into().unwrap()))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_encodings() {
+        assert_eq!(left_encode(0), vec![0x01, 0x00]);
+        assert_eq!(left_encode(168), vec![0x01, 0xa8]);
+        assert_eq!(left_encode(256), vec![0x02, 0x01, 0x00]);
+        assert_eq!(right_encode(256), vec![0x01, 0x00, 0x02]);
+        assert_eq!(encode_string(b""), vec![0x01, 0x00]);
+        assert_eq!(
+            encode_string(b"KMAC"),
+            vec![0x01, 0x20, b'K', b'M', b'A', b'C']
+        );
+    }
+
+    #[test]
+    fn test_bytepad_alignment() {
+        assert_eq!(bytepad(&[], 8), vec![0x01, 0x08, 0, 0, 0, 0, 0, 0]);
+        // `left_encode(8)` and 6 bytes fill the width, no zero block follows
+        assert_eq!(bytepad(&[0xff; 6], 8).len(), 8);
+        assert_eq!(bytepad(&[0xff; 7], 8).len(), 16);
+        assert_eq!(cshake_prefix(21, b"", b""), vec![]);
+    }
+
+    #[test]
+    fn test_kmac128_prefix() {
+        // KMAC128 sample #1 of the NIST examples, the rate is 168 bytes
+        const RATE: usize = 21;
+        let key: Vec<u8> = (0x40..0x60).collect();
+        let prefix = kmac_prefix(RATE, &key, b"");
+        let blocks = prefix_blocks(&prefix, RATE);
+        assert_eq!(blocks.len(), 2);
+
+        // bytepad(encode_string("KMAC") || encode_string(""), 168)
+        let mut first = vec![0u64; RATE];
+        first[0] = u64::from_le_bytes([0x01, 0xa8, 0x01, 0x20, b'K', b'M', b'A', b'C']);
+        first[1] = u64::from_le_bytes([0x01, 0x00, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(blocks[0], first);
+
+        // bytepad(encode_string(key), 168): 01 a8 02 01 00 40 41 ... 5f
+        let mut bytes = vec![0x01, 0xa8, 0x02, 0x01, 0x00];
+        bytes.extend_from_slice(&key);
+        bytes.resize(8 * RATE, 0);
+        assert_eq!(blocks[1], prefix_blocks(&bytes, RATE)[0]);
+        assert_eq!(
+            blocks[1][4],
+            u64::from_le_bytes([0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the prefix has to be bytepadded to the rate")]
+    fn test_unaligned_prefix() {
+        prefix_blocks(&encode_string(b"KMAC"), 21);
+    }
+}
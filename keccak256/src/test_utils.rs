@@ -0,0 +1,183 @@
+//! Test circuits shared by the tests of the permutation steps.
+use crate::arith_helpers::{convert_b2_to_b13, convert_b2_to_b9, B13, B9};
+use crate::common::{LaneIndex, BASE_NUM_OF_CHUNKS, ROTATION_CONSTANTS};
+use crate::gate_helpers::biguint_to_f;
+use crate::permutation::{
+    generic::GenericConfig,
+    rho_checks::LaneRotateConversionConfig,
+    rho_helpers::{get_overflow_detector, slice_lane, RhoWitness},
+    tables::{Base13toBase9TableConfig, StackableTable},
+};
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    pairing::bn256::Fr as Fp,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, TableColumn},
+};
+use itertools::Itertools;
+use num_bigint::BigUint;
+use pretty_assertions::assert_eq;
+use std::convert::TryInto;
+
+/// Runs lanes through [`LaneRotateConversionConfig`] and constrains each
+/// output lane to the expected one.
+///
+/// Lanes given as `(lane, input lane, expected output lane)`
+#[derive(Default)]
+pub(crate) struct LaneCircuit<F> {
+    pub(crate) lanes: Vec<(LaneIndex, F, F)>,
+}
+
+#[derive(Clone)]
+pub(crate) struct LaneCircuitConfig<F> {
+    pub(crate) lane_config: LaneRotateConversionConfig<F>,
+    pub(crate) table: Base13toBase9TableConfig<F>,
+    pub(crate) stackable: StackableTable<F>,
+    pub(crate) advices: [Column<Advice>; 3],
+    pub(crate) copied: Column<Advice>,
+}
+
+impl<F: Field> LaneCircuitConfig<F> {
+    /// Loads the base 13 to base 9 table and the stackable table.
+    pub(crate) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.table.load(layouter)?;
+        self.stackable.load(layouter)
+    }
+
+    /// Assigns `lane` in a region of its own, as the input of a conversion.
+    pub(crate) fn assign_input_lane(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        lane: F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        layouter.assign_region(
+            || "input lane",
+            |mut region| region.assign_advice(|| "lane", self.advices[0], 0, || Ok(lane)),
+        )
+    }
+}
+
+impl<F: Field> Circuit<F> for LaneCircuit<F> {
+    type Config = LaneCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advices: [Column<Advice>; 3] = (0..3)
+            .map(|_| {
+                let column = meta.advice_column();
+                meta.enable_equality(column);
+                column
+            })
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let copied = meta.advice_column();
+        meta.enable_equality(copied);
+        let fixed = meta.fixed_column();
+        let table_cols: [TableColumn; 3] = (0..3)
+            .map(|_| meta.lookup_table_column())
+            .collect_vec()
+            .try_into()
+            .unwrap();
+        let stackable = StackableTable::configure(meta, advices, table_cols);
+        let generic = GenericConfig::configure(meta, advices, fixed);
+        let table = Base13toBase9TableConfig::configure(meta);
+        let lane_config = LaneRotateConversionConfig::configure(
+            meta,
+            &table,
+            advices,
+            fixed,
+            generic,
+            stackable.clone(),
+        );
+        LaneCircuitConfig {
+            lane_config,
+            table,
+            stackable,
+            advices,
+            copied,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        for &(index, lane, expected) in self.lanes.iter() {
+            let lane = config.assign_input_lane(&mut layouter, lane)?;
+            let out_lane = config
+                .lane_config
+                .assign_lane(&mut layouter, lane, index)?
+                .output_lane;
+            layouter.assign_region(
+                || "copy output lane",
+                |mut region| {
+                    let copied =
+                        out_lane.copy_advice(|| "copied lane", &mut region, config.copied, 0)?;
+                    let expected = region.assign_advice(
+                        || "expected lane",
+                        config.copied,
+                        1,
+                        || Ok(expected),
+                    )?;
+                    region.constrain_equal(copied.cell(), expected.cell())
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the conversion of the binary `lane` at `(x, y)` in the circuit.
+///
+/// The expected output is the reference rho rotation of the lane in base 9.
+/// It is also compared against [`LaneRotateConversionConfig::compute`], then
+/// the lane goes through a [`LaneCircuit`] which has to be satisfied.
+pub(crate) fn test_lane_conversion(lane: u64, x: usize, y: usize) {
+    let index = LaneIndex::new(x, y).unwrap();
+    let input = convert_b2_to_b13(lane);
+    let expected = convert_b2_to_b9(lane.rotate_left(ROTATION_CONSTANTS[x][y]));
+    assert_eq!(
+        LaneRotateConversionConfig::<Fp>::compute(input.clone(), index),
+        expected
+    );
+
+    let circuit = LaneCircuit::<Fp> {
+        lanes: vec![(index, biguint_to_f(&input), biguint_to_f(&expected))],
+    };
+    let prover = MockProver::<Fp>::run(15, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+}
+
+/// Lets the step 2 slice of lane 5 right before its rotation offset overflow
+/// into the next chunk, without changing the input lane.
+///
+/// Lane 5 rotates by 1, so its chunks 61 and 62 are a step 2 slice and chunk
+/// 63 a step 1 slice. The step 2 slice takes chunk 63 too, which pushes the
+/// output chunk past the top of the lane. Chunk 63 of the lane has to be 1.
+pub(crate) fn craft_step2_overflow(witness: &mut RhoWitness) {
+    let slices = slice_lane(ROTATION_CONSTANTS[1][0]);
+    let idx = slices.iter().position(|&s| s == (61, 2)).unwrap();
+    assert_eq!(slices[idx + 1], (63, 1));
+    let (conversions, _) = witness;
+    assert_eq!(conversions[idx + 1].input.coef, BigUint::from(1u8));
+    conversions[idx + 1].input.coef = BigUint::from(0u8);
+    conversions[idx + 1].input.pre_acc -= BigUint::from(B13).pow(63);
+    conversions[idx + 1].output.coef = BigUint::from(0u8);
+    let overflowed = &mut conversions[idx];
+    overflowed.input.coef += BigUint::from(B13).pow(2);
+    overflowed.output.coef += BigUint::from(B9).pow(2);
+    let mut chunks_be = overflowed.input.coef.to_radix_be(B13.into());
+    while chunks_be.len() < BASE_NUM_OF_CHUNKS as usize {
+        chunks_be.insert(0, 0);
+    }
+    overflowed.overflow_detector.value = get_overflow_detector(chunks_be.try_into().unwrap());
+    assert_eq!(overflowed.overflow_detector.value, 13);
+}
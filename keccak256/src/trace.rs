@@ -0,0 +1,321 @@
+//! Records the witness of the permutation configs as JSON, to diff it against
+//! an independent computation.
+//!
+//! Only built with the `trace` feature, the configs don't record anything
+//! otherwise. Cells are recorded per thread when they get a value, so the
+//! layout passes of the floor planner and key generation record nothing.
+//!
+//! With the `checked-assign` feature, a circuit can wrap its layouter in a
+//! [`CheckedLayouter`]: every advice cell any config assigns through it is
+//! checked against an oracle set with [`set_oracle`], so a wrong witness panics
+//! at the cell that went wrong instead of failing the whole circuit later.
+use crate::gate_helpers::f_to_biguint;
+use eth_types::Field;
+use halo2_proofs::circuit::AssignedCell;
+#[cfg(feature = "checked-assign")]
+use halo2_proofs::{
+    circuit::{layouter::RegionLayouter, Cell, Layouter, Region, Table},
+    plonk::{Advice, Assigned, Column, Error, Fixed, Instance, Selector},
+};
+#[cfg(feature = "checked-assign")]
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+/// An assigned cell, with the value in decimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracedCell {
+    pub region: String,
+    pub column: String,
+    pub offset: usize,
+    pub value: String,
+}
+
+/// Returns the expected value of the cell at `(region, column, offset)`, or
+/// `None` if it has no opinion on it.
+#[cfg(feature = "checked-assign")]
+type Oracle = Box<dyn Fn(&str, &str, usize) -> Option<BigUint>>;
+
+thread_local! {
+    static TRACE: RefCell<Vec<TracedCell>> = RefCell::new(vec![]);
+    #[cfg(feature = "checked-assign")]
+    static ORACLE: RefCell<Option<Oracle>> = RefCell::new(None);
+}
+
+/// Checks the cells assigned on this thread against `oracle` from now on.
+///
+/// `oracle` gets the region, the column and the offset of a cell, and returns
+/// the value it expects there. An assigned cell with another value panics.
+#[cfg(feature = "checked-assign")]
+pub fn set_oracle(oracle: impl Fn(&str, &str, usize) -> Option<BigUint> + 'static) {
+    ORACLE.with(|cell| *cell.borrow_mut() = Some(Box::new(oracle)));
+}
+
+/// Stops checking the cells assigned on this thread.
+#[cfg(feature = "checked-assign")]
+pub fn clear_oracle() {
+    ORACLE.with(|cell| cell.borrow_mut().take());
+}
+
+#[cfg(feature = "checked-assign")]
+fn check(region: &str, column: &str, offset: usize, value: &BigUint) {
+    ORACLE.with(|oracle| {
+        if let Some(expected) = oracle
+            .borrow()
+            .as_ref()
+            .and_then(|oracle| oracle(region, column, offset))
+        {
+            assert!(
+                &expected == value,
+                "{} at offset {} of {} is {}, the oracle expects {}",
+                column,
+                offset,
+                region,
+                value,
+                expected
+            );
+        }
+    });
+}
+
+/// Records `cell` at `offset` of a region. The names are the annotations the
+/// region and the cell were assigned with, so the trace reads like the layout.
+pub(crate) fn record<F: Field, R: Into<String>, C: Into<String>>(
+    region: impl Fn() -> R,
+    column: impl Fn() -> C,
+    offset: usize,
+    cell: &AssignedCell<F, F>,
+) {
+    if let Some(value) = cell.value() {
+        let value = f_to_biguint(*value);
+        let (region, column) = (region().into(), column().into());
+        TRACE.with(|trace| {
+            trace.borrow_mut().push(TracedCell {
+                region,
+                column,
+                offset,
+                value: value.to_string(),
+            })
+        });
+    }
+}
+
+/// Returns the cells recorded on this thread as a JSON array and clears them.
+pub fn dump_witness() -> String {
+    TRACE.with(|trace| serde_json::to_string(&trace.take()).unwrap())
+}
+
+/// Wraps a layouter to check every advice cell assigned through it against
+/// the oracle, see [`set_oracle`]. The region is named by its annotation and
+/// the column by the annotation of the cell.
+#[cfg(feature = "checked-assign")]
+#[derive(Debug)]
+pub struct CheckedLayouter<L>(pub L);
+
+#[cfg(feature = "checked-assign")]
+impl<F: Field, L: Layouter<F>> Layouter<F> for CheckedLayouter<L> {
+    type Root = Self;
+
+    fn assign_region<A, AR, N, NR>(&mut self, name: N, mut assignment: A) -> Result<AR, Error>
+    where
+        A: FnMut(Region<'_, F>) -> Result<AR, Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        let name: String = name().into();
+        self.0.assign_region(
+            || name.clone(),
+            |region| {
+                let mut checked = CheckedRegion {
+                    region,
+                    name: &name,
+                };
+                assignment(Region::from(&mut checked as &mut dyn RegionLayouter<F>))
+            },
+        )
+    }
+
+    fn assign_table<A, N, NR>(&mut self, name: N, assignment: A) -> Result<(), Error>
+    where
+        A: FnMut(Table<'_, F>) -> Result<(), Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.0.assign_table(name, assignment)
+    }
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        self.0.constrain_instance(cell, column, row)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.0.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.0.pop_namespace(gadget_name)
+    }
+}
+
+/// A region of a [`CheckedLayouter`], checking its advice cells once they are
+/// assigned.
+#[cfg(feature = "checked-assign")]
+struct CheckedRegion<'a, 'r, F: Field> {
+    region: Region<'r, F>,
+    name: &'a str,
+}
+
+#[cfg(feature = "checked-assign")]
+impl<F: Field> CheckedRegion<'_, '_, F> {
+    fn check_cell(&self, annotation: &dyn Fn() -> String, offset: usize, value: Option<F>) {
+        if let Some(value) = value {
+            check(self.name, &annotation(), offset, &f_to_biguint(value));
+        }
+    }
+}
+
+#[cfg(feature = "checked-assign")]
+impl<F: Field> RegionLayouter<F> for CheckedRegion<'_, '_, F> {
+    fn enable_selector<'v>(
+        &'v mut self,
+        _annotation: &'v (dyn Fn() -> String + 'v),
+        selector: &Selector,
+        offset: usize,
+    ) -> Result<(), Error> {
+        selector.enable(&mut self.region, offset)
+    }
+
+    fn assign_advice<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Advice>,
+        offset: usize,
+        to: &'v mut (dyn FnMut() -> Result<Assigned<F>, Error> + 'v),
+    ) -> Result<Cell, Error> {
+        let cell = self.region.assign_advice(annotation, column, offset, to)?;
+        self.check_cell(annotation, offset, cell.value().map(Assigned::evaluate));
+        Ok(cell.cell())
+    }
+
+    fn assign_advice_from_constant<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Advice>,
+        offset: usize,
+        constant: Assigned<F>,
+    ) -> Result<Cell, Error> {
+        let cell = self
+            .region
+            .assign_advice_from_constant(annotation, column, offset, constant)?;
+        self.check_cell(annotation, offset, cell.value().map(Assigned::evaluate));
+        Ok(cell.cell())
+    }
+
+    fn assign_advice_from_instance<'v>(
+        &mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        instance: Column<Instance>,
+        row: usize,
+        advice: Column<Advice>,
+        offset: usize,
+    ) -> Result<(Cell, Option<F>), Error> {
+        let cell = self
+            .region
+            .assign_advice_from_instance(annotation, instance, row, advice, offset)?;
+        let value = cell.value().cloned();
+        self.check_cell(annotation, offset, value);
+        Ok((cell.cell(), value))
+    }
+
+    fn assign_fixed<'v>(
+        &'v mut self,
+        annotation: &'v (dyn Fn() -> String + 'v),
+        column: Column<Fixed>,
+        offset: usize,
+        to: &'v mut (dyn FnMut() -> Result<Assigned<F>, Error> + 'v),
+    ) -> Result<Cell, Error> {
+        Ok(self
+            .region
+            .assign_fixed(annotation, column, offset, to)?
+            .cell())
+    }
+
+    fn constrain_constant(&mut self, cell: Cell, constant: Assigned<F>) -> Result<(), Error> {
+        self.region.constrain_constant(cell, constant)
+    }
+
+    fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error> {
+        self.region.constrain_equal(left, right)
+    }
+}
+
+#[cfg(all(test, feature = "checked-assign"))]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        pairing::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use std::marker::PhantomData;
+
+    #[test]
+    #[should_panic(expected = "x at offset 1 of summands is 3, the oracle expects 4")]
+    fn test_checked_assign() {
+        #[derive(Default)]
+        struct MyCircuit<F> {
+            _marker: PhantomData<F>,
+        }
+
+        impl<F: Field> Circuit<F> for MyCircuit<F> {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                Self::default()
+            }
+
+            fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+                meta.advice_column()
+            }
+
+            fn synthesize(
+                &self,
+                column: Self::Config,
+                layouter: impl Layouter<F>,
+            ) -> Result<(), Error> {
+                // None of these cells is recorded in the trace, they are still
+                // checked
+                CheckedLayouter(layouter).assign_region(
+                    || "summands",
+                    |mut region| {
+                        for (offset, x) in [2, 3, 4].into_iter().enumerate() {
+                            region.assign_advice(|| "x", column, offset, || Ok(F::from(x)))?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // Agrees with the summands but at offset 1
+        set_oracle(|region, column, offset| {
+            (region == "summands" && column == "x").then(|| BigUint::from([2u64, 4, 4][offset]))
+        });
+        MockProver::<Fp>::run(4, &MyCircuit::default(), vec![]).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+//! Witness generation for browser based provers, built with the `wasm`
+//! feature.
+//!
+//! [`keccak_witness`] runs the reference computation only, nothing is proven,
+//! so a web app can check its input against the expected digest before
+//! sending it to a prover. The lanes are hex strings, JS numbers can't hold a
+//! `u64`.
+//!
+//! The crate is built as a library, build the wasm module with
+//!
+//! ```text
+//! cargo rustc -p keccak256 --lib --release --target wasm32-unknown-unknown \
+//!     --features wasm -- --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/keccak256.wasm
+//! ```
+use crate::reference::keccak256_states;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// The expected values of a Keccak-256 run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeccakWitness {
+    /// The digest in hex
+    pub digest: String,
+    /// The state after each permutation, the lanes in hex in the spec order
+    /// `x + 5 * y`
+    pub states: Vec<Vec<String>>,
+}
+
+/// Computes the digest of `input` and the states leading to it.
+pub fn witness(input: &[u8]) -> KeccakWitness {
+    let states = keccak256_states(input);
+    let digest = states.last().unwrap()[..4]
+        .iter()
+        .flat_map(|lane| lane.to_le_bytes())
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    KeccakWitness {
+        digest,
+        states: states
+            .iter()
+            .map(|state| state.iter().map(|lane| format!("{:016x}", lane)).collect())
+            .collect(),
+    }
+}
+
+/// [`witness`] as a JS object `{ digest, states }`.
+#[wasm_bindgen]
+pub fn keccak_witness(input: &[u8]) -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&witness(input)).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_witness() {
+        let abc = witness(b"abc");
+        assert_eq!(
+            abc.digest,
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(abc.states.len(), 1);
+        assert_eq!(abc.states[0][0], "4fa945ea7a65034e");
+
+        // An input filling a block exactly gets another one for the padding
+        assert_eq!(witness(&[0; 136]).states.len(), 2);
+    }
+}